@@ -0,0 +1,184 @@
+//! Derive macro for the telemetry traits of `rootcause-opentelemetry`, enabled
+//! by its `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{DeriveInput, Ident, Lit, LitStr, parse_macro_input};
+
+const SEVERITIES: &[&str] = &[
+    "Trace", "Trace2", "Trace3", "Trace4", "Debug", "Debug2", "Debug3", "Debug4", "Info", "Info2",
+    "Info3", "Info4", "Warn", "Warn2", "Warn3", "Warn4", "Error", "Error2", "Error3", "Error4",
+    "Fatal", "Fatal2", "Fatal3", "Fatal4",
+];
+
+/// Implement `OtelErrorType`, and `OtelSeverity` if a severity is given, for a
+/// report context type, along with `RegisterOtelError` registering them.
+///
+/// Report contexts are type-erased, so the implementations only take effect
+/// once registered at runtime, e.g. at startup, with `register_otel_error::<T>()`.
+/// Reports of unregistered types are emitted as if the derive was not there.
+///
+/// ```
+/// use rootcause_opentelemetry::{OtelError, error_type::register_otel_error};
+///
+/// #[derive(Debug, OtelError)]
+/// #[otel(error_type = "db.timeout", severity = "warn")]
+/// #[otel(attribute(key = "db.system", value = "postgresql"))]
+/// struct QueryTimeout;
+///
+/// #[derive(Debug, OtelError)]
+/// #[otel(error_type = "payment.rejected")]
+/// enum PaymentError {
+///     Declined,
+///     Expired { months: u32 },
+/// }
+///
+/// register_otel_error::<QueryTimeout>();
+/// register_otel_error::<PaymentError>();
+/// ```
+///
+/// - `error_type` is the stable `error.type` of the type, and is required. All
+///   variants of an enum share it.
+/// - `severity` is the name of a log `Severity` variant, in any case. It needs
+///   the `logs` feature of `rootcause-opentelemetry`.
+/// - `attribute` adds a static attribute with a string, integer, float or
+///   boolean `value`, and can be repeated.
+///
+/// ```compile_fail
+/// use rootcause_opentelemetry::OtelError;
+///
+/// #[derive(Debug, OtelError)]
+/// #[otel(severity = "warn")]
+/// struct MissingErrorType;
+/// ```
+#[proc_macro_derive(OtelError, attributes(otel))]
+pub fn derive_otel_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = quote!(::rootcause_opentelemetry);
+    let otel = quote!(#krate::__private::opentelemetry);
+
+    let mut error_type: Option<LitStr> = None;
+    let mut severity: Option<Ident> = None;
+    let mut attributes = Vec::new();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("otel"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("error_type") {
+                error_type = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("severity") {
+                let name: LitStr = meta.value()?.parse()?;
+                let variant = SEVERITIES
+                    .iter()
+                    .find(|variant| variant.eq_ignore_ascii_case(&name.value()))
+                    .ok_or_else(|| {
+                        syn::Error::new(name.span(), "unknown severity, expected e.g. \"warn\"")
+                    })?;
+                severity = Some(Ident::new(variant, name.span()));
+                Ok(())
+            } else if meta.path.is_ident("attribute") {
+                let mut key: Option<LitStr> = None;
+                let mut value: Option<proc_macro2::TokenStream> = None;
+                meta.parse_nested_meta(|inner| {
+                    if inner.path.is_ident("key") {
+                        key = Some(inner.value()?.parse()?);
+                        Ok(())
+                    } else if inner.path.is_ident("value") {
+                        value = Some(attribute_value(inner.value()?.parse()?)?);
+                        Ok(())
+                    } else {
+                        Err(inner.error("expected `key` or `value`"))
+                    }
+                })?;
+                match (key, value) {
+                    (Some(key), Some(value)) => {
+                        attributes.push(quote! {
+                            #otel::KeyValue::new(#key, #value)
+                        });
+                        Ok(())
+                    }
+                    _ => Err(meta.error("expected `attribute(key = \"...\", value = ...)`")),
+                }
+            } else {
+                Err(meta.error("expected `error_type`, `severity` or `attribute`"))
+            }
+        })?;
+    }
+
+    let error_type = error_type.ok_or_else(|| {
+        syn::Error::new(
+            Span::call_site(),
+            "missing `#[otel(error_type = \"...\")]` attribute",
+        )
+    })?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (severity_impl, severity_registration) = match severity {
+        Some(variant) => (
+            quote! {
+                impl #impl_generics #krate::severity::OtelSeverity for #name #ty_generics #where_clause {
+                    fn otel_severity(&self) -> #otel::logs::Severity {
+                        #otel::logs::Severity::#variant
+                    }
+                }
+            },
+            quote!(#krate::severity::register_severity::<Self>();),
+        ),
+        None => (quote!(), quote!()),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #krate::error_type::OtelErrorType for #name #ty_generics #where_clause {
+            fn otel_error_type(&self) -> ::std::borrow::Cow<'static, str> {
+                ::std::borrow::Cow::Borrowed(#error_type)
+            }
+
+            fn otel_attributes(&self) -> ::std::vec::Vec<#otel::KeyValue> {
+                ::std::vec![#(#attributes),*]
+            }
+        }
+
+        #severity_impl
+
+        impl #impl_generics #krate::error_type::RegisterOtelError for #name #ty_generics #where_clause {
+            fn register() {
+                #krate::error_type::register_error_type::<Self>();
+                #severity_registration
+            }
+        }
+    })
+}
+
+/// The attribute value of a literal, with integers and floats typed as the
+/// `i64` and `f64` attribute values are.
+fn attribute_value(lit: Lit) -> syn::Result<proc_macro2::TokenStream> {
+    match lit {
+        Lit::Str(value) => Ok(quote!(#value)),
+        Lit::Bool(value) => Ok(quote!(#value)),
+        Lit::Int(value) => {
+            let value: i64 = value.base10_parse()?;
+            Ok(quote!(#value))
+        }
+        Lit::Float(value) => {
+            let value: f64 = value.base10_parse()?;
+            Ok(quote!(#value))
+        }
+        other => Err(syn::Error::new(
+            other.span(),
+            "expected a string, integer, float or boolean",
+        )),
+    }
+}
@@ -32,25 +32,24 @@ use rootcause::{
 use rootcause_backtrace::{Backtrace, BacktraceCollector};
 use rootcause_opentelemetry::{
     attachments::{HideTraceAttachments, OpenTelemetryMetadataCollector},
+    hooks::install_recommended_hooks,
     log_event::LoggerExt,
+    setup::TelemetrySetup,
     span_event::SpanRefReportExt,
 };
-use tokio;
 
 #[tokio::main]
 async fn main() -> Result<(), Report> {
-    Hooks::new()
-        .report_creation_hook(BacktraceCollector::new_from_env())
-        .report_creation_hook(OpenTelemetryMetadataCollector::new())
-        .attachment_formatter(HideTraceAttachments)
-        .install()
-        .expect("Failed to install rootcause hooks");
+    install_recommended_hooks()?;
 
-    let (trace_system, logs_system) = setup_system();
+    let telemetry = TelemetrySetup::new("rootcause-opentelemetry")
+        .with_simple_span_exporter(opentelemetry_stdout::SpanExporter::default())
+        .with_simple_log_exporter(opentelemetry_stdout::LogExporter::default())
+        .install()?;
     let scope = InstrumentationScope::builder("otel-example").build();
 
-    let logger = logs_system.logger("otel-logger");
-    let tracer = trace_system.tracer_with_scope(scope.clone());
+    let logger = telemetry.logger_provider().logger("otel-logger");
+    let tracer = telemetry.tracer_provider().tracer_with_scope(scope.clone());
 
     tracer.in_span("outer-span", |c| {
         let rep = tracer
@@ -68,36 +67,5 @@ async fn main() -> Result<(), Report> {
         });
     });
 
-    logs_system.force_flush();
-    trace_system.force_flush();
-
-    [logs_system.shutdown(), trace_system.shutdown()]
-        .into_iter()
-        .collect_reports_vec::<SendSync>()
-        .context("Shutdown failed")
-        .map_err(|rep| eprintln!("{}", rep));
-
-    Ok(())
-}
-
-pub(crate) fn setup_system() -> (SdkTracerProvider, SdkLoggerProvider) {
-    let resource = Resource::builder()
-        .with_service_name("rootcause-opentelemetry")
-        .build();
-
-    let span_exporter = opentelemetry_stdout::SpanExporter::default();
-
-    let trace_provider = SdkTracerProvider::builder()
-        .with_resource(resource.clone())
-        .with_simple_exporter(span_exporter)
-        .build();
-
-    let log_exporter = opentelemetry_stdout::LogExporter::default();
-
-    let logger_provider = SdkLoggerProvider::builder()
-        .with_resource(resource.clone())
-        .with_simple_exporter(log_exporter)
-        .build();
-
-    (trace_provider, logger_provider)
+    telemetry.shutdown()
 }
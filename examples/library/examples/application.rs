@@ -0,0 +1,34 @@
+//! An application setting up the telemetry providers which the library of this
+//! crate reports its errors with.
+
+use opentelemetry::{global, logs::LoggerProvider};
+use opentelemetry_sdk::{logs::SdkLoggerProvider, trace::SdkTracerProvider};
+use rootcause::{Report, hooks::Hooks};
+use rootcause_opentelemetry::{
+    attachments::OpenTelemetryMetadataCollector, log_event::install_fallback_logger,
+};
+use rootcause_opentelemetry_library_example as storage;
+
+fn main() -> Result<(), Report> {
+    Hooks::new()
+        .report_creation_hook(OpenTelemetryMetadataCollector::new())
+        .install()
+        .expect("Failed to install rootcause hooks");
+
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_simple_exporter(opentelemetry_stdout::SpanExporter::default())
+        .build();
+    global::set_tracer_provider(tracer_provider.clone());
+
+    let logger_provider = SdkLoggerProvider::builder()
+        .with_simple_exporter(opentelemetry_stdout::LogExporter::default())
+        .build();
+    install_fallback_logger(logger_provider.logger("application"));
+
+    storage::warm_cache();
+    let _ = storage::load_or_default("theme");
+
+    let _ = tracer_provider.shutdown();
+    let _ = logger_provider.shutdown();
+    Ok(())
+}
@@ -0,0 +1,27 @@
+//! A library reporting its errors without choosing telemetry providers.
+//!
+//! It depends on `rootcause-opentelemetry` with `default-features = false` and
+//! on no `opentelemetry_sdk` at all, leaving provider setup to the application,
+//! as in the `application` example of this crate.
+
+use rootcause::{Report, report};
+use rootcause_opentelemetry::library;
+
+pub fn load(key: &str) -> Result<Vec<u8>, Report> {
+    Err(report!("no entry for key {key}"))
+}
+
+/// Warm the cache in the background, outside of any span of the caller.
+pub fn warm_cache() {
+    if let Err(rep) = load("config") {
+        library::report_in_span("storage", "warm_cache", &rep);
+    }
+}
+
+/// Load a key, reporting failures on the caller's span or logger.
+pub fn load_or_default(key: &str) -> Vec<u8> {
+    load(key).unwrap_or_else(|rep| {
+        library::report(&rep);
+        Vec::new()
+    })
+}
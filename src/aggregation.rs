@@ -0,0 +1,389 @@
+//! Summarizing emitted exceptions per time window, for services where an
+//! event per error would overwhelm the backend.
+//!
+//! An [`ErrorAggregator`] is an [`EmitLayer`] counting the exceptions passing
+//! through the pipeline by `error.type`, along with a sample trace for each.
+//! When a window has elapsed, the counts are handed to its sinks as an
+//! [`AggregationWindow`], e.g. as summary log records such as
+//! `37× db.timeout in last 60s, sample trace: 4bf92f3577b34da6a3ce929d0e0e4736`.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    ops::ControlFlow,
+    sync::{Arc, Mutex, MutexGuard, OnceLock, Weak},
+    thread::{self, Thread},
+    time::{Duration, SystemTime},
+};
+
+use opentelemetry::{
+    Context,
+    trace::{TraceContextExt, TraceId},
+};
+
+use crate::{
+    clock, correlation,
+    error_type::error_type_of,
+    pipeline::{Destination, EmitLayer, ExceptionSnapshot},
+    span_event::target_span,
+};
+
+#[cfg(feature = "logs")]
+pub const EXCEPTION_SUMMARY: &str = "exception.summary";
+#[cfg(feature = "logs")]
+pub const EXCEPTION_SUMMARY_COUNT: &str = "exception.summary.count";
+#[cfg(feature = "logs")]
+pub const EXCEPTION_SUMMARY_WINDOW: &str = "exception.summary.window";
+
+type Sink = Box<dyn Fn(&AggregationWindow) + Send + Sync>;
+
+/// The exceptions of one `error.type` emitted within an [`AggregationWindow`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorSummary {
+    pub error_type: Cow<'static, str>,
+    pub count: u64,
+    /// The trace of the first exception of the window that had one.
+    pub sample_trace_id: Option<TraceId>,
+}
+
+impl fmt::Display for ErrorSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}× {}", self.count, self.error_type)
+    }
+}
+
+/// The exceptions emitted between `start` and `end`, by `error.type` in order
+/// of first occurrence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregationWindow {
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub summaries: Vec<ErrorSummary>,
+}
+
+impl AggregationWindow {
+    /// The length of the window.
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+}
+
+/// [`EmitLayer`] counting exceptions by `error.type` and periodically handing
+/// the counts to its sinks.
+///
+/// A window is flushed by the first emission after it has elapsed, or else by
+/// a background thread started with the first counted exception, so that the
+/// last window before a quiet period is not held back. The current window is
+/// also flushed when the last clone of the aggregator is dropped. Only span
+/// events and log records are counted, as span attributes usually accompany an
+/// event for the same report.
+///
+/// The aggregator is a handle, so one clone can be installed with
+/// [`install_layer`](crate::pipeline::install_layer) and another kept for
+/// flushing, e.g. at shutdown.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rootcause_opentelemetry::{aggregation::ErrorAggregator, pipeline::install_layer};
+/// let aggregator = ErrorAggregator::new(Duration::from_secs(60))
+///     .suppress_events()
+///     .on_flush(|window| {
+///         for summary in &window.summaries {
+///             eprintln!("{summary} in last {}s", window.duration().as_secs());
+///         }
+///     });
+/// install_layer(aggregator.clone());
+/// // ...
+/// aggregator.flush();
+/// ```
+#[derive(Clone)]
+pub struct ErrorAggregator {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    window: Duration,
+    suppress: bool,
+    sinks: Vec<Sink>,
+    state: Mutex<State>,
+    /// The thread flushing quiet windows, once started, if it could be spawned.
+    timer: OnceLock<Option<Thread>>,
+}
+
+struct State {
+    start: SystemTime,
+    summaries: Vec<ErrorSummary>,
+    index: HashMap<Cow<'static, str>, usize>,
+}
+
+impl State {
+    fn new(start: SystemTime) -> Self {
+        Self {
+            start,
+            summaries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl ErrorAggregator {
+    /// Aggregate exceptions over windows of `window`, starting now.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                window,
+                suppress: false,
+                sinks: Vec::new(),
+                state: Mutex::new(State::new(clock::now())),
+                timer: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Abort the emission of the aggregated exceptions, so that only the
+    /// summaries reach the backend.
+    ///
+    /// Must be called before the aggregator is cloned.
+    pub fn suppress_events(mut self) -> Self {
+        self.inner_mut().suppress = true;
+        self
+    }
+
+    /// Call `sink` with each flushed window which counted any exceptions.
+    ///
+    /// Must be called before the aggregator is cloned.
+    pub fn on_flush(mut self, sink: impl Fn(&AggregationWindow) + Send + Sync + 'static) -> Self {
+        self.inner_mut().sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Emit each flushed window as one `exception.summary` log record per
+    /// `error.type` through `logger`.
+    ///
+    /// ## Attributes & Details
+    /// - The body reads e.g. `37× db.timeout in last 60s, sample trace: …`.
+    /// - `error.type` is the type of the summarized exceptions.
+    /// - `exception.summary.count` is the number of exceptions.
+    /// - `exception.summary.window` is the length of the window, in seconds.
+    /// - The trace context of the record is the sample trace, if any.
+    #[cfg(feature = "logs")]
+    pub fn log_to<L>(self, logger: L) -> Self
+    where
+        L: opentelemetry::logs::Logger + Send + Sync + 'static,
+    {
+        self.on_flush(move |window| emit_summaries(&logger, window))
+    }
+
+    /// Add each flushed window's counts to the `exceptions` counter of `meter`,
+    /// by `error.type`, as [`MeterExt`](crate::meter::MeterExt) would have.
+    #[cfg(feature = "metrics")]
+    pub fn count_on(self, meter: &opentelemetry::metrics::Meter) -> Self {
+        let counter = crate::meter::MeterExt::exception_counter(meter);
+        self.on_flush(move |window| {
+            for summary in &window.summaries {
+                counter.add_for_type(summary.error_type.clone(), summary.count);
+            }
+        })
+    }
+
+    /// End the current window now, handing it to the sinks unless it is empty.
+    pub fn flush(&self) {
+        let now = clock::now();
+        let window = {
+            let mut state = self.inner.lock();
+            take(&mut state, now)
+        };
+        self.inner.dispatch(window);
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("ErrorAggregator configured after being cloned")
+    }
+
+    /// Start the thread flushing quiet windows, unless it already runs.
+    ///
+    /// It only holds a [`Weak`] reference, so that it stops once the last
+    /// clone of the aggregator is dropped.
+    fn start_timer(&self) {
+        self.inner.timer.get_or_init(|| {
+            let inner = Arc::downgrade(&self.inner);
+            thread::Builder::new()
+                .name("rootcause-aggregation".into())
+                .spawn(move || run_timer(inner))
+                .ok()
+                .map(|handle| handle.thread().clone())
+        });
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    /// Flush the current window if it has elapsed, returning the time left
+    /// until the next one does.
+    fn flush_elapsed(&self) -> Duration {
+        let now = clock::now();
+        let (window, left) = {
+            let mut state = self.lock();
+            let age = now.duration_since(state.start).unwrap_or_default();
+            match self.window.checked_sub(age).filter(|left| !left.is_zero()) {
+                Some(left) => return left,
+                None => (take(&mut state, now), self.window),
+            }
+        };
+        self.dispatch(window);
+        left
+    }
+
+    fn dispatch(&self, window: AggregationWindow) {
+        if window.summaries.is_empty() {
+            return;
+        }
+        for sink in &self.sinks {
+            sink(&window);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let now = clock::now();
+        let window = take(
+            self.state
+                .get_mut()
+                .unwrap_or_else(|poison| poison.into_inner()),
+            now,
+        );
+        self.dispatch(window);
+        if let Some(Some(timer)) = self.timer.get() {
+            timer.unpark();
+        }
+    }
+}
+
+fn take(state: &mut State, now: SystemTime) -> AggregationWindow {
+    let state = std::mem::replace(state, State::new(now));
+    AggregationWindow {
+        start: state.start,
+        end: now,
+        summaries: state.summaries,
+    }
+}
+
+/// Flush the windows of `inner` as they elapse, until it is dropped.
+fn run_timer(inner: Weak<Inner>) {
+    let Some(mut wait) = inner.upgrade().map(|inner| inner.window) else {
+        return;
+    };
+    loop {
+        thread::park_timeout(wait);
+        // The aggregator is only kept alive while flushing, so that dropping
+        // its last clone stops the timer.
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        wait = inner.flush_elapsed();
+    }
+}
+
+impl fmt::Debug for ErrorAggregator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorAggregator")
+            .field("window", &self.inner.window)
+            .field("suppress", &self.inner.suppress)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmitLayer for ErrorAggregator {
+    fn on_emit(&self, snapshot: &mut ExceptionSnapshot<'_>) -> ControlFlow<()> {
+        if !matches!(
+            snapshot.destination,
+            Destination::SpanEvent | Destination::LogRecord
+        ) {
+            return ControlFlow::Continue(());
+        }
+
+        let rep = snapshot.report;
+        let error_type = error_type_of(rep);
+        let trace_id = target_span(rep)
+            .or_else(|| correlation::span_context(&rep))
+            .map(|span_context| span_context.trace_id())
+            .unwrap_or_else(|| Context::current().span().span_context().trace_id());
+        let trace_id = (trace_id != TraceId::INVALID).then_some(trace_id);
+
+        self.start_timer();
+        let now = clock::now();
+        let elapsed = {
+            let mut state = self.inner.lock();
+            let elapsed = now
+                .duration_since(state.start)
+                .is_ok_and(|age| age >= self.inner.window);
+            let elapsed = elapsed.then(|| take(&mut state, now));
+
+            let position = match state.index.get(&error_type) {
+                Some(&position) => position,
+                None => {
+                    let position = state.summaries.len();
+                    state.index.insert(error_type.clone(), position);
+                    state.summaries.push(ErrorSummary {
+                        error_type,
+                        count: 0,
+                        sample_trace_id: None,
+                    });
+                    position
+                }
+            };
+            let summary = &mut state.summaries[position];
+            summary.count += 1;
+            summary.sample_trace_id = summary.sample_trace_id.or(trace_id);
+            elapsed
+        };
+        // Sinks run without the lock, so that they can themselves emit.
+        if let Some(window) = elapsed {
+            self.inner.dispatch(window);
+        }
+
+        if self.inner.suppress {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+#[cfg(feature = "logs")]
+fn emit_summaries<L: opentelemetry::logs::Logger>(logger: &L, window: &AggregationWindow) {
+    use opentelemetry::{
+        logs::{LogRecord, Severity},
+        trace::{SpanId, TraceFlags},
+    };
+    use opentelemetry_semantic_conventions::attribute;
+
+    let seconds = window.duration().as_secs_f64();
+    for summary in &window.summaries {
+        let mut record = logger.create_log_record();
+        record.set_event_name(EXCEPTION_SUMMARY);
+        record.set_timestamp(window.end);
+        record.set_observed_timestamp(window.end);
+        record.set_severity_number(Severity::Warn);
+        record.set_severity_text(Severity::Warn.name());
+
+        let mut body = format!("{summary} in last {}s", seconds.round());
+        if let Some(trace_id) = summary.sample_trace_id {
+            body.push_str(&format!(", sample trace: {trace_id}"));
+            record.set_trace_context(trace_id, SpanId::INVALID, Some(TraceFlags::SAMPLED));
+        }
+        record.set_body(body.into());
+
+        record.add_attribute(attribute::ERROR_TYPE, summary.error_type.clone());
+        record.add_attribute(EXCEPTION_SUMMARY_COUNT, summary.count as i64);
+        record.add_attribute(EXCEPTION_SUMMARY_WINDOW, seconds);
+        logger.emit(record);
+    }
+}
@@ -1,39 +1,158 @@
 use core::fmt;
 use std::{
     fmt::{Debug, Write},
-    i32,
-    time::SystemTime,
+    time::{Instant, SystemTime},
 };
 
 use opentelemetry::{
-    Context,
+    Context, Key, KeyValue, Value,
     trace::{SpanContext, TraceContextExt},
 };
 use rootcause::{
-    ReportMut,
+    Report, ReportMut,
     handlers::{
         self, AttachmentFormattingPlacement, AttachmentFormattingStyle, AttachmentHandler,
         FormattingFunction,
     },
     hooks::{attachment_formatter::AttachmentFormatterHook, report_creation::ReportCreationHook},
-    markers::{self, Local, SendSync},
+    markers::{self, Local, ObjectMarkerFor, SendSync},
     report_attachment::ReportAttachmentRef,
 };
 
-#[derive(Debug, Default, Clone, Copy)]
+use crate::{clock, utilities::AttachmentsExt};
+
+/// Default cap on the number of [`SpanContext`] attachments kept across a
+/// single report tree, see [`OpenTelemetryMetadataCollector::max_span_contexts`].
+pub const DEFAULT_MAX_SPAN_CONTEXTS: usize = 32;
+
+/// Report creation hook attaching the [`SpanContext`] of the current span, the
+/// creation [`Instant`], and with `TIMESTAMPS` the creation [`SystemTime`], to
+/// new reports.
+///
+/// These attachments give the emitted events and records their timestamp and
+/// trace context, and let [`RecordErrorReport`](crate::span_event::RecordErrorReport)
+/// link to the spans child reports originated in.
+#[derive(Debug, Clone, Copy)]
 pub struct OpenTelemetryMetadataCollector<const TIMESTAMPS: bool = true> {
-    _priv: (),
+    max_span_contexts: usize,
+    span_contexts: bool,
 }
 
 impl OpenTelemetryMetadataCollector<true> {
+    /// Capture the span context and the creation timestamp.
     pub fn new() -> Self {
-        Self { _priv: () }
+        Self {
+            max_span_contexts: DEFAULT_MAX_SPAN_CONTEXTS,
+            span_contexts: true,
+        }
     }
 }
 
 impl OpenTelemetryMetadataCollector<false> {
+    /// Capture the span context and creation [`Instant`] only, leaving emissions
+    /// timestamped at emission time.
     pub fn no_timestamps() -> Self {
-        Self { _priv: () }
+        Self {
+            max_span_contexts: DEFAULT_MAX_SPAN_CONTEXTS,
+            span_contexts: true,
+        }
+    }
+}
+
+impl<const TIMESTAMPS: bool> Default for OpenTelemetryMetadataCollector<TIMESTAMPS> {
+    fn default() -> Self {
+        Self {
+            max_span_contexts: DEFAULT_MAX_SPAN_CONTEXTS,
+            span_contexts: true,
+        }
+    }
+}
+
+impl<const TIMESTAMPS: bool> OpenTelemetryMetadataCollector<TIMESTAMPS> {
+    /// Cap the number of [`SpanContext`] attachments kept across a report tree.
+    ///
+    /// Once a new report's children already carry `max` span contexts, the
+    /// new report gets an [`ElidedSpanContext`] marker instead, which is
+    /// counted towards the `exception.dropped_link_count` attribute when
+    /// linking spans.
+    ///
+    /// Defaults to [`DEFAULT_MAX_SPAN_CONTEXTS`].
+    pub fn max_span_contexts(mut self, max: usize) -> Self {
+        self.max_span_contexts = max;
+        self
+    }
+
+    /// Whether to capture the [`SpanContext`] of the current span.
+    ///
+    /// Enabled by default.
+    pub fn span_contexts(mut self, enabled: bool) -> Self {
+        self.span_contexts = enabled;
+        self
+    }
+
+    fn collect<T>(&self, mut report: ReportMut<'_, markers::Dynamic, T>)
+    where
+        SystemTime: ObjectMarkerFor<T>,
+        Instant: ObjectMarkerFor<T>,
+        SpanContext: ObjectMarkerFor<T>,
+        ElidedSpanContext: ObjectMarkerFor<T>,
+    {
+        // The monotonic creation instant gives `exception.report_age_ms` even
+        // without timestamps.
+        report = report.attach_custom::<Hidden, _>(clock::monotonic());
+        if TIMESTAMPS {
+            report = report.attach_custom::<OpenTelemetryMetadataCollector, _>(clock::now());
+        }
+        if !self.span_contexts {
+            return;
+        }
+        let ctx = Context::current();
+        let span = ctx.span();
+        let span_ctx = span.span_context();
+        if !span_ctx.is_valid() {
+            return;
+        }
+
+        let kept = report
+            .as_ref()
+            .iter_reports()
+            .filter(|r| r.find_attachment::<SpanContext>().is_some())
+            .take(self.max_span_contexts)
+            .count();
+
+        if kept < self.max_span_contexts {
+            let _ = report.attach_custom::<OpenTelemetryMetadataCollector, _>(span_ctx.clone());
+        } else {
+            let _ = report.attach_custom::<OpenTelemetryMetadataCollector, _>(ElidedSpanContext);
+        }
+    }
+}
+
+/// Marker attached in place of a [`SpanContext`] when the
+/// [`OpenTelemetryMetadataCollector`] cap has been reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElidedSpanContext;
+
+impl<const TIMESTAMPS: bool> AttachmentHandler<ElidedSpanContext>
+    for OpenTelemetryMetadataCollector<TIMESTAMPS>
+{
+    fn display(_value: &ElidedSpanContext, _formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
+
+    fn debug(_value: &ElidedSpanContext, _formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
+
+    fn preferred_formatting_style(
+        _value: &ElidedSpanContext,
+        report_formatting_function: FormattingFunction,
+    ) -> AttachmentFormattingStyle {
+        AttachmentFormattingStyle {
+            placement: AttachmentFormattingPlacement::Hidden,
+            function: report_formatting_function,
+            priority: i32::MIN,
+        }
     }
 }
 
@@ -107,34 +226,190 @@ impl<const TIMESTAMPS: bool> AttachmentHandler<SpanContext>
 }
 
 impl<const TIMESTAMPS: bool> ReportCreationHook for OpenTelemetryMetadataCollector<TIMESTAMPS> {
-    fn on_local_creation(&self, mut report: ReportMut<'_, markers::Dynamic, Local>) {
-        if TIMESTAMPS {
-            report = report.attach_custom::<OpenTelemetryMetadataCollector, _>(SystemTime::now());
+    fn on_local_creation(&self, report: ReportMut<'_, markers::Dynamic, Local>) {
+        self.collect(report);
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, markers::Dynamic, SendSync>) {
+        self.collect(report);
+    }
+}
+
+/// [`AttachmentHandler`] for [`KeyValue`] attachments added with
+/// [`AttributeReportExt::attach_attribute`], listing them in an `Attributes`
+/// appendix of the formatted report.
+#[derive(Debug, Clone, Copy)]
+pub struct OTelAttribute;
+
+impl AttachmentHandler<KeyValue> for OTelAttribute {
+    fn display(value: &KeyValue, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{} = {}", value.key, value.value)
+    }
+
+    fn debug(value: &KeyValue, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(value, formatter)
+    }
+
+    fn preferred_formatting_style(
+        _value: &KeyValue,
+        report_formatting_function: FormattingFunction,
+    ) -> AttachmentFormattingStyle {
+        AttachmentFormattingStyle {
+            placement: AttachmentFormattingPlacement::Appendix {
+                appendix_name: "Attributes",
+            },
+            function: report_formatting_function,
+            priority: 0,
         }
-        let ctx = Context::current();
-        let span = ctx.span();
-        let span_ctx = span.span_context();
-        if span_ctx.is_valid() {
-            let _ = report.attach_custom::<OpenTelemetryMetadataCollector, _>(span_ctx.clone());
+    }
+}
+
+/// Attributes attached as a group by [`AttributeReportExt::attach_attributes`],
+/// emitted as if attached one by one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeGroup(pub Vec<KeyValue>);
+
+impl AttachmentHandler<AttributeGroup> for OTelAttribute {
+    fn display(value: &AttributeGroup, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, kv) in value.0.iter().enumerate() {
+            if index > 0 {
+                formatter.write_char('\n')?;
+            }
+            write!(formatter, "{} = {}", kv.key, kv.value)?;
         }
+        Ok(())
+    }
+
+    fn debug(value: &AttributeGroup, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(value, formatter)
+    }
+
+    fn preferred_formatting_style(
+        _value: &AttributeGroup,
+        report_formatting_function: FormattingFunction,
+    ) -> AttachmentFormattingStyle {
+        AttachmentFormattingStyle {
+            placement: AttachmentFormattingPlacement::Appendix {
+                appendix_name: "Attributes",
+            },
+            function: report_formatting_function,
+            priority: 0,
+        }
+    }
+}
+
+/// Extension trait for [`Report`]s, for attaching attributes to be emitted
+/// with them.
+///
+/// The attributes are added to the emitted events and records as described on
+/// [`ExceptionEventSpec::attachment_attributes`](crate::spec::ExceptionEventSpec::attachment_attributes).
+pub trait AttributeReportExt: Sized {
+    /// Attach the attribute `key = value`.
+    fn attach_attribute(self, key: impl Into<Key>, value: impl Into<Value>) -> Self;
+
+    /// Attach `attributes` as a single [`AttributeGroup`], rather than one
+    /// attachment per attribute.
+    fn attach_attributes(self, attributes: impl IntoIterator<Item = KeyValue>) -> Self;
+}
+
+impl<C: ?Sized, T> AttributeReportExt for Report<C, markers::Mutable, T>
+where
+    KeyValue: ObjectMarkerFor<T>,
+    AttributeGroup: ObjectMarkerFor<T>,
+{
+    fn attach_attribute(self, key: impl Into<Key>, value: impl Into<Value>) -> Self {
+        self.attach_custom::<OTelAttribute, _>(KeyValue::new(key, value))
     }
 
-    fn on_sendsync_creation(&self, mut report: ReportMut<'_, markers::Dynamic, SendSync>) {
-        report = report.attach_custom::<OpenTelemetryMetadataCollector, _>(SystemTime::now());
+    fn attach_attributes(self, attributes: impl IntoIterator<Item = KeyValue>) -> Self {
+        self.attach_custom::<OTelAttribute, _>(AttributeGroup(attributes.into_iter().collect()))
+    }
+}
+
+/// Extension trait for [`Result`]s of [`Report`]s, for attaching attributes
+/// to the error where it is created.
+pub trait AttributeResultExt: Sized {
+    /// [Attach](AttributeReportExt::attach_attribute) the attribute `key = value`
+    /// to the error, if any.
+    ///
+    /// ```
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::attachments::AttributeResultExt;
+    /// let result: Result<(), Report> =
+    ///     Err(report!("upstream timed out")).attach_attribute("http.route", "/orders");
+    /// ```
+    fn attach_attribute(self, key: impl Into<Key>, value: impl Into<Value>) -> Self;
+
+    /// [Attach](AttributeReportExt::attach_attributes) `attributes` to the error,
+    /// if any, as a single [`AttributeGroup`].
+    fn attach_attributes(self, attributes: impl IntoIterator<Item = KeyValue>) -> Self;
+}
+
+impl<V, C: ?Sized, T> AttributeResultExt for Result<V, Report<C, markers::Mutable, T>>
+where
+    KeyValue: ObjectMarkerFor<T>,
+    AttributeGroup: ObjectMarkerFor<T>,
+{
+    fn attach_attribute(self, key: impl Into<Key>, value: impl Into<Value>) -> Self {
+        self.map_err(|report| report.attach_attribute(key, value))
+    }
+
+    fn attach_attributes(self, attributes: impl IntoIterator<Item = KeyValue>) -> Self {
+        self.map_err(|report| report.attach_attributes(attributes))
+    }
+}
+
+/// Report creation hook running the wrapped hook only for reports created
+/// outside of unsampled traces, e.g. to skip capturing backtraces that would
+/// never be exported.
+///
+/// Reports created without a valid span context are still collected, since
+/// they may be emitted as log records, which are not subject to trace sampling.
+/// Recording on unsampled spans is already skipped by
+/// [`RecordErrorReport`](crate::span_event::RecordErrorReport) without
+/// formatting the report.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampledOnly<H>(pub H);
+
+impl<H> SampledOnly<H> {
+    fn should_collect() -> bool {
         let ctx = Context::current();
         let span = ctx.span();
         let span_ctx = span.span_context();
-        if span_ctx.is_valid() {
-            let _ = report.attach_custom::<OpenTelemetryMetadataCollector, _>(span_ctx.clone());
+        !span_ctx.is_valid() || span_ctx.is_sampled()
+    }
+}
+
+impl<H: ReportCreationHook> ReportCreationHook for SampledOnly<H> {
+    fn on_local_creation(&self, report: ReportMut<'_, markers::Dynamic, Local>) {
+        if Self::should_collect() {
+            self.0.on_local_creation(report);
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, markers::Dynamic, SendSync>) {
+        if Self::should_collect() {
+            self.0.on_sendsync_creation(report);
         }
     }
 }
 
-pub struct HideTraceAttachments;
-impl AttachmentFormatterHook<SpanContext> for HideTraceAttachments {
+/// [`AttachmentHandler`] for telemetry plumbing attachments, which are
+/// hidden from the formatted report and only surface as emitted attributes.
+#[derive(Debug, Clone, Copy)]
+pub struct Hidden;
+
+impl<A: Debug + 'static> AttachmentHandler<A> for Hidden {
+    fn display(value: &A, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(value, formatter)
+    }
+
+    fn debug(value: &A, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(value, formatter)
+    }
+
     fn preferred_formatting_style(
-        &self,
-        _attachment: ReportAttachmentRef<'_, markers::Dynamic>,
+        _value: &A,
         report_formatting_function: FormattingFunction,
     ) -> AttachmentFormattingStyle {
         AttachmentFormattingStyle {
@@ -144,3 +419,45 @@ impl AttachmentFormatterHook<SpanContext> for HideTraceAttachments {
         }
     }
 }
+
+/// Attachment formatter hook hiding [`SpanContext`] attachments from the
+/// formatted report and thus from the `exception.stacktrace` attribute.
+///
+/// The [`OpenTelemetryMetadataCollector`] shows them as `TRACE` lines, while its
+/// other attachments are hidden already. Use [`HideTimestampAttachments`] as
+/// well to hide [`SystemTime`]s attached by other means.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HideTraceAttachments;
+
+impl AttachmentFormatterHook<SpanContext> for HideTraceAttachments {
+    fn preferred_formatting_style(
+        &self,
+        _attachment: ReportAttachmentRef<'_, markers::Dynamic>,
+        report_formatting_function: FormattingFunction,
+    ) -> AttachmentFormattingStyle {
+        hidden_style(report_formatting_function)
+    }
+}
+
+/// Attachment formatter hook hiding [`SystemTime`] attachments from the
+/// formatted report, as [`HideTraceAttachments`] does for span contexts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HideTimestampAttachments;
+
+impl AttachmentFormatterHook<SystemTime> for HideTimestampAttachments {
+    fn preferred_formatting_style(
+        &self,
+        _attachment: ReportAttachmentRef<'_, markers::Dynamic>,
+        report_formatting_function: FormattingFunction,
+    ) -> AttachmentFormattingStyle {
+        hidden_style(report_formatting_function)
+    }
+}
+
+fn hidden_style(report_formatting_function: FormattingFunction) -> AttachmentFormattingStyle {
+    AttachmentFormattingStyle {
+        placement: AttachmentFormattingPlacement::Hidden,
+        function: report_formatting_function,
+        priority: i32::MIN,
+    }
+}
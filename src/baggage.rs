@@ -0,0 +1,63 @@
+//! Baggage captured at report creation.
+
+use opentelemetry::{
+    Context, KeyValue,
+    baggage::{Baggage, BaggageExt},
+};
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::attachments::Hidden;
+
+/// The [`Baggage`] of the context a report was created in.
+///
+/// Its entries are emitted as attributes on exception events and log records,
+/// without overriding attributes with the same key, so that request-scoped
+/// values survive the report being emitted from another context.
+#[derive(Debug, Clone)]
+pub struct BaggageSnapshot(pub Baggage);
+
+impl BaggageSnapshot {
+    /// Capture the baggage of the current context, if it has any entries.
+    pub fn capture() -> Option<Self> {
+        let ctx = Context::current();
+        let baggage = ctx.baggage();
+        (!baggage.is_empty()).then(|| Self(baggage.clone()))
+    }
+
+    pub(crate) fn attributes(&self) -> impl Iterator<Item = KeyValue> + '_ {
+        self.0
+            .iter()
+            .map(|(key, (value, _))| KeyValue::new(key.clone(), value.clone()))
+    }
+}
+
+/// Report creation hook attaching a [`BaggageSnapshot`] to reports created
+/// in a context with baggage.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BaggageCollector {
+    _priv: (),
+}
+
+impl BaggageCollector {
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl ReportCreationHook for BaggageCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        if let Some(snapshot) = BaggageSnapshot::capture() {
+            let _ = report.attach_custom::<Hidden, _>(snapshot);
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        if let Some(snapshot) = BaggageSnapshot::capture() {
+            let _ = report.attach_custom::<Hidden, _>(snapshot);
+        }
+    }
+}
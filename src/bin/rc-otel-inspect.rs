@@ -0,0 +1,382 @@
+//! Offline triage of exception telemetry: reads OTLP/JSON log and trace data,
+//! one JSON document per line, and pretty-prints the exceptions in it as
+//! re-created reports, followed by their decoded fields, the full report tree
+//! of their `exception.report.cbor` attribute, if any, and their custom
+//! attributes.
+//!
+//! Accepts the records written by the fatal emitter as well as OTLP/JSON
+//! `ExportLogsServiceRequest`s and `ExportTraceServiceRequest`s, e.g. as written
+//! by the collector's `file` exporter.
+//!
+//! ```text
+//! rc-otel-inspect [FILE]...
+//! ```
+//!
+//! Reads standard input if no file is given.
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    process::ExitCode,
+    time::UNIX_EPOCH,
+};
+
+use opentelemetry::{Array, KeyValue, StringValue, Value};
+use rootcause_opentelemetry::{
+    cbor::{EXCEPTION_REPORT_CBOR, ReportTree},
+    rehydrate::{ExceptionEventData, report_from_exception_event},
+};
+use serde_json::Value as Json;
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    let mut out = io::stdout().lock();
+    let result = if paths.is_empty() {
+        inspect(io::stdin().lock(), "<stdin>", &mut out)
+    } else {
+        paths.iter().try_for_each(|path| {
+            let file = File::open(path).map_err(|error| format!("{path}: {error}"))?;
+            inspect(BufReader::new(file), path, &mut out)
+        })
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("rc-otel-inspect: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn inspect(input: impl BufRead, source: &str, out: &mut impl Write) -> Result<(), String> {
+    for (index, line) in input.lines().enumerate() {
+        let line = line.map_err(|error| format!("{source}: {error}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let document: Json = serde_json::from_str(&line)
+            .map_err(|error| format!("{source}:{}: {error}", index + 1))?;
+        for (name, attributes) in exception_events(&document) {
+            if let Some(text) = render(&format!("{source}:{}", index + 1), &name, attributes) {
+                out.write_all(text.as_bytes())
+                    .map_err(|error| format!("<stdout>: {error}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The name and attributes of every log record and span event in `document`.
+fn exception_events(document: &Json) -> Vec<(String, Vec<KeyValue>)> {
+    let mut events = Vec::new();
+    if document.get("eventName").is_some() {
+        events.push(event(document, "eventName"));
+    }
+    for resource_logs in array(document, "resourceLogs") {
+        for scope_logs in array(resource_logs, "scopeLogs") {
+            for record in array(scope_logs, "logRecords") {
+                events.push(event(record, "eventName"));
+            }
+        }
+    }
+    for resource_spans in array(document, "resourceSpans") {
+        for scope_spans in array(resource_spans, "scopeSpans") {
+            for span in array(scope_spans, "spans") {
+                for span_event in array(span, "events") {
+                    events.push(event(span_event, "name"));
+                }
+            }
+        }
+    }
+    events
+}
+
+fn event(object: &Json, name_key: &str) -> (String, Vec<KeyValue>) {
+    let name = object
+        .get(name_key)
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+        .to_owned();
+    let attributes = array(object, "attributes")
+        .filter_map(|kv| {
+            let key = kv.get("key")?.as_str()?.to_owned();
+            Some(KeyValue::new(key, any_value(kv.get("value")?)?))
+        })
+        .collect();
+    (name, attributes)
+}
+
+fn array<'j>(object: &'j Json, key: &str) -> impl Iterator<Item = &'j Json> {
+    object
+        .get(key)
+        .and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// An OTLP/JSON `AnyValue`, with nested values other than string arrays flattened to JSON text.
+fn any_value(value: &Json) -> Option<Value> {
+    if let Some(string) = value.get("stringValue").and_then(Json::as_str) {
+        return Some(string.to_owned().into());
+    }
+    if let Some(boolean) = value.get("boolValue").and_then(Json::as_bool) {
+        return Some(boolean.into());
+    }
+    if let Some(int) = value.get("intValue") {
+        // Encoded as a string in OTLP/JSON, since it may not fit a double.
+        let int = match int {
+            Json::String(int) => int.parse().ok()?,
+            int => int.as_i64()?,
+        };
+        return Some(int.into());
+    }
+    if let Some(double) = value.get("doubleValue").and_then(Json::as_f64) {
+        return Some(double.into());
+    }
+    if let Some(values) = value.get("arrayValue") {
+        let strings: Option<Vec<StringValue>> = array(values, "values")
+            .map(|value| {
+                value
+                    .get("stringValue")
+                    .and_then(Json::as_str)
+                    .map(|string| string.to_owned().into())
+            })
+            .collect();
+        if let Some(strings) = strings {
+            return Some(Value::Array(Array::String(strings)));
+        }
+    }
+    Some(value.to_string().into())
+}
+
+/// The exception of an event named `name` as its re-created report, followed
+/// by its decoded fields, report tree and custom attributes, or `None` if the
+/// event is not an exception.
+fn render(location: &str, name: &str, attributes: Vec<KeyValue>) -> Option<String> {
+    if name != "exception" {
+        return None;
+    }
+    let data = ExceptionEventData::from_attributes(attributes.iter().cloned());
+    let tree = data.report_tree();
+    // The encoded tree is printed decoded instead.
+    let report = report_from_exception_event(
+        name,
+        attributes
+            .into_iter()
+            .filter(|kv| tree.is_none() || kv.key.as_str() != EXCEPTION_REPORT_CBOR),
+    );
+
+    let mut text = String::new();
+    let _ = writeln!(text, "── {location}");
+    match &report {
+        Some(report) => {
+            let _ = writeln!(text, "{report}");
+        }
+        None => {
+            if let Some(stacktrace) = data.stacktrace() {
+                let _ = writeln!(text, "{stacktrace}");
+            }
+        }
+    }
+
+    let _ = writeln!(text, "fields:");
+    let fields = [
+        ("exception.type", data.exception_type()),
+        ("message", data.message()),
+        ("error.type", data.error_type()),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            let _ = writeln!(text, "  {key}: {value}");
+        }
+    }
+    if let Some(escaped) = data.escaped() {
+        let _ = writeln!(text, "  escaped: {escaped}");
+    }
+    if let Some(count) = data.dropped_attribute_count() {
+        let _ = writeln!(text, "  dropped attributes: {count}");
+    }
+    for kv in data.extras().iter().filter(|kv| {
+        !matches!(
+            kv.key.as_str(),
+            "exception.escaped" | "exception.dropped_attribute_count"
+        ) && !(tree.is_some() && kv.key.as_str() == EXCEPTION_REPORT_CBOR)
+    }) {
+        let _ = writeln!(text, "  {}: {}", kv.key, kv.value);
+    }
+    if let Some(tree) = &tree {
+        let _ = writeln!(text, "report tree:");
+        write_tree(&mut text, tree, 1);
+    }
+    if !data.attributes().is_empty() {
+        let _ = writeln!(text, "attributes:");
+        for kv in data.attributes() {
+            let _ = writeln!(text, "  {}: {}", kv.key, kv.value);
+        }
+    }
+    let _ = writeln!(text);
+    Some(text)
+}
+
+/// Each report of `tree` as its message and type, followed by its timestamp
+/// and attachments, and its children indented one level further.
+fn write_tree(text: &mut String, tree: &ReportTree, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let _ = writeln!(
+        text,
+        "{indent}{} ({})",
+        indented(&tree.message, &indent),
+        tree.type_name
+    );
+    if let Some(since_epoch) = tree
+        .timestamp
+        .and_then(|timestamp| timestamp.duration_since(UNIX_EPOCH).ok())
+    {
+        let _ = writeln!(
+            text,
+            "{indent}  at: {}.{:09} s since the Unix epoch",
+            since_epoch.as_secs(),
+            since_epoch.subsec_nanos()
+        );
+    }
+    for (type_name, value) in &tree.attachments {
+        let _ = writeln!(
+            text,
+            "{indent}  - {type_name}: {}",
+            indented(value, &format!("{indent}    "))
+        );
+    }
+    for child in &tree.children {
+        write_tree(text, child, depth + 1);
+    }
+}
+
+/// `value` with its lines after the first indented by `indent`.
+fn indented(value: &str, indent: &str) -> String {
+    value.replace('\n', &format!("\n{indent}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inspected(input: &str) -> String {
+        let mut out = Vec::new();
+        inspect(input.as_bytes(), "input", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn decodes_otlp_json_values() {
+        let int = serde_json::json!({ "intValue": "9007199254740993" });
+        assert_eq!(any_value(&int), Some(Value::I64(9007199254740993)));
+        let strings = serde_json::json!({
+            "arrayValue": { "values": [{ "stringValue": "a" }, { "stringValue": "b" }] }
+        });
+        assert_eq!(
+            any_value(&strings),
+            Some(Value::Array(Array::String(vec!["a".into(), "b".into()])))
+        );
+        let nested = serde_json::json!({ "kvlistValue": { "values": [] } });
+        assert_eq!(any_value(&nested), Some(Value::from(nested.to_string())));
+    }
+
+    #[test]
+    fn prints_span_events_with_their_fields_and_attributes() {
+        let document = serde_json::json!({
+            "resourceSpans": [{ "scopeSpans": [{ "spans": [{ "events": [
+                { "name": "checkpoint", "attributes": [] },
+                { "name": "exception", "attributes": [
+                    { "key": "exception.type", "value": { "stringValue": "std::io::Error" } },
+                    { "key": "exception.message", "value": { "stringValue": "connection refused" } },
+                    { "key": "exception.escaped", "value": { "boolValue": true } },
+                    { "key": "exception.dropped_attribute_count", "value": { "intValue": "2" } },
+                    { "key": "error.type", "value": { "stringValue": "io.connection_refused" } },
+                    { "key": "http.route", "value": { "stringValue": "/orders" } }
+                ]}
+            ]}]}]}]
+        });
+        let output = inspected(&format!("\n{document}\n"));
+
+        assert_eq!(output.matches("── input:2").count(), 1, "{output}");
+        assert!(output.contains("connection refused"), "{output}");
+        assert!(
+            output.contains("  exception.type: std::io::Error\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("  error.type: io.connection_refused\n"),
+            "{output}"
+        );
+        assert!(output.contains("  escaped: true\n"), "{output}");
+        assert!(output.contains("  dropped attributes: 2\n"), "{output}");
+        assert!(
+            output.contains("attributes:\n  http.route: /orders\n"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn prints_log_records_without_exception_attributes() {
+        let document = serde_json::json!({
+            "resourceLogs": [{ "scopeLogs": [{ "logRecords": [{
+                "eventName": "exception",
+                "attributes": [
+                    { "key": "error.type", "value": { "stringValue": "db.timeout" } },
+                    { "key": "error.message", "value": { "stringValue": "query timed out" } }
+                ]
+            }]}]}]
+        });
+        let output = inspected(&document.to_string());
+
+        assert!(output.contains("  message: query timed out\n"), "{output}");
+        assert!(output.contains("  error.type: db.timeout\n"), "{output}");
+    }
+
+    #[test]
+    fn prints_the_report_tree() {
+        let tree = ReportTree {
+            type_name: "&str".to_owned(),
+            message: "checkout failed".to_owned(),
+            timestamp: Some(UNIX_EPOCH + std::time::Duration::from_millis(1500)),
+            attachments: vec![("&str".to_owned(), "attempt 3".to_owned())],
+            children: vec![ReportTree {
+                type_name: "std::io::Error".to_owned(),
+                message: "connection refused".to_owned(),
+                timestamp: None,
+                attachments: Vec::new(),
+                children: Vec::new(),
+            }],
+        };
+        let document = serde_json::json!({
+            "resourceSpans": [{ "scopeSpans": [{ "spans": [{ "events": [
+                { "name": "exception", "attributes": [
+                    { "key": "exception.type", "value": { "stringValue": "&str" } },
+                    { "key": "exception.message", "value": { "stringValue": "checkout failed" } },
+                    { "key": EXCEPTION_REPORT_CBOR, "value": { "stringValue": tree.encode_base64() } }
+                ]}
+            ]}]}]}]
+        });
+        let output = inspected(&document.to_string());
+
+        assert!(
+            output.contains(
+                "report tree:\n  \
+                 checkout failed (&str)\n    \
+                 at: 1.500000000 s since the Unix epoch\n    \
+                 - &str: attempt 3\n    \
+                 connection refused (std::io::Error)\n"
+            ),
+            "{output}"
+        );
+        assert!(!output.contains(EXCEPTION_REPORT_CBOR), "{output}");
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let error = inspect("{}\nnot json\n".as_bytes(), "input", &mut Vec::new()).unwrap_err();
+        assert!(error.starts_with("input:2: "), "{error}");
+    }
+}
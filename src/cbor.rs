@@ -0,0 +1,442 @@
+//! Compact [CBOR](https://www.rfc-editor.org/rfc/rfc8949) encoding of whole
+//! report trees, for backends and collector processors reconstructing reports
+//! losslessly, see [`ExceptionEventSpec::report_cbor`](crate::spec::ExceptionEventSpec::report_cbor).
+//!
+//! Each report in the tree is encoded as a map of:
+//! - `type`: the [type name](rootcause::Report::current_context_type_name) of its context, as text.
+//! - `message`: its [formatted context](rootcause::Report::format_current_context), as text.
+//! - `timestamp`: its creation time in nanoseconds since the Unix epoch, as an
+//!   unsigned integer, if it has a [`SystemTime`] attachment.
+//! - `attachments`: an array of maps of the `type` name and formatted `value`
+//!   of its non-hidden attachments, if any.
+//! - `children`: an array of the maps of its child reports, if any.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::utilities::{AttachmentsExt, format_contained, visible_attachments};
+
+pub const EXCEPTION_REPORT_CBOR: &str = "exception.report.cbor";
+
+const UNSIGNED: u8 = 0;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A report tree as carried by the `exception.report.cbor` attribute, which
+/// [`EmitLayer`](crate::pipeline::EmitLayer)s may rewrite or remove through
+/// [`ExceptionSnapshot::report_tree`](crate::pipeline::ExceptionSnapshot::report_tree)
+/// before it is encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTree {
+    pub type_name: String,
+    pub message: String,
+    pub timestamp: Option<SystemTime>,
+    /// The type name and formatted value of each non-hidden attachment.
+    pub attachments: Vec<(String, String)>,
+    pub children: Vec<ReportTree>,
+}
+
+impl ReportTree {
+    pub(crate) fn new(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Self {
+        let type_name = rep.current_context_type_name();
+        Self {
+            type_name: type_name.to_owned(),
+            message: format_contained(type_name, || rep.format_current_context().to_string()),
+            timestamp: rep.find_attachment_inner::<SystemTime>().copied(),
+            attachments: visible_attachments(rep)
+                .map(|attachment| {
+                    let type_name = attachment.inner_type_name();
+                    let value =
+                        format_contained(type_name, || attachment.format_inner().to_string());
+                    (type_name.to_owned(), value)
+                })
+                .collect(),
+            children: rep
+                .children()
+                .iter()
+                .map(|child| Self::new(child.into_uncloneable()))
+                .collect(),
+        }
+    }
+
+    /// The tree encoded as CBOR, in padded standard base64.
+    pub fn encode_base64(&self) -> String {
+        let mut cbor = Vec::new();
+        self.encode(&mut cbor);
+        base64(&cbor)
+    }
+
+    /// Decode a tree encoded by [`Self::encode_base64`], e.g. the value of an
+    /// `exception.report.cbor` attribute, or `None` if it is malformed.
+    pub fn decode_base64(encoded: &str) -> Option<Self> {
+        let cbor = unbase64(encoded)?;
+        let mut rest = &cbor[..];
+        let tree = Self::decode(&mut rest)?;
+        rest.is_empty().then_some(tree)
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let timestamp = self
+            .timestamp
+            .and_then(|timestamp| timestamp.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| u64::try_from(since_epoch.as_nanos()).unwrap_or(u64::MAX));
+
+        let entries = 2
+            + usize::from(timestamp.is_some())
+            + usize::from(!self.attachments.is_empty())
+            + usize::from(!self.children.is_empty());
+        head(out, MAP, entries as u64);
+
+        text(out, "type");
+        text(out, &self.type_name);
+        text(out, "message");
+        text(out, &self.message);
+        if let Some(timestamp) = timestamp {
+            text(out, "timestamp");
+            head(out, UNSIGNED, timestamp);
+        }
+        if !self.attachments.is_empty() {
+            text(out, "attachments");
+            head(out, ARRAY, self.attachments.len() as u64);
+            for (type_name, value) in &self.attachments {
+                head(out, MAP, 2);
+                text(out, "type");
+                text(out, type_name);
+                text(out, "value");
+                text(out, value);
+            }
+        }
+        if !self.children.is_empty() {
+            text(out, "children");
+            head(out, ARRAY, self.children.len() as u64);
+            for child in &self.children {
+                child.encode(out);
+            }
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        let mut tree = Self {
+            type_name: String::new(),
+            message: String::new(),
+            timestamp: None,
+            attachments: Vec::new(),
+            children: Vec::new(),
+        };
+        for _ in 0..read_head(bytes, MAP)? {
+            match read_text(bytes)?.as_str() {
+                "type" => tree.type_name = read_text(bytes)?,
+                "message" => tree.message = read_text(bytes)?,
+                "timestamp" => {
+                    let nanos = read_head(bytes, UNSIGNED)?;
+                    tree.timestamp = Some(UNIX_EPOCH + Duration::from_nanos(nanos));
+                }
+                "attachments" => {
+                    for _ in 0..read_head(bytes, ARRAY)? {
+                        let (mut type_name, mut value) = (None, None);
+                        for _ in 0..read_head(bytes, MAP)? {
+                            match read_text(bytes)?.as_str() {
+                                "type" => type_name = Some(read_text(bytes)?),
+                                "value" => value = Some(read_text(bytes)?),
+                                _ => return None,
+                            }
+                        }
+                        tree.attachments.push((type_name?, value?));
+                    }
+                }
+                "children" => {
+                    for _ in 0..read_head(bytes, ARRAY)? {
+                        tree.children.push(Self::decode(bytes)?);
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(tree)
+    }
+}
+
+/// The initial byte of a data item of the `major` type, and its argument `value`
+/// in the shortest form.
+fn head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if let Ok(value) = u8::try_from(value) {
+        out.push(major | 24);
+        out.push(value);
+    } else if let Ok(value) = u16::try_from(value) {
+        out.push(major | 25);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else if let Ok(value) = u32::try_from(value) {
+        out.push(major | 26);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn text(out: &mut Vec<u8>, value: &str) {
+    head(out, TEXT, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// The argument of the data item of the `major` type at the start of `bytes`,
+/// advancing past its head, or `None` if there is no such item.
+fn read_head(bytes: &mut &[u8], major: u8) -> Option<u64> {
+    let (&initial, rest) = bytes.split_first()?;
+    if initial >> 5 != major {
+        return None;
+    }
+    let width = match initial & 0x1f {
+        value @ 0..24 => {
+            *bytes = rest;
+            return Some(u64::from(value));
+        }
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return None,
+    };
+    let argument = rest.get(..width)?;
+    *bytes = &rest[width..];
+    Some(
+        argument
+            .iter()
+            .fold(0, |acc, &byte| (acc << 8) | u64::from(byte)),
+    )
+}
+
+fn read_text(bytes: &mut &[u8]) -> Option<String> {
+    let len = usize::try_from(read_head(bytes, TEXT)?).ok()?;
+    let value = bytes.get(..len)?;
+    *bytes = &bytes[len..];
+    String::from_utf8(value.to_vec()).ok()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |acc, (index, &byte)| {
+            acc | (u32::from(byte) << (16 - 8 * index))
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(char::from(
+                    BASE64[((triple >> (18 - 6 * index)) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn unbase64(encoded: &str) -> Option<Vec<u8>> {
+    let sextets = encoded
+        .trim_end_matches('=')
+        .bytes()
+        .map(|byte| {
+            BASE64
+                .iter()
+                .position(|&c| c == byte)
+                .map(|sextet| sextet as u32)
+        })
+        .collect::<Option<Vec<u32>>>()?;
+    let mut bytes = Vec::with_capacity(sextets.len() * 3 / 4);
+    for chunk in sextets.chunks(4) {
+        let quad = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (index, &sextet)| {
+                acc | (sextet << (18 - 6 * index))
+            });
+        for index in 0..chunk.len() - 1 {
+            bytes.push((quad >> (16 - 8 * index)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rootcause::prelude::*;
+
+    use super::*;
+    use crate::AsReportRef;
+
+    /// A decoded data item of the subset of CBOR produced by [`ReportTree::encode`].
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Unsigned(u64),
+        Text(String),
+        Array(Vec<Item>),
+        Map(Vec<(Item, Item)>),
+    }
+
+    impl Item {
+        fn get(&self, key: &str) -> Option<&Item> {
+            let Item::Map(entries) = self else {
+                return None;
+            };
+            entries
+                .iter()
+                .find(|(k, _)| *k == Item::Text(key.to_owned()))
+                .map(|(_, v)| v)
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Item {
+        let (&initial, rest) = bytes.split_first().expect("truncated item");
+        *bytes = rest;
+        let width = match initial & 0x1f {
+            value @ 0..24 => return decode_with(initial >> 5, u64::from(value), bytes),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            other => panic!("unexpected additional information {other}"),
+        };
+        let (argument, rest) = bytes.split_at(width);
+        *bytes = rest;
+        let value = argument
+            .iter()
+            .fold(0u64, |acc, &byte| (acc << 8) | u64::from(byte));
+        decode_with(initial >> 5, value, bytes)
+    }
+
+    fn decode_with(major: u8, value: u64, bytes: &mut &[u8]) -> Item {
+        match major {
+            UNSIGNED => Item::Unsigned(value),
+            TEXT => {
+                let (text, rest) = bytes.split_at(value as usize);
+                *bytes = rest;
+                Item::Text(String::from_utf8(text.to_vec()).expect("invalid UTF-8"))
+            }
+            ARRAY => Item::Array((0..value).map(|_| decode(bytes)).collect()),
+            MAP => Item::Map((0..value).map(|_| (decode(bytes), decode(bytes))).collect()),
+            other => panic!("unexpected major type {other}"),
+        }
+    }
+
+    #[test]
+    fn base64_matches_rfc_4648_vectors() {
+        for (input, expected) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(input.as_bytes()), expected);
+            assert_eq!(unbase64(expected).unwrap(), input.as_bytes());
+        }
+    }
+
+    #[test]
+    fn base64_round_trips_all_byte_values() {
+        let bytes: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        for len in 0..bytes.len() {
+            assert_eq!(unbase64(&base64(&bytes[..len])).unwrap(), &bytes[..len]);
+        }
+    }
+
+    #[test]
+    fn head_uses_the_shortest_argument() {
+        for (value, expected) in [
+            (0, &[0x00][..]),
+            (23, &[0x17]),
+            (24, &[0x18, 0x18]),
+            (255, &[0x18, 0xff]),
+            (256, &[0x19, 0x01, 0x00]),
+            (65_536, &[0x1a, 0x00, 0x01, 0x00, 0x00]),
+            (
+                u64::MAX,
+                &[0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+        ] {
+            let mut out = Vec::new();
+            head(&mut out, UNSIGNED, value);
+            assert_eq!(out, expected);
+            assert_eq!(decode(&mut &out[..]), Item::Unsigned(value));
+        }
+    }
+
+    #[test]
+    fn text_round_trips() {
+        for value in ["", "a", "ünïcødé", "x".repeat(300).as_str()] {
+            let mut out = Vec::new();
+            text(&mut out, value);
+            let mut bytes = &out[..];
+            assert_eq!(decode(&mut bytes), Item::Text(value.to_owned()));
+            assert!(bytes.is_empty());
+        }
+    }
+
+    #[test]
+    fn report_tree_round_trips() {
+        let report = report!("child failed")
+            .attach("attempt 3")
+            .context("parent failed");
+        let bytes = unbase64(&ReportTree::new(report.as_report_ref()).encode_base64()).unwrap();
+        let mut rest = &bytes[..];
+        let root = decode(&mut rest);
+        assert!(rest.is_empty());
+
+        assert_eq!(
+            root.get("message"),
+            Some(&Item::Text("parent failed".to_owned()))
+        );
+        assert!(matches!(root.get("type"), Some(Item::Text(_))));
+        let Some(Item::Array(children)) = root.get("children") else {
+            panic!("missing children in {root:?}");
+        };
+        assert_eq!(children.len(), 1);
+        let child = &children[0];
+        assert_eq!(
+            child.get("message"),
+            Some(&Item::Text("child failed".to_owned()))
+        );
+        let Some(Item::Array(attachments)) = child.get("attachments") else {
+            panic!("missing attachments in {child:?}");
+        };
+        assert!(
+            attachments
+                .iter()
+                .any(|attachment| attachment.get("value")
+                    == Some(&Item::Text("attempt 3".to_owned())))
+        );
+        assert_eq!(child.get("children"), None);
+    }
+
+    #[test]
+    fn report_tree_decodes_what_it_encodes() {
+        let report = report!("child failed")
+            .attach("attempt 3")
+            .context("parent failed");
+        let tree = ReportTree::new(report.as_report_ref());
+        assert_eq!(
+            ReportTree::decode_base64(&tree.encode_base64()),
+            Some(tree.clone())
+        );
+
+        let encoded = tree.encode_base64();
+        assert_eq!(
+            ReportTree::decode_base64(&encoded[..encoded.len() - 4]),
+            None
+        );
+        assert_eq!(ReportTree::decode_base64("not base64!"), None);
+    }
+}
@@ -0,0 +1,82 @@
+//! Classification of exception messages into a bounded set of `error.category` labels.
+
+use std::{borrow::Cow, sync::RwLock};
+
+use regex::Regex;
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::utilities::format_contained;
+
+pub const ERROR_CATEGORY: &str = "error.category";
+
+static RULES: RwLock<Option<CategoryRules>> = RwLock::new(None);
+
+/// Ordered set of regular expressions over exception messages, each mapping
+/// to a category label such as `connection_refused` or `deadline_exceeded`.
+///
+/// Once [installed](Self::install), the label of the first matching rule is
+/// emitted as the `error.category` attribute on exception events and log records.
+/// Since labels only ever come from the rules, the attribute has bounded
+/// cardinality, unlike the message itself.
+#[derive(Debug, Clone, Default)]
+pub struct CategoryRules {
+    rules: Vec<(Regex, Cow<'static, str>)>,
+    fallback: Option<Cow<'static, str>>,
+}
+
+impl CategoryRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a rule labelling messages matching `pattern` with `category`.
+    pub fn rule(
+        mut self,
+        pattern: &str,
+        category: impl Into<Cow<'static, str>>,
+    ) -> Result<Self, regex::Error> {
+        self.rules.push((Regex::new(pattern)?, category.into()));
+        Ok(self)
+    }
+
+    /// Label messages matching none of the rules with `category`,
+    /// instead of omitting the attribute.
+    pub fn fallback(mut self, category: impl Into<Cow<'static, str>>) -> Self {
+        self.fallback = Some(category.into());
+        self
+    }
+
+    /// The category of `message`, if any.
+    pub fn classify(&self, message: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(message))
+            .map(|(_, category)| category)
+            .or(self.fallback.as_ref())
+            .map(AsRef::as_ref)
+    }
+
+    /// Make these the process-wide rules, returning the previous ones.
+    pub fn install(self) -> Option<Self> {
+        RULES
+            .write()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .replace(self)
+    }
+}
+
+/// The category of a report's current context according to the installed rules.
+pub(crate) fn category(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<String> {
+    // The rules are cloned out, as formatting the message runs user code.
+    let rules = RULES
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone()?;
+    let message = format_contained(rep.current_context_type_name(), || {
+        rep.format_current_context().to_string()
+    });
+    rules.classify(&message).map(str::to_owned)
+}
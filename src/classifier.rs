@@ -0,0 +1,141 @@
+//! Classification of reports whose context types the application does not
+//! own, such as `std::io::Error` or errors of third-party crates, which cannot
+//! implement this crate's traits.
+
+use std::{
+    any::TypeId,
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use opentelemetry::{KeyValue, trace::Status};
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::error_type;
+
+type StatusFn =
+    Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<Status> + Send + Sync>;
+
+static STATUSES: LazyLock<RwLock<HashMap<TypeId, StatusFn>>> = LazyLock::new(Default::default);
+
+/// How reports whose current context is a `C` are classified, registered at
+/// runtime with [`Self::register`].
+///
+/// Each part left unset keeps its default, and parts registered for `C`
+/// elsewhere, e.g. with [`register_severity_with`](crate::severity::register_severity_with),
+/// are replaced by the ones set here.
+///
+/// ```
+/// use opentelemetry::{KeyValue, trace::Status};
+/// use rootcause_opentelemetry::classifier::ErrorClassifier;
+///
+/// ErrorClassifier::<std::io::Error>::new()
+///     .error_type(|error| format!("io.{:?}", error.kind()).into())
+///     .span_status(|error| match error.kind() {
+///         std::io::ErrorKind::NotFound => Status::Unset,
+///         _ => Status::error(error.to_string()),
+///     })
+///     .attributes(|error| vec![KeyValue::new("io.os_error", error.raw_os_error().is_some())])
+///     .register();
+/// ```
+#[must_use]
+pub struct ErrorClassifier<C> {
+    span_status: Option<Box<dyn Fn(&C) -> Status + Send + Sync>>,
+    #[cfg(feature = "logs")]
+    severity: Option<Box<dyn Fn(&C) -> opentelemetry::logs::Severity + Send + Sync>>,
+    error_type: Option<Box<dyn Fn(&C) -> Cow<'static, str> + Send + Sync>>,
+    attributes: Option<Box<dyn Fn(&C) -> Vec<KeyValue> + Send + Sync>>,
+}
+
+impl<C: 'static> Default for ErrorClassifier<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: 'static> ErrorClassifier<C> {
+    pub fn new() -> Self {
+        Self {
+            span_status: None,
+            #[cfg(feature = "logs")]
+            severity: None,
+            error_type: None,
+            attributes: None,
+        }
+    }
+
+    /// The span status set by [`with_error_status`](crate::span_event::RecordErrorReport::with_error_status),
+    /// instead of [`Error`](Status::Error) with the formatted context, e.g.
+    /// [`Unset`](Status::Unset) for errors which are expected.
+    pub fn span_status(mut self, status: impl Fn(&C) -> Status + Send + Sync + 'static) -> Self {
+        self.span_status = Some(Box::new(status));
+        self
+    }
+
+    /// The log severity, as in [`register_severity_with`](crate::severity::register_severity_with).
+    #[cfg(feature = "logs")]
+    pub fn severity(
+        mut self,
+        severity: impl Fn(&C) -> opentelemetry::logs::Severity + Send + Sync + 'static,
+    ) -> Self {
+        self.severity = Some(Box::new(severity));
+        self
+    }
+
+    /// The `error.type`, as in [`register_error_type_with`](crate::error_type::register_error_type_with).
+    pub fn error_type(
+        mut self,
+        error_type: impl Fn(&C) -> Cow<'static, str> + Send + Sync + 'static,
+    ) -> Self {
+        self.error_type = Some(Box::new(error_type));
+        self
+    }
+
+    /// Attributes added to exception events and log records.
+    pub fn attributes(
+        mut self,
+        attributes: impl Fn(&C) -> Vec<KeyValue> + Send + Sync + 'static,
+    ) -> Self {
+        self.attributes = Some(Box::new(attributes));
+        self
+    }
+
+    /// Make the span and log builders use this classification for reports whose
+    /// current context is a `C`.
+    pub fn register(self) {
+        if let Some(status) = self.span_status {
+            STATUSES
+                .write()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .insert(
+                    TypeId::of::<C>(),
+                    Arc::new(move |rep| rep.downcast_current_context::<C>().map(&status)),
+                );
+        }
+        #[cfg(feature = "logs")]
+        if let Some(severity) = self.severity {
+            crate::severity::register_severity_with::<C>(severity);
+        }
+        if let Some(error_type) = self.error_type {
+            error_type::register_error_type_with::<C>(error_type);
+        }
+        if let Some(attributes) = self.attributes {
+            error_type::register_attributes_with::<C>(attributes);
+        }
+    }
+}
+
+/// The span status registered for the context type of `rep`, if any.
+pub(crate) fn span_status(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<Status> {
+    // The function is cloned out so that it can itself emit or register classifications.
+    let status = STATUSES
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .get(&rep.current_context_type_id())
+        .cloned();
+    status.and_then(|status| status(rep))
+}
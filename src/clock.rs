@@ -0,0 +1,131 @@
+//! Source of the timestamps given to reports and emitted telemetry, which can
+//! be replaced to make them deterministic in tests.
+
+use std::{
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+/// Source of the current time.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> SystemTime;
+
+    /// The current instant of a monotonic clock, for measuring durations
+    /// unaffected by changes of the wall-clock time, defaulting to [`Instant::now`].
+    fn monotonic(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The system clock, as given by [`SystemTime::now`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock standing still until advanced manually.
+///
+/// Clones share the same time, so a clone can be [installed](install_clock)
+/// while the original is kept to advance it.
+///
+/// Its [monotonic](Clock::monotonic) instant starts at the creation of the
+/// clock, and only moves when the clock is [advanced](Self::advance).
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    state: Arc<Mutex<ManualState>>,
+}
+
+#[derive(Debug)]
+struct ManualState {
+    now: SystemTime,
+    monotonic: Instant,
+}
+
+impl ManualClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ManualState {
+                now: start,
+                monotonic: Instant::now(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ManualState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    /// Move the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state();
+        state.now += by;
+        state.monotonic += by;
+    }
+
+    /// Set the clock to `to`, leaving its monotonic instant unchanged.
+    pub fn set(&self, to: SystemTime) {
+        self.state().now = to;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.state().now
+    }
+
+    fn monotonic(&self) -> Instant {
+        self.state().monotonic
+    }
+}
+
+/// Make `clock` the process-wide clock, used for the timestamps attached by the
+/// [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector)
+/// and for emissions of reports without one.
+pub fn install_clock(clock: impl Clock) {
+    *CLOCK.write().unwrap_or_else(|poison| poison.into_inner()) = Some(Arc::new(clock));
+}
+
+/// Go back to the [`SystemClock`].
+pub fn reset_clock() {
+    *CLOCK.write().unwrap_or_else(|poison| poison.into_inner()) = None;
+}
+
+/// The current time according to the installed clock.
+pub fn now() -> SystemTime {
+    // The clock is cloned out so that it can itself install a clock.
+    let clock = CLOCK
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    match clock {
+        Some(clock) => clock.now(),
+        None => SystemTime::now(),
+    }
+}
+
+/// The current monotonic instant according to the installed clock.
+pub fn monotonic() -> Instant {
+    // The clock is cloned out so that it can itself install a clock.
+    let clock = CLOCK
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    match clock {
+        Some(clock) => clock.monotonic(),
+        None => Instant::now(),
+    }
+}
@@ -0,0 +1,82 @@
+//! [`code.*`](https://opentelemetry.io/docs/specs/semconv/registry/attributes/code/)
+//! attributes locating where a report was created.
+//!
+//! The file path, line and column are taken from the [`Location`] attachment
+//! collected by rootcause's location hook, so that backends can link to the
+//! source without parsing `exception.stacktrace`. The function name is not
+//! known to the hook, and is emitted when attached with
+//! [`CodeReportExt::attach_code_function`].
+
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    Report, ReportRef,
+    hooks::builtin_hooks::location::Location,
+    markers::{Dynamic, Local, Mutable, ObjectMarkerFor, Uncloneable},
+};
+
+use crate::{attachments::Hidden, utilities::AttachmentsExt};
+
+/// The fully-qualified name of the function a report was created in, see
+/// [`CodeReportExt::attach_code_function`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeFunction(pub &'static str);
+
+/// The fully-qualified name of the enclosing function, as a [`CodeFunction`].
+///
+/// ```
+/// use rootcause_opentelemetry::code_function;
+///
+/// fn load_config() -> &'static str {
+///     code_function!().0
+/// }
+/// assert!(load_config().ends_with("::load_config"));
+/// ```
+#[macro_export]
+macro_rules! code_function {
+    () => {{
+        fn f() {}
+        let name = ::std::any::type_name_of_val(&f);
+        $crate::code::CodeFunction(name.strip_suffix("::f").unwrap_or(name))
+    }};
+}
+
+/// Extension trait for [`Report`]s, for locating them in the code.
+pub trait CodeReportExt: Sized {
+    /// Attach the name of the function the report is created in, emitted as
+    /// `code.function.name`, typically from [`code_function!`](crate::code_function).
+    fn attach_code_function(self, function: CodeFunction) -> Self;
+}
+
+impl<C: ?Sized, T> CodeReportExt for Report<C, Mutable, T>
+where
+    CodeFunction: ObjectMarkerFor<T>,
+{
+    fn attach_code_function(self, function: CodeFunction) -> Self {
+        self.attach_custom::<Hidden, _>(function)
+    }
+}
+
+/// The `code.*` attributes of `rep` itself, from its [`Location`] and
+/// [`CodeFunction`] attachments.
+pub(crate) fn attributes(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<KeyValue> {
+    let mut attributes = Vec::new();
+    if let Some(location) = rep.attachments().find_attachment_inner::<Location>() {
+        attributes.push(KeyValue::new(
+            attribute::CODE_FILE_PATH,
+            location.file().to_owned(),
+        ));
+        attributes.push(KeyValue::new(
+            attribute::CODE_LINE_NUMBER,
+            i64::from(location.line()),
+        ));
+        attributes.push(KeyValue::new(
+            attribute::CODE_COLUMN_NUMBER,
+            i64::from(location.column()),
+        ));
+    }
+    if let Some(function) = rep.attachments().find_attachment_inner::<CodeFunction>() {
+        attributes.push(KeyValue::new(attribute::CODE_FUNCTION_NAME, function.0));
+    }
+    attributes
+}
@@ -0,0 +1,76 @@
+//! Conversion of attachments into typed attributes.
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use opentelemetry::KeyValue;
+use rootcause::{markers::Dynamic, report_attachment::ReportAttachmentRef};
+
+type ConversionFn = Arc<dyn Fn(ReportAttachmentRef<'_, Dynamic>) -> Vec<KeyValue> + Send + Sync>;
+
+static REGISTRY: LazyLock<RwLock<HashMap<TypeId, ConversionFn>>> = LazyLock::new(Default::default);
+
+/// Attachment types describing how they become attributes, e.g. a
+/// `RequestInfo` attachment becoming `http.request.method` and `url.path`.
+///
+/// Attachments are type-erased, so implementations only take effect once
+/// registered with [`register_attributes`]. Registered attachments are emitted
+/// as attributes, see [`ExceptionEventSpec::attachment_attributes`](crate::spec::ExceptionEventSpec::attachment_attributes),
+/// and as maps of their attributes in structured bodies, instead of as their
+/// formatted value.
+pub trait IntoOtelAttributes: 'static {
+    /// Prefix of the keys of [`Self::otel_attributes`], joined to them by a `.`.
+    fn namespace(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The attributes describing this attachment.
+    fn otel_attributes(&self) -> Vec<KeyValue>;
+}
+
+/// Convert attachments of type `A` using its [`IntoOtelAttributes`] implementation.
+pub fn register_attributes<A: IntoOtelAttributes>() {
+    register_attributes_with::<A>(|attachment| {
+        let attributes = attachment.otel_attributes();
+        match attachment.namespace() {
+            Some(namespace) => attributes
+                .into_iter()
+                .map(|kv| KeyValue::new(format!("{namespace}.{}", kv.key), kv.value))
+                .collect(),
+            None => attributes,
+        }
+    });
+}
+
+/// Convert attachments of type `A` using `convert`, for foreign types which
+/// cannot implement [`IntoOtelAttributes`].
+pub fn register_attributes_with<A: 'static>(
+    convert: impl Fn(&A) -> Vec<KeyValue> + Send + Sync + 'static,
+) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .insert(
+            TypeId::of::<A>(),
+            Arc::new(move |attachment| {
+                attachment
+                    .downcast_inner::<A>()
+                    .map(&convert)
+                    .unwrap_or_default()
+            }),
+        );
+}
+
+/// The attributes registered for the type of `attachment`, if any.
+pub(crate) fn attributes_of(attachment: ReportAttachmentRef<'_, Dynamic>) -> Option<Vec<KeyValue>> {
+    // The conversion is cloned out so that it can itself register conversions.
+    let convert = REGISTRY
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .get(&attachment.inner_type_id())
+        .cloned();
+    convert.map(|convert| convert(attachment))
+}
@@ -0,0 +1,153 @@
+//! Correlation data captured at report creation, combining the span context
+//! and selected baggage in a single attachment.
+
+use std::borrow::Cow;
+
+use opentelemetry::{
+    Context, KeyValue,
+    baggage::BaggageExt,
+    trace::{SpanContext, TraceContextExt, TraceFlags},
+};
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::{
+    attachments::Hidden, baggage::BaggageSnapshot, traceparent::RemoteSpanContext,
+    utilities::AttachmentsExt,
+};
+
+/// The span context and selected baggage entries of the context a report was
+/// created in.
+///
+/// The span, log and metric emitters look these up through a single
+/// attachment, falling back to individual [`SpanContext`] and
+/// [`BaggageSnapshot`] attachments for reports without one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationInfo {
+    pub span_context: SpanContext,
+    pub baggage: Vec<KeyValue>,
+}
+
+impl CorrelationInfo {
+    /// Capture the span context of the current span, and the entries of the
+    /// current baggage selected by `baggage`.
+    pub fn capture(baggage: &BaggageSelection) -> Self {
+        let ctx = Context::current();
+        let entries = match baggage {
+            BaggageSelection::None => Vec::new(),
+            BaggageSelection::All => ctx
+                .baggage()
+                .iter()
+                .map(|(key, (value, _))| KeyValue::new(key.clone(), value.clone()))
+                .collect(),
+            BaggageSelection::Keys(keys) => keys
+                .iter()
+                .filter_map(|key| {
+                    ctx.baggage()
+                        .get(key.as_ref())
+                        .map(|value| KeyValue::new(key.clone(), value.clone()))
+                })
+                .collect(),
+        };
+        Self {
+            span_context: ctx.span().span_context().clone(),
+            baggage: entries,
+        }
+    }
+
+    /// The captured span context, if it is valid.
+    pub fn span_context(&self) -> Option<&SpanContext> {
+        self.span_context.is_valid().then_some(&self.span_context)
+    }
+
+    /// The trace flags of the captured span context.
+    pub fn trace_flags(&self) -> TraceFlags {
+        self.span_context.trace_flags()
+    }
+
+    /// The captured baggage entries.
+    pub fn baggage(&self) -> &[KeyValue] {
+        &self.baggage
+    }
+}
+
+/// Which baggage entries a [`CorrelationCollector`] captures.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BaggageSelection {
+    /// No entries.
+    #[default]
+    None,
+    /// All entries.
+    All,
+    /// The entries with these keys.
+    Keys(Vec<Cow<'static, str>>),
+}
+
+/// Report creation hook attaching a [`CorrelationInfo`] to reports created
+/// in a span or in a context with selected baggage.
+#[derive(Debug, Default, Clone)]
+pub struct CorrelationCollector {
+    baggage: BaggageSelection,
+}
+
+impl CorrelationCollector {
+    /// Capture the span context only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture the baggage entries selected by `baggage` as well.
+    pub fn baggage(mut self, baggage: BaggageSelection) -> Self {
+        self.baggage = baggage;
+        self
+    }
+
+    fn capture(&self) -> Option<CorrelationInfo> {
+        let info = CorrelationInfo::capture(&self.baggage);
+        (info.span_context().is_some() || !info.baggage.is_empty()).then_some(info)
+    }
+}
+
+impl ReportCreationHook for CorrelationCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        if let Some(info) = self.capture() {
+            let _ = report.attach_custom::<Hidden, _>(info);
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        if let Some(info) = self.capture() {
+            let _ = report.attach_custom::<Hidden, _>(info);
+        }
+    }
+}
+
+/// The span context a single report originated in, from its [`RemoteSpanContext`],
+/// [`CorrelationInfo`] or [`SpanContext`] attachment.
+pub(crate) fn span_context<A: AttachmentsExt + ?Sized>(attachments: &A) -> Option<&SpanContext> {
+    attachments
+        .find_attachment_inner::<RemoteSpanContext>()
+        .map(|remote| &remote.0)
+        .or_else(|| {
+            attachments
+                .find_attachment_inner::<CorrelationInfo>()
+                .and_then(CorrelationInfo::span_context)
+        })
+        .or_else(|| attachments.find_attachment_inner::<SpanContext>())
+}
+
+/// The baggage entries captured for a single report, from its [`CorrelationInfo`]
+/// or [`BaggageSnapshot`] attachment.
+pub(crate) fn baggage<A: AttachmentsExt + ?Sized>(attachments: &A) -> Option<Vec<KeyValue>> {
+    if let Some(info) = attachments.find_attachment_inner::<CorrelationInfo>()
+        && !info.baggage.is_empty()
+    {
+        return Some(info.baggage.clone());
+    }
+    attachments
+        .find_attachment_inner::<BaggageSnapshot>()
+        .map(|snapshot| snapshot.attributes().collect())
+}
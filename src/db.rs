@@ -0,0 +1,107 @@
+//! [Database semantic conventions](https://opentelemetry.io/docs/specs/semconv/database/database-spans/)
+//! for reports of failed queries, so that they line up with the spans of
+//! database client instrumentations.
+//!
+//! Context types implementing [`DbError`] and [registered](register_db_error)
+//! get a canonical `error.type`, such as `db.unique_violation`, along with the
+//! `db.system.name` and `db.response.status_code` attributes. With the `sqlx`
+//! feature, `sqlx::Error` is supported through [`register_sqlx_error`].
+
+use std::borrow::Cow;
+
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions::attribute;
+
+use crate::error_type::{register_attributes_with, register_error_type_with};
+
+/// Context types describing a failed database operation.
+///
+/// Report contexts are type-erased, so implementations only take effect
+/// once registered with [`register_db_error`].
+pub trait DbError: 'static {
+    /// The canonical `error.type` of the failure, such as `db.row_not_found`.
+    fn db_error_type(&self) -> Cow<'static, str>;
+
+    /// The `db.system.name` of the database, such as `postgresql`.
+    fn db_system(&self) -> Option<Cow<'static, str>> {
+        None
+    }
+
+    /// The status code returned by the database, such as a SQLSTATE.
+    fn db_status_code(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Use the [`DbError`] implementation of `C` for reports whose current
+/// context is a `C`.
+pub fn register_db_error<C: DbError>() {
+    register_db_error_for_system::<C>(None);
+}
+
+/// Register `C` as in [`register_db_error`], with `system` as the
+/// `db.system.name` when `C` does not know it.
+fn register_db_error_for_system<C: DbError>(system: Option<&'static str>) {
+    register_error_type_with::<C>(C::db_error_type);
+    register_attributes_with::<C>(move |error| {
+        let mut attributes = Vec::new();
+        if let Some(system) = error.db_system().or(system.map(Cow::Borrowed)) {
+            attributes.push(KeyValue::new(attribute::DB_SYSTEM_NAME, system));
+        }
+        if let Some(status_code) = error.db_status_code() {
+            attributes.push(KeyValue::new(
+                attribute::DB_RESPONSE_STATUS_CODE,
+                status_code,
+            ));
+        }
+        attributes
+    });
+}
+
+/// Register `sqlx::Error` as a [`DbError`] of the database `system`, the
+/// `db.system.name` such as `postgresql`, which its errors do not carry.
+///
+/// ```
+/// rootcause_opentelemetry::db::register_sqlx_error("postgresql");
+/// ```
+#[cfg(feature = "sqlx")]
+pub fn register_sqlx_error(system: &'static str) {
+    register_db_error_for_system::<sqlx::Error>(Some(system));
+}
+
+#[cfg(feature = "sqlx")]
+impl DbError for sqlx::Error {
+    fn db_error_type(&self) -> Cow<'static, str> {
+        use sqlx::error::ErrorKind;
+
+        Cow::Borrowed(match self {
+            Self::Database(error) => match error.kind() {
+                ErrorKind::UniqueViolation => "db.unique_violation",
+                ErrorKind::ForeignKeyViolation => "db.foreign_key_violation",
+                ErrorKind::NotNullViolation => "db.not_null_violation",
+                ErrorKind::CheckViolation => "db.check_violation",
+                _ => "db.database_error",
+            },
+            Self::RowNotFound => "db.row_not_found",
+            Self::PoolTimedOut => "db.pool_timed_out",
+            Self::PoolClosed => "db.pool_closed",
+            Self::WorkerCrashed => "db.worker_crashed",
+            Self::Io(_) => "db.io",
+            Self::Tls(_) => "db.tls",
+            Self::Protocol(_) => "db.protocol",
+            Self::Configuration(_) => "db.configuration",
+            Self::ColumnNotFound(_) | Self::ColumnIndexOutOfBounds { .. } => "db.column_not_found",
+            Self::TypeNotFound { .. } => "db.type_not_found",
+            Self::ColumnDecode { .. } | Self::Decode(_) => "db.decode",
+            Self::Encode(_) => "db.encode",
+            _ => "db.other",
+        })
+    }
+
+    fn db_status_code(&self) -> Option<String> {
+        match self {
+            Self::Database(error) => error.code().map(Cow::into_owned),
+            _ => None,
+        }
+    }
+}
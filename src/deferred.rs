@@ -0,0 +1,100 @@
+//! Emission of reports after an await point, in whichever context is current then.
+//!
+//! A [`RecordErrorReport`](crate::span_event::RecordErrorReport) borrows both
+//! the span and the report, so it cannot be held across an `.await` in a task
+//! which must be `Send`. A [`DeferredEmit`] instead snapshots what the
+//! `exception` event is made of when created, so that the report need not
+//! outlive it nor be `Send`, and looks up the current span only when finalized.
+
+use std::time::SystemTime;
+
+use opentelemetry::{
+    Context, KeyValue,
+    trace::{TraceContextExt, TraceId},
+};
+
+use crate::{
+    pipeline::{self, Destination, ExceptionSnapshot},
+    sampling,
+    spec::ExceptionEventSpec,
+    utilities::{
+        AsReportRef, EXCEPTION, forward_to, origin_trace_ids, sampled_attributes, timestamp,
+    },
+};
+
+/// Snapshot the `exception` event of `rep`, to be recorded when
+/// [`DeferredEmit::finalize`] is awaited, with the
+/// [global `ExceptionEventSpec`](ExceptionEventSpec::install).
+pub fn defer_emit(rep: &impl AsReportRef) -> DeferredEmit {
+    defer_emit_with_spec(rep, &ExceptionEventSpec::global())
+}
+
+/// As [`defer_emit`], with the given [`ExceptionEventSpec`].
+///
+/// The report is only at hand now, so the [`ReportSampler`](crate::sampling::ReportSampler)
+/// and the [`EmitLayer`](crate::pipeline::EmitLayer)s see it now, and the
+/// report counts as emitted by the [bridge metrics](crate::meter::enable_bridge_metrics)
+/// unless they drop it. Only the `rootcause.forward_to_trace_ids` attribute,
+/// which depends on the span, is added when finalized, after the others.
+pub fn defer_emit_with_spec(rep: &impl AsReportRef, spec: &ExceptionEventSpec) -> DeferredEmit {
+    let rep = rep.as_report_ref();
+    let event = sampled_attributes(rep, spec, sampling::decide(rep), true).and_then(|attributes| {
+        // The trace ids to forward to are only known once the span is.
+        let layer_spec = spec.clone().forward_trace_ids(false);
+        pipeline::process(
+            ExceptionSnapshot::new(rep, Destination::SpanEvent, timestamp(rep), attributes),
+            &layer_spec,
+            Context::current().span().span_context(),
+        )
+        .map(|snapshot| DeferredEvent {
+            timestamp: snapshot.timestamp,
+            attributes: snapshot.attributes,
+            origin_trace_ids: if spec.forward_trace_ids {
+                origin_trace_ids(rep)
+            } else {
+                Vec::new()
+            },
+        })
+    });
+    DeferredEmit { event }
+}
+
+/// An `exception` event awaiting emission, which is `Send + 'static` and can
+/// therefore be held across await points.
+///
+/// See [`defer_emit`].
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct DeferredEmit {
+    /// `None` if the sampler or a layer dropped the event.
+    event: Option<DeferredEvent>,
+}
+
+#[derive(Debug, Clone)]
+struct DeferredEvent {
+    timestamp: SystemTime,
+    attributes: Vec<KeyValue>,
+    origin_trace_ids: Vec<TraceId>,
+}
+
+impl DeferredEmit {
+    /// Record the `exception` event on the span current when this is awaited,
+    /// e.g. within [`FutureExt::with_context`](opentelemetry::trace::FutureExt::with_context),
+    /// as in [`RecordErrorReport::as_event`](crate::span_event::RecordErrorReport::as_event).
+    pub async fn finalize(self) {
+        let Some(mut event) = self.event else {
+            return;
+        };
+        let ctx = Context::current();
+        let span = ctx.span();
+        if !span.is_recording() {
+            #[cfg(feature = "metrics")]
+            crate::meter::record_dropped(crate::meter::DropReason::NotRecording);
+            return;
+        }
+        if let Some(kv) = forward_to(&event.origin_trace_ids, span.span_context()) {
+            event.attributes.push(kv);
+        }
+        span.add_event_with_timestamp(EXCEPTION, event.timestamp, event.attributes);
+    }
+}
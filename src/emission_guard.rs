@@ -0,0 +1,141 @@
+//! Coordination between holders of clones of the same report, which would
+//! otherwise each emit it.
+//!
+//! Clones of a [`Cloneable`](rootcause::markers::Cloneable) report share their
+//! attachments, so an [`EmissionGuard`] attached at creation by the
+//! [`EmissionGuardCollector`] is seen by all of them. The [`DeduplicateLayer`]
+//! then decides, per destination, what happens to every emission but the first.
+
+use std::{
+    ops::ControlFlow,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use opentelemetry::KeyValue;
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::{
+    attachments::Hidden,
+    pipeline::{Destination, EmitLayer, ExceptionSnapshot},
+    utilities::AttachmentsExt,
+};
+
+pub const EXCEPTION_DUPLICATE: &str = "exception.duplicate";
+
+/// Attachment recording to which [`Destination`]s a report has been emitted.
+#[derive(Debug, Default)]
+pub struct EmissionGuard {
+    emitted: AtomicU8,
+}
+
+impl EmissionGuard {
+    /// Mark the report as emitted to `destination`, returning whether it already was.
+    pub fn mark_emitted(&self, destination: Destination) -> bool {
+        let bit = 1 << destination as u8;
+        self.emitted.fetch_or(bit, Ordering::AcqRel) & bit != 0
+    }
+
+    /// Undo [`Self::mark_emitted`] for an emission which was aborted after all.
+    fn release(&self, destination: Destination) {
+        let bit = 1 << destination as u8;
+        self.emitted.fetch_and(!bit, Ordering::AcqRel);
+    }
+}
+
+/// Report creation hook attaching an [`EmissionGuard`] to new reports.
+///
+/// By default only thread-safe reports, which can be emitted from multiple
+/// tasks concurrently, are guarded.
+#[derive(Debug, Clone, Copy)]
+pub struct EmissionGuardCollector {
+    local: bool,
+}
+
+impl Default for EmissionGuardCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmissionGuardCollector {
+    /// Guard thread-safe reports only.
+    pub fn new() -> Self {
+        Self { local: false }
+    }
+
+    /// Guard thread-local reports as well.
+    pub fn including_local(mut self) -> Self {
+        self.local = true;
+        self
+    }
+}
+
+impl ReportCreationHook for EmissionGuardCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        if self.local {
+            let _ = report.attach_custom::<Hidden, _>(EmissionGuard::default());
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        let _ = report.attach_custom::<Hidden, _>(EmissionGuard::default());
+    }
+}
+
+/// What the [`DeduplicateLayer`] does with repeated emissions of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Only the first emission to each destination goes through.
+    Skip,
+    /// All emissions go through, with the repeated ones having the
+    /// `exception.duplicate` attribute set to `true`.
+    Mark,
+}
+
+/// [`EmitLayer`] applying a [`DuplicatePolicy`] to reports guarded by an [`EmissionGuard`].
+///
+/// Reports without a guard are always emitted. An emission only counts once
+/// every layer let it through: if a later layer, such as a rate limiter,
+/// aborts it, the next emission of the report is treated as the first.
+#[derive(Debug, Clone, Copy)]
+pub struct DeduplicateLayer {
+    policy: DuplicatePolicy,
+}
+
+impl DeduplicateLayer {
+    pub fn new(policy: DuplicatePolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl EmitLayer for DeduplicateLayer {
+    fn on_emit(&self, snapshot: &mut ExceptionSnapshot<'_>) -> ControlFlow<()> {
+        let Some(guard) = snapshot.report.find_attachment_inner::<EmissionGuard>() else {
+            return ControlFlow::Continue(());
+        };
+        if !guard.mark_emitted(snapshot.destination) {
+            snapshot.claimed = true;
+            return ControlFlow::Continue(());
+        }
+
+        match self.policy {
+            DuplicatePolicy::Skip => ControlFlow::Break(()),
+            DuplicatePolicy::Mark => {
+                snapshot.set_attribute(KeyValue::new(EXCEPTION_DUPLICATE, true));
+                ControlFlow::Continue(())
+            }
+        }
+    }
+}
+
+/// Undo the marking of `snapshot`'s report as emitted by a [`DeduplicateLayer`],
+/// as a later layer aborted the emission.
+pub(crate) fn release(snapshot: &ExceptionSnapshot<'_>) {
+    if let Some(guard) = snapshot.report.find_attachment_inner::<EmissionGuard>() {
+        guard.release(snapshot.destination);
+    }
+}
@@ -0,0 +1,291 @@
+//! Stable, low-cardinality `error.type` values for report context types.
+//!
+//! [`std::io::Error`]s are mapped out of the box: a report whose context, or
+//! that of its first descendant with one, is an `io::Error` has the `error.type`
+//! `io.<kind>`, such as `io.not_found` or `io.timed_out`, and an [`IO_ERROR_KIND`]
+//! attribute, unless an `error.type` is registered for its own context type.
+
+use std::{
+    any::TypeId,
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use opentelemetry::KeyValue;
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+pub const IO_ERROR_KIND: &str = "io.error.kind";
+
+type ErrorTypeFn = Arc<
+    dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<Cow<'static, str>> + Send + Sync,
+>;
+
+type AttributesFn =
+    Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<KeyValue> + Send + Sync>;
+
+static REGISTRY: LazyLock<RwLock<HashMap<TypeId, ErrorTypeFn>>> = LazyLock::new(Default::default);
+
+static ATTRIBUTES: LazyLock<RwLock<HashMap<TypeId, AttributesFn>>> =
+    LazyLock::new(Default::default);
+
+/// Context types with a stable `error.type`, such as `db.timeout`, to use
+/// instead of their Rust type name, whose module paths and generics make for
+/// poor dashboard dimensions.
+///
+/// Report contexts are type-erased, so implementations only take effect
+/// once registered with [`register_error_type`].
+///
+/// The `error.type` is set by [`with_error_status`](crate::span_event::RecordErrorReport::with_error_status),
+/// falling back to the type name, and added to exception events and log records
+/// of reports whose context type has one.
+pub trait OtelErrorType: 'static {
+    fn otel_error_type(&self) -> Cow<'static, str>;
+
+    /// Attributes added alongside the `error.type` on exception events and
+    /// log records, such as `db.system`.
+    fn otel_attributes(&self) -> Vec<KeyValue> {
+        Vec::new()
+    }
+}
+
+/// Use the [`OtelErrorType`] implementation of `C` for reports whose current
+/// context is a `C`.
+pub fn register_error_type<C: OtelErrorType>() {
+    register_error_type_with::<C>(C::otel_error_type);
+    register_attributes_with::<C>(C::otel_attributes);
+}
+
+/// Context types whose telemetry implementations are registered together,
+/// as generated by `#[derive(OtelError)]` with the `derive` feature.
+pub trait RegisterOtelError: 'static {
+    /// Register the [`OtelErrorType`] implementation, and the other ones the
+    /// type has, such as its severity.
+    fn register();
+}
+
+/// Register the telemetry implementations of `C`, see [`RegisterOtelError`].
+pub fn register_otel_error<C: RegisterOtelError>() {
+    C::register();
+}
+
+/// Use `error_type` for reports whose current context is a `C`, for types
+/// which cannot implement [`OtelErrorType`].
+pub fn register_error_type_with<C: 'static>(
+    error_type: impl Fn(&C) -> Cow<'static, str> + Send + Sync + 'static,
+) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .insert(
+            TypeId::of::<C>(),
+            Arc::new(move |rep| rep.downcast_current_context::<C>().map(&error_type)),
+        );
+}
+
+/// The `error.type` registered for the context type of `rep`, or the one of
+/// its `io::Error`, if any.
+pub(crate) fn registered_error_type(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+) -> Option<Cow<'static, str>> {
+    registered_context_error_type(rep)
+        .or_else(|| io_error_kind(rep).map(|kind| format!("io.{kind}").into()))
+}
+
+/// The `error.type` registered for the context type of `rep` itself, if any.
+pub(crate) fn registered_context_error_type(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+) -> Option<Cow<'static, str>> {
+    // The function is cloned out so that it can itself emit or register error types.
+    let error_type = REGISTRY
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .get(&rep.current_context_type_id())
+        .cloned();
+    error_type.and_then(|error_type| error_type(rep))
+}
+
+/// The attributes registered alongside the `error.type` for the context type of `rep`,
+/// and the [`IO_ERROR_KIND`] of its `io::Error`.
+pub(crate) fn registered_attributes(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+) -> Vec<KeyValue> {
+    let registered = ATTRIBUTES
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .get(&rep.current_context_type_id())
+        .cloned();
+    let mut attributes = registered
+        .map(|attributes| attributes(rep))
+        .unwrap_or_default();
+    if let Some(kind) = io_error_kind(rep) {
+        attributes.push(KeyValue::new(IO_ERROR_KIND, kind));
+    }
+    attributes
+}
+
+/// The [`io::ErrorKind`] of the first `io::Error` context in the report tree,
+/// in snake case.
+fn io_error_kind(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<String> {
+    let kind = rep
+        .iter_reports()
+        .find_map(|r| r.downcast_current_context::<io::Error>())?
+        .kind();
+    Some(snake_case(&format!("{kind:?}")))
+}
+
+/// `CamelCase` words in snake case, keeping acronyms together, so that
+/// `IOError` becomes `io_error`.
+fn snake_case(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut snake = String::with_capacity(text.len() + 4);
+    for (index, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next_is_lower = chars.get(index + 1).is_some_and(char::is_ascii_lowercase);
+            if previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+static NORMALIZER: RwLock<TypeNameNormalizer> = RwLock::new(TypeNameNormalizer::new());
+
+/// Normalization of the Rust type names emitted as `exception.type` and as
+/// the fallback `error.type`, whose module paths and generic parameters, as in
+/// `alloc::boxed::Box<dyn core::error::Error + Send + Sync>`, make for
+/// high-cardinality metric dimensions and alert keys.
+///
+/// Applied once [installed](Self::install), and leaves type names as they are
+/// by default.
+///
+/// ```
+/// use rootcause_opentelemetry::error_type::TypeNameNormalizer;
+///
+/// let normalizer = TypeNameNormalizer::new().strip_module_paths(true);
+/// assert_eq!(normalizer.normalize("alloc::boxed::Box<dyn core::error::Error>"), "Box<dyn Error>");
+///
+/// let normalizer = normalizer.strip_generics(true).snake_case(true);
+/// assert_eq!(normalizer.normalize("my_app::db::QueryTimeout<u64>"), "query_timeout");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TypeNameNormalizer {
+    strip_module_paths: bool,
+    strip_generics: bool,
+    snake_case: bool,
+}
+
+impl TypeNameNormalizer {
+    /// The normalizer leaving type names as they are.
+    pub const fn new() -> Self {
+        Self {
+            strip_module_paths: false,
+            strip_generics: false,
+            snake_case: false,
+        }
+    }
+
+    /// Whether to keep only the last segment of paths, e.g. `Error` for
+    /// `std::io::Error`.
+    pub const fn strip_module_paths(mut self, enabled: bool) -> Self {
+        self.strip_module_paths = enabled;
+        self
+    }
+
+    /// Whether to drop generic parameters, e.g. `Box` for `Box<dyn Error>`.
+    pub const fn strip_generics(mut self, enabled: bool) -> Self {
+        self.strip_generics = enabled;
+        self
+    }
+
+    /// Whether to convert `CamelCase` type names to snake case, e.g.
+    /// `query_timeout` for `QueryTimeout`.
+    pub const fn snake_case(mut self, enabled: bool) -> Self {
+        self.snake_case = enabled;
+        self
+    }
+
+    /// The process-wide normalizer set by [`Self::install`], or [`Self::new`] if
+    /// none was installed.
+    pub fn global() -> Self {
+        *NORMALIZER
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    /// Make this the process-wide normalizer, returning the previous one.
+    pub fn install(self) -> Self {
+        let mut global = NORMALIZER
+            .write()
+            .unwrap_or_else(|poison| poison.into_inner());
+        std::mem::replace(&mut *global, self)
+    }
+
+    pub fn normalize<'a>(&self, type_name: &'a str) -> Cow<'a, str> {
+        let mut name = Cow::Borrowed(type_name);
+        if self.strip_generics
+            && let Some(start) = name.find('<')
+        {
+            name = Cow::Owned(name[..start].to_owned());
+        }
+        if self.strip_module_paths && name.contains("::") {
+            let mut stripped = String::with_capacity(name.len());
+            let mut path = String::new();
+            for c in name.chars().chain(std::iter::once(' ')) {
+                if c.is_alphanumeric() || c == '_' || c == ':' {
+                    path.push(c);
+                } else {
+                    stripped.push_str(path.rsplit("::").next().unwrap_or_default());
+                    path.clear();
+                    stripped.push(c);
+                }
+            }
+            stripped.pop();
+            name = Cow::Owned(stripped);
+        }
+        if self.snake_case {
+            name = Cow::Owned(snake_case(&name));
+        }
+        name
+    }
+}
+
+/// The type name of the current context of `rep`, normalized by the
+/// [global](TypeNameNormalizer::global) [`TypeNameNormalizer`].
+pub(crate) fn type_name(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Cow<'static, str> {
+    TypeNameNormalizer::global().normalize(rep.current_context_type_name())
+}
+
+/// Add `attributes` to the exception events and log records of reports whose
+/// current context is a `C`.
+pub(crate) fn register_attributes_with<C: 'static>(
+    attributes: impl Fn(&C) -> Vec<KeyValue> + Send + Sync + 'static,
+) {
+    ATTRIBUTES
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .insert(
+            TypeId::of::<C>(),
+            Arc::new(move |rep| {
+                rep.downcast_current_context::<C>()
+                    .map(&attributes)
+                    .unwrap_or_default()
+            }),
+        );
+}
+
+/// The `error.type` of `rep`: the one registered for its context type, falling
+/// back to the [normalized](TypeNameNormalizer) type name.
+pub(crate) fn error_type_of(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Cow<'static, str> {
+    registered_error_type(rep).unwrap_or_else(|| type_name(rep))
+}
@@ -0,0 +1,90 @@
+//! Measurements in error paths carrying exemplars which point at the failing trace.
+
+use opentelemetry::{
+    Context, KeyValue,
+    metrics::{Counter, Histogram},
+    trace::TraceContextExt,
+};
+
+use crate::{span_event::target_span, utilities::AsReportRef};
+
+/// Extension trait for [`Report`](rootcause::Report)s and references to them,
+/// for recording metric measurements in the context of the report.
+///
+/// Metric SDKs take exemplars from the span of the current [`Context`], which in
+/// an error path is often no longer the span the error originated in, if any.
+pub trait ExemplarReportExt: AsReportRef {
+    /// The current [`Context`] with its span replaced by the one the report
+    /// is emitted to, as given by its [`TargetSpan`](crate::span_event::TargetSpan),
+    /// or else the one it originated in, as given by the [`CorrelationInfo`](crate::correlation::CorrelationInfo)
+    /// or [`SpanContext`](opentelemetry::trace::SpanContext) attachment of the
+    /// outermost report in the tree having one, or the current context if there is none.
+    ///
+    /// [`SpanContext`](opentelemetry::trace::SpanContext) attachments are
+    /// provided report creation hook [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector).
+    fn exemplar_context(&self) -> Context;
+
+    /// Run `measure` with the [`Self::exemplar_context`] as the current context,
+    /// so that measurements it records carry exemplars pointing at the failing trace.
+    ///
+    /// ```
+    /// # use opentelemetry::global;
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::exemplar::ExemplarReportExt;
+    /// let failures = global::meter("checkout").u64_counter("checkout.failures").build();
+    /// let report = report!("payment declined");
+    /// report.in_exemplar_context(|| failures.add(1, &[]));
+    /// ```
+    fn in_exemplar_context<T>(&self, measure: impl FnOnce() -> T) -> T {
+        let _guard = self.exemplar_context().attach();
+        measure()
+    }
+}
+
+impl<R: AsReportRef> ExemplarReportExt for R {
+    fn exemplar_context(&self) -> Context {
+        let rep = self.as_report_ref();
+        match target_span(rep).or_else(|| {
+            rep.iter_reports()
+                .find_map(|r| crate::correlation::span_context(r.attachments()))
+        }) {
+            Some(span_context) => Context::current().with_remote_span_context(span_context.clone()),
+            None => Context::current(),
+        }
+    }
+}
+
+/// Extension trait for [`Counter`]s, for incrementing error counters in the
+/// [exemplar context](ExemplarReportExt::exemplar_context) of a report.
+///
+/// ```
+/// # use opentelemetry::{KeyValue, global};
+/// # use rootcause::prelude::*;
+/// # use rootcause_opentelemetry::exemplar::CounterReportExt;
+/// let failures = global::meter("checkout").u64_counter("checkout.failures").build();
+/// let report = report!("payment declined");
+/// failures.add_for_report(&report, 1, &[KeyValue::new("payment.provider", "acme")]);
+/// ```
+pub trait CounterReportExt<T> {
+    /// Add `value` as [`Counter::add`] does, within the exemplar context of `rep`.
+    fn add_for_report(&self, rep: &impl AsReportRef, value: T, attributes: &[KeyValue]);
+}
+
+impl<T> CounterReportExt<T> for Counter<T> {
+    fn add_for_report(&self, rep: &impl AsReportRef, value: T, attributes: &[KeyValue]) {
+        rep.in_exemplar_context(|| self.add(value, attributes));
+    }
+}
+
+/// Extension trait for [`Histogram`]s, for recording measurements of failed
+/// operations in the [exemplar context](ExemplarReportExt::exemplar_context) of a report.
+pub trait HistogramReportExt<T> {
+    /// Record `value` as [`Histogram::record`] does, within the exemplar context of `rep`.
+    fn record_for_report(&self, rep: &impl AsReportRef, value: T, attributes: &[KeyValue]);
+}
+
+impl<T> HistogramReportExt<T> for Histogram<T> {
+    fn record_for_report(&self, rep: &impl AsReportRef, value: T, attributes: &[KeyValue]) {
+        rep.in_exemplar_context(|| self.record(value, attributes));
+    }
+}
@@ -0,0 +1,50 @@
+//! Telemetry representations of attachments, independent of how they are
+//! formatted in the report itself.
+
+use std::sync::{Arc, RwLock};
+
+use opentelemetry::StringValue;
+use rootcause::{markers::Dynamic, report_attachment::ReportAttachmentRef};
+
+type ExtraFn = Arc<dyn Fn(ReportAttachmentRef<'_, Dynamic>) -> Option<Extra> + Send + Sync>;
+
+static REGISTRY: RwLock<Vec<ExtraFn>> = RwLock::new(Vec::new());
+
+/// Attachment types controlling their entry in the `attachments` of a
+/// [structured body](crate::log_event::LoggerExt::emit_error_report_structured),
+/// instead of it being their formatted value.
+///
+/// Attachments are type-erased, so implementations only take effect once
+/// registered with [`register_extra`].
+pub trait ExtraEntry: 'static {
+    /// The value of the entry, or `None` to leave the attachment out.
+    fn extra(&self) -> Option<StringValue>;
+}
+
+/// Make structured bodies use the [`ExtraEntry`] implementation of `A` for
+/// attachments of type `A`.
+pub fn register_extra<A: ExtraEntry>() {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .push(Arc::new(|attachment| {
+            attachment
+                .downcast_inner::<A>()
+                .map(|inner| inner.extra().map_or(Extra::Omit, Extra::Value))
+        }));
+}
+
+pub(crate) enum Extra {
+    Value(StringValue),
+    Omit,
+}
+
+/// The registered [`ExtraEntry`] representation of `attachment`, if its type has one.
+pub(crate) fn extra_of(attachment: ReportAttachmentRef<'_, Dynamic>) -> Option<Extra> {
+    // The representations are cloned out so that they can themselves register ones.
+    let registry = REGISTRY
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    registry.iter().find_map(|extra| extra(attachment))
+}
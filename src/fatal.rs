@@ -0,0 +1,330 @@
+//! Emergency emission of a minimal exception record from signal or abort handlers.
+//!
+//! Nothing on the emission path allocates, formats, or takes a lock: the record
+//! is assembled from bytes pre-serialized by [`install_fatal_emitter`] into a
+//! static buffer, and written to a file descriptor with a single `write`.
+//! The record is a line holding an OTLP/JSON `ExportLogsServiceRequest` with a
+//! single log record, intended to be picked up by a log-tailing collector since
+//! the SDK pipeline cannot be trusted to run after a crash.
+
+use std::{
+    cell::UnsafeCell,
+    fs::File,
+    io::Write,
+    mem::ManuallyDrop,
+    os::fd::{FromRawFd, RawFd},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::SystemTime,
+};
+
+const BUFFER_LEN: usize = 4096;
+const STDERR: RawFd = 2;
+/// Digits of the largest timestamp, in nanoseconds as a `u128`.
+const MAX_TIMESTAMP_LEN: usize = 39;
+/// Room left in the buffer for the prefix, so that the fixed parts of the
+/// record always fit and the JSON is always closed.
+const MAX_PREFIX_LEN: usize = BUFFER_LEN - MAX_TIMESTAMP_LEN - TYPE_PREFIX.len() - SUFFIX.len();
+
+static EMITTER: OnceLock<FatalEmitter> = OnceLock::new();
+static BUFFER: FatalBuffer = FatalBuffer {
+    in_use: AtomicBool::new(false),
+    bytes: UnsafeCell::new([0; BUFFER_LEN]),
+};
+
+struct FatalBuffer {
+    in_use: AtomicBool,
+    bytes: UnsafeCell<[u8; BUFFER_LEN]>,
+}
+
+// SAFETY: `bytes` is only accessed by whoever swapped `in_use` from `false` to `true`.
+unsafe impl Sync for FatalBuffer {}
+
+#[derive(Debug)]
+struct FatalEmitter {
+    fd: RawFd,
+    prefix: Box<[u8]>,
+}
+
+const RESOURCE_PREFIX: &[u8] = br#"{"resourceLogs":[{"resource":{"attributes":["#;
+const TIMESTAMP_PREFIX: &[u8] = concat!(
+    r#"]},"scopeLogs":[{"scope":{"name":""#,
+    env!("CARGO_PKG_NAME"),
+    r#""},"logRecords":[{"timeUnixNano":""#
+)
+.as_bytes();
+const TYPE_PREFIX: &[u8] = br#"","severityNumber":21,"severityText":"FATAL","eventName":"exception","attributes":[{"key":"exception.type","value":{"stringValue":""#;
+const SUFFIX: &[u8] = b"\"}}]}]}]}]}\n";
+
+/// Configure where [`emit_fatal_minimal`] writes, and the resource attributes
+/// (e.g. `service.name`) to pre-serialize into every record.
+///
+/// Must be called during normal operation, before any signal handler may run.
+/// Only the first call has an effect; returns whether it was this one.
+/// Without it, records are written to standard error without resource attributes.
+///
+/// Resource attributes which would not leave room for the rest of the record
+/// in the fixed-size buffer are left out.
+pub fn install_fatal_emitter<'r>(
+    fd: RawFd,
+    resource: impl IntoIterator<Item = (&'r str, &'r str)>,
+) -> bool {
+    EMITTER.set(FatalEmitter::new(fd, resource)).is_ok()
+}
+
+impl FatalEmitter {
+    fn new<'r>(fd: RawFd, resource: impl IntoIterator<Item = (&'r str, &'r str)>) -> Self {
+        let mut prefix = RESOURCE_PREFIX.to_vec();
+        let mut attribute = Vec::new();
+        for (key, value) in resource {
+            attribute.clear();
+            if prefix.len() > RESOURCE_PREFIX.len() {
+                attribute.push(b',');
+            }
+            attribute.extend_from_slice(br#"{"key":""#);
+            push_escaped(&mut attribute, key);
+            attribute.extend_from_slice(br#"","value":{"stringValue":""#);
+            push_escaped(&mut attribute, value);
+            attribute.extend_from_slice(br#""}}"#);
+            if prefix.len() + attribute.len() + TIMESTAMP_PREFIX.len() <= MAX_PREFIX_LEN {
+                prefix.extend_from_slice(&attribute);
+            }
+        }
+        prefix.extend_from_slice(TIMESTAMP_PREFIX);
+
+        Self {
+            fd,
+            prefix: prefix.into_boxed_slice(),
+        }
+    }
+}
+
+/// Write a single `FATAL` exception record with the given `exception.type`.
+///
+/// Async-signal-safe: safe to call from signal handlers, abort hooks and
+/// panic handlers, even while other threads hold locks. If another thread is
+/// already emitting a fatal record, this one is skipped.
+pub fn emit_fatal_minimal(type_name: &str) {
+    if BUFFER.in_use.swap(true, Ordering::Acquire) {
+        return;
+    }
+
+    // SAFETY: `in_use` was swapped from `false` to `true` above, so this is the only access.
+    let buffer = unsafe { &mut *BUFFER.bytes.get() };
+    let emitter = EMITTER.get();
+    let len = assemble(
+        buffer,
+        emitter.map(|emitter| &*emitter.prefix),
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos()),
+        type_name,
+    );
+
+    let fd = emitter.map_or(STDERR, |emitter| emitter.fd);
+    // SAFETY: the descriptor is not closed, since the `File` is never dropped.
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let _ = file.write_all(&buffer[..len]);
+
+    BUFFER.in_use.store(false, Ordering::Release);
+}
+
+/// Write the record into `buffer`, returning its length.
+///
+/// The prefix is at most [`MAX_PREFIX_LEN`] long, so only the type name is
+/// ever truncated, and the record is always complete JSON.
+fn assemble(
+    buffer: &mut [u8; BUFFER_LEN],
+    prefix: Option<&[u8]>,
+    timestamp: u128,
+    type_name: &str,
+) -> usize {
+    let mut cursor = Cursor { buffer, len: 0 };
+    match prefix {
+        Some(prefix) => cursor.push(prefix),
+        None => {
+            cursor.push(RESOURCE_PREFIX);
+            cursor.push(TIMESTAMP_PREFIX);
+        }
+    }
+    cursor.push_decimal(timestamp);
+    cursor.push(TYPE_PREFIX);
+    cursor.push_sanitized(type_name, BUFFER_LEN - SUFFIX.len());
+    cursor.push(SUFFIX);
+    cursor.len
+}
+
+/// [`emit_fatal_minimal`] with the type name of `T`.
+pub fn emit_fatal_minimal_for<T: ?Sized>() {
+    emit_fatal_minimal(std::any::type_name::<T>());
+}
+
+struct Cursor<'b> {
+    buffer: &'b mut [u8; BUFFER_LEN],
+    len: usize,
+}
+
+impl Cursor<'_> {
+    fn push(&mut self, bytes: &[u8]) {
+        let n = bytes.len().min(BUFFER_LEN - self.len);
+        self.buffer[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+    }
+
+    /// Push `text` up to `limit`, cut at a character boundary, replacing
+    /// anything needing JSON escaping.
+    fn push_sanitized(&mut self, text: &str, limit: usize) {
+        let mut end = text.len().min(limit.saturating_sub(self.len));
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        for &byte in &text.as_bytes()[..end] {
+            self.buffer[self.len] = match byte {
+                b'"' | b'\\' | ..0x20 => b'_',
+                byte => byte,
+            };
+            self.len += 1;
+        }
+    }
+
+    fn push_decimal(&mut self, mut value: u128) {
+        let mut digits = [0u8; 39];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        self.push(&digits[start..]);
+    }
+}
+
+fn push_escaped(out: &mut Vec<u8>, text: &str) {
+    for c in text.chars() {
+        match c {
+            '"' => out.extend_from_slice(br#"\""#),
+            '\\' => out.extend_from_slice(br"\\"),
+            c if (c as u32) < 0x20 => {
+                out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes())
+            }
+            c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, ErrorKind, Read},
+        os::{fd::AsRawFd, unix::net::UnixStream},
+    };
+
+    use serde_json::Value;
+
+    use super::*;
+
+    fn record(prefix: Option<&[u8]>, type_name: &str) -> (usize, Value) {
+        let mut buffer = [0; BUFFER_LEN];
+        let len = assemble(&mut buffer, prefix, 1_700_000_000_000_000_000, type_name);
+        assert!(buffer[..len].ends_with(SUFFIX));
+        let json = serde_json::from_slice(&buffer[..len]).expect("record is not valid JSON");
+        (len, json)
+    }
+
+    fn log_record(json: &Value) -> &Value {
+        &json["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0]
+    }
+
+    fn exception_type(json: &Value) -> &str {
+        log_record(json)["attributes"][0]["value"]["stringValue"]
+            .as_str()
+            .expect("missing exception.type")
+    }
+
+    #[test]
+    fn record_has_the_otlp_log_shape() {
+        let (_, json) = record(None, "my_crate::Error");
+        let record = log_record(&json);
+        assert_eq!(record["timeUnixNano"], "1700000000000000000");
+        assert_eq!(record["severityNumber"], 21);
+        assert_eq!(record["severityText"], "FATAL");
+        assert_eq!(record["eventName"], "exception");
+        assert_eq!(record["attributes"][0]["key"], "exception.type");
+        assert_eq!(exception_type(&json), "my_crate::Error");
+        assert_eq!(
+            json["resourceLogs"][0]["scopeLogs"][0]["scope"]["name"],
+            env!("CARGO_PKG_NAME")
+        );
+    }
+
+    #[test]
+    fn type_name_is_sanitized() {
+        let (_, json) = record(None, "a\"b\\c\nd");
+        assert_eq!(exception_type(&json), "a_b_c_d");
+    }
+
+    #[test]
+    fn long_type_name_is_cut_at_a_char_boundary() {
+        for filler in ["x", "é", "🦀"] {
+            let (len, json) = record(None, &filler.repeat(BUFFER_LEN));
+            assert!(len <= BUFFER_LEN && len + filler.len() > BUFFER_LEN - SUFFIX.len());
+            let type_name = exception_type(&json);
+            assert!(type_name.len() > BUFFER_LEN / 2);
+            assert!(type_name.chars().all(|c| c.to_string() == filler));
+        }
+    }
+
+    #[test]
+    fn resource_attributes_are_escaped() {
+        let emitter = FatalEmitter::new(STDERR, [("service.name", "say \"hi\"\n")]);
+        let (_, json) = record(Some(&emitter.prefix), "T");
+        let attribute = &json["resourceLogs"][0]["resource"]["attributes"][0];
+        assert_eq!(attribute["key"], "service.name");
+        assert_eq!(attribute["value"]["stringValue"], "say \"hi\"\n");
+    }
+
+    #[test]
+    fn oversized_resource_leaves_room_for_the_record() {
+        let value = "v".repeat(BUFFER_LEN / 4);
+        let emitter = FatalEmitter::new(STDERR, (0..8).map(|_| ("service.name", value.as_str())));
+        assert!(emitter.prefix.len() <= MAX_PREFIX_LEN);
+        let (_, json) = record(Some(&emitter.prefix), &"T".repeat(BUFFER_LEN));
+        let attributes = json["resourceLogs"][0]["resource"]["attributes"]
+            .as_array()
+            .expect("missing resource attributes");
+        assert!(!attributes.is_empty() && attributes.len() < 8);
+        assert!(exception_type(&json).starts_with('T'));
+    }
+
+    #[test]
+    fn emits_one_line_and_skips_while_the_buffer_is_in_use() {
+        let (writer, reader) = UnixStream::pair().expect("socket pair");
+        assert!(install_fatal_emitter(
+            writer.as_raw_fd(),
+            [("service.name", "fatal-test")]
+        ));
+        assert!(!install_fatal_emitter(STDERR, []));
+
+        emit_fatal_minimal_for::<FatalBuffer>();
+        let mut line = String::new();
+        BufReader::new(&reader)
+            .read_line(&mut line)
+            .expect("read record");
+        let json: Value = serde_json::from_str(&line).expect("record is not valid JSON");
+        assert!(exception_type(&json).ends_with("::FatalBuffer"));
+
+        BUFFER.in_use.store(true, Ordering::Release);
+        emit_fatal_minimal("skipped");
+        BUFFER.in_use.store(false, Ordering::Release);
+        reader.set_nonblocking(true).expect("set non-blocking");
+        let error = (&reader)
+            .read(&mut [0; 1])
+            .expect_err("nothing should have been written");
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    }
+}
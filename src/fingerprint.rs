@@ -0,0 +1,165 @@
+use std::fmt::{self, Display, Write};
+
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::{error_type::registered_context_error_type, utilities::AsReportRef};
+
+/// Version of the fingerprint input format, used as the fingerprint prefix.
+///
+/// The prefix only changes when the way a report tree is turned into hash
+/// input changes, so fingerprints stay comparable across crate versions
+/// (and dependency bumps) for as long as the prefix stays the same.
+///
+/// `v3` no longer hashes the `error.type` derived by global configuration,
+/// such as the [`TypeNameNormalizer`](crate::error_type::TypeNameNormalizer)
+/// or the `io.<kind>` types of `io::Error`s, which `v2` fingerprints changed
+/// with.
+pub const FINGERPRINT_VERSION: &str = "v3";
+
+/// A stable grouping key for a [`Report`](rootcause::Report), formatted as
+/// `v3:<hasher id>:<hex digest>`, e.g. `v3:fnv1a64:…`.
+///
+/// The fingerprint is derived from the shape of the report tree and the
+/// context type of each report in it, i.e. its registered
+/// [`OtelErrorType`](crate::error_type::OtelErrorType) or its raw type name,
+/// but not from the formatted messages, so reports with the same causal
+/// structure group together regardless of embedded ids or other variable
+/// data. Other configuration which affects the `error.type`, such as the
+/// [`TypeNameNormalizer`](crate::error_type::TypeNameNormalizer), does not
+/// affect the fingerprint.
+///
+/// Raw type names are not guaranteed to be stable across compiler versions,
+/// so context types whose fingerprints must stay comparable across builds
+/// should have an `OtelErrorType`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint(String);
+
+impl Fingerprint {
+    /// Compute the fingerprint of a report with the default [`Fnv1a64`] hasher.
+    pub fn of(rep: &impl AsReportRef) -> Self {
+        Self::with_hasher(rep, &Fnv1a64)
+    }
+
+    /// Compute the fingerprint of a report with the given hasher.
+    pub fn with_hasher(rep: &impl AsReportRef, hasher: &impl FingerprintHasher) -> Self {
+        let mut fingerprint = format!("{FINGERPRINT_VERSION}:{}:", hasher.id());
+        for byte in hasher.digest(&fingerprint_input(rep)) {
+            let _ = write!(fingerprint, "{byte:02x}");
+        }
+        Self(fingerprint)
+    }
+
+    /// The fingerprint as a string, including the version and hasher prefix.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Fingerprint> for String {
+    fn from(value: Fingerprint) -> Self {
+        value.0
+    }
+}
+
+/// Input format `v3`: one line per report in depth-first order, each line
+/// being the report's depth in the tree followed by `=` and its registered
+/// `error.type`, or by `:` and its raw type name if it has none, so that the
+/// two never collide.
+fn fingerprint_input(rep: &impl AsReportRef) -> Vec<u8> {
+    fn walk(rep: ReportRef<'_, Dynamic, Uncloneable, Local>, depth: usize, out: &mut String) {
+        let _ = match registered_context_error_type(rep) {
+            Some(error_type) => writeln!(out, "{depth} ={error_type}"),
+            None => writeln!(out, "{depth} :{}", rep.current_context_type_name()),
+        };
+        for child in rep.children().iter() {
+            walk(child.into_uncloneable(), depth + 1, out);
+        }
+    }
+
+    let mut out = String::new();
+    walk(rep.as_report_ref(), 0, &mut out);
+    out.into_bytes()
+}
+
+/// Hash function used to turn the fingerprint input into a digest.
+///
+/// Implementations must be deterministic across processes, platforms and
+/// versions; in particular [`std::hash::DefaultHasher`] is not suitable.
+pub trait FingerprintHasher: Send + Sync + 'static {
+    /// Short name of the hash function, e.g. `fnv1a64`, included in the
+    /// fingerprint prefix so that digests of different hashers are never
+    /// compared with each other.
+    fn id(&self) -> &'static str;
+
+    /// Hash the fingerprint input.
+    fn digest(&self, input: &[u8]) -> Vec<u8>;
+}
+
+/// 64-bit FNV-1a, the default [`FingerprintHasher`].
+///
+/// Needs no dependencies and is trivially stable, but is not collision
+/// resistant against adversarial input.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fnv1a64;
+
+impl FingerprintHasher for Fnv1a64 {
+    fn id(&self) -> &'static str {
+        "fnv1a64"
+    }
+
+    fn digest(&self, input: &[u8]) -> Vec<u8> {
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        input
+            .iter()
+            .fold(OFFSET_BASIS, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+            })
+            .to_be_bytes()
+            .to_vec()
+    }
+}
+
+/// 64-bit XXH3.
+#[cfg(feature = "xxhash")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Xxh3;
+
+#[cfg(feature = "xxhash")]
+impl FingerprintHasher for Xxh3 {
+    fn id(&self) -> &'static str {
+        "xxh3"
+    }
+
+    fn digest(&self, input: &[u8]) -> Vec<u8> {
+        xxhash_rust::xxh3::xxh3_64(input).to_be_bytes().to_vec()
+    }
+}
+
+/// SHA-256 truncated to its first 16 bytes.
+#[cfg(feature = "sha256")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sha256Truncated;
+
+#[cfg(feature = "sha256")]
+impl FingerprintHasher for Sha256Truncated {
+    fn id(&self) -> &'static str {
+        "sha256-128"
+    }
+
+    fn digest(&self, input: &[u8]) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+
+        Sha256::digest(input)[..16].to_vec()
+    }
+}
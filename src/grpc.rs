@@ -0,0 +1,153 @@
+//! [gRPC semantic conventions](https://opentelemetry.io/docs/specs/semconv/rpc/grpc/)
+//! for reports of failed calls, with the `grpc` feature.
+//!
+//! The [`Code`] of a report is the first one found in the report tree, from a
+//! [`Status`] context or attachment, or an attached [`Code`]. It is emitted as
+//! the `rpc.grpc.status_code` attribute alongside `rpc.system`, and decides the
+//! span status set by [`with_error_status`](crate::span_event::RecordErrorReport::with_error_status):
+//! - `UNKNOWN`, `DEADLINE_EXCEEDED`, `UNIMPLEMENTED`, `INTERNAL`, `UNAVAILABLE`
+//!   and `DATA_LOSS` set it to [`Error`](opentelemetry::trace::Status::Error).
+//! - The other codes besides `OK`, which are caused by the client, set it to
+//!   [`Error`](opentelemetry::trace::Status::Error) unless disabled with
+//!   [`ExceptionEventSpec::grpc_client_errors`](crate::spec::ExceptionEventSpec::grpc_client_errors),
+//!   as server spans should.
+//! - `OK` leaves it unset.
+
+use opentelemetry::{KeyValue, StringValue, Value, trace::Status as SpanStatus};
+use rootcause::{
+    Report, ReportRef,
+    markers::{Dynamic, Local, Mutable, ObjectMarkerFor, Uncloneable},
+};
+use tonic::{Code, Status, metadata::KeyAndValueRef};
+
+use crate::{attachments::Hidden, spec::ExceptionEventSpec, utilities::AttachmentsExt};
+
+pub const RPC_SYSTEM: &str = "rpc.system";
+pub const RPC_GRPC_STATUS_CODE: &str = "rpc.grpc.status_code";
+pub const RPC_GRPC_RESPONSE_METADATA: &str = "rpc.grpc.response.metadata";
+
+/// The metadata of a gRPC [`Status`], as key and value pairs in their order.
+///
+/// Binary values are kept in their base64-encoded form.
+///
+/// It is attached hidden by [`status_report`], and only the keys allowlisted
+/// with [`ExceptionEventSpec::grpc_metadata`] are emitted, as
+/// `rpc.grpc.response.metadata.<key>` attributes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrpcMetadata(pub Vec<(String, String)>);
+
+impl GrpcMetadata {
+    /// The metadata of `status`.
+    pub fn of(status: &Status) -> Self {
+        Self(
+            status
+                .metadata()
+                .iter()
+                .map(|entry| match entry {
+                    KeyAndValueRef::Ascii(key, value) => (
+                        key.as_str().to_owned(),
+                        String::from_utf8_lossy(value.as_encoded_bytes()).into_owned(),
+                    ),
+                    KeyAndValueRef::Binary(key, value) => (
+                        key.as_str().to_owned(),
+                        String::from_utf8_lossy(value.as_encoded_bytes()).into_owned(),
+                    ),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Turn a `status` received from or returned to a gRPC peer into a report,
+/// with its [`GrpcMetadata`] attached so that the allowlisted keys are emitted
+/// even once the report is wrapped in other contexts.
+///
+/// ```
+/// use rootcause_opentelemetry::grpc::status_report;
+///
+/// let report = status_report(tonic::Status::unavailable("inventory service is down"));
+/// ```
+pub fn status_report(status: Status) -> Report<Status> {
+    let metadata = GrpcMetadata::of(&status);
+    Report::new(status).attach_custom::<Hidden, _>(metadata)
+}
+
+/// Extension trait for [`Report`]s of failed gRPC calls.
+pub trait GrpcReportExt: Sized {
+    /// Attach the [`Code`] the call failed with, for reports of calls whose
+    /// [`Status`] is not in the report tree.
+    fn attach_grpc_code(self, code: Code) -> Self;
+}
+
+impl<C: ?Sized, T> GrpcReportExt for Report<C, Mutable, T>
+where
+    Code: ObjectMarkerFor<T>,
+{
+    fn attach_grpc_code(self, code: Code) -> Self {
+        self.attach_custom::<Hidden, _>(code)
+    }
+}
+
+/// The status code of `rep` or its first descendant with one.
+pub(crate) fn code(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<Code> {
+    rep.iter_reports().find_map(|r| {
+        r.downcast_current_context::<Status>()
+            .or_else(|| r.attachments().find_attachment_inner::<Status>())
+            .map(Status::code)
+            .or_else(|| r.attachments().find_attachment_inner::<Code>().copied())
+    })
+}
+
+/// The `rpc.system`, `rpc.grpc.status_code` and allowlisted metadata attributes
+/// of `rep`, if it has a status code.
+pub(crate) fn attributes(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> Vec<KeyValue> {
+    let Some(code) = code(rep) else {
+        return Vec::new();
+    };
+    let mut attributes = vec![
+        KeyValue::new(RPC_SYSTEM, "grpc"),
+        KeyValue::new(RPC_GRPC_STATUS_CODE, i64::from(i32::from(code))),
+    ];
+    if let Some(metadata) = rep
+        .iter_reports()
+        .find_map(|r| r.attachments().find_attachment_inner::<GrpcMetadata>())
+    {
+        for key in &spec.grpc_metadata {
+            let values: Vec<StringValue> = metadata
+                .0
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.clone().into())
+                .collect();
+            if !values.is_empty() {
+                attributes.push(KeyValue::new(
+                    format!("{RPC_GRPC_RESPONSE_METADATA}.{}", key.to_ascii_lowercase()),
+                    Value::Array(values.into()),
+                ));
+            }
+        }
+    }
+    attributes
+}
+
+/// [`Unset`](SpanStatus::Unset) if the status code of `rep` does not indicate
+/// an error under `spec`, or `None` to set the error status as usual.
+pub(crate) fn span_status(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> Option<SpanStatus> {
+    let error = match code(rep)? {
+        Code::Ok => false,
+        Code::Unknown
+        | Code::DeadlineExceeded
+        | Code::Unimplemented
+        | Code::Internal
+        | Code::Unavailable
+        | Code::DataLoss => true,
+        _ => spec.grpc_client_errors,
+    };
+    (!error).then_some(SpanStatus::Unset)
+}
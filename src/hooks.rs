@@ -0,0 +1,37 @@
+//! Installing the rootcause hooks this crate relies on in one call.
+
+use rootcause::{Report, hooks::Hooks, prelude::*};
+use rootcause_backtrace::BacktraceCollector;
+
+use crate::attachments::{HideTraceAttachments, OpenTelemetryMetadataCollector};
+
+/// The hooks installed by [`install_recommended_hooks`], to add further hooks to
+/// before installing them.
+///
+/// - [`BacktraceCollector::new_from_env`], for the `exception.stacktrace` attribute.
+/// - [`OpenTelemetryMetadataCollector`], for the timestamps and span contexts of reports.
+/// - [`HideTraceAttachments`], keeping span contexts out of the formatted report.
+/// - With the `process-metrics` feature on Linux, [`ProcessCollector`](crate::process_metrics::ProcessCollector).
+pub fn recommended_hooks() -> Hooks {
+    let hooks = Hooks::new()
+        .report_creation_hook(BacktraceCollector::new_from_env())
+        .report_creation_hook(OpenTelemetryMetadataCollector::new())
+        .attachment_formatter(HideTraceAttachments);
+    #[cfg(feature = "process-metrics")]
+    let hooks = hooks.report_creation_hook(crate::process_metrics::ProcessCollector::new());
+    hooks
+}
+
+/// Install the [`recommended_hooks`] process-wide.
+///
+/// Fails if rootcause hooks have been installed already.
+///
+/// ```
+/// rootcause_opentelemetry::hooks::install_recommended_hooks().unwrap();
+/// ```
+pub fn install_recommended_hooks() -> Result<(), Report> {
+    recommended_hooks()
+        .install()
+        .context("Failed to install rootcause hooks")
+        .map_err(Report::into_dynamic)
+}
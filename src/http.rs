@@ -0,0 +1,144 @@
+//! [HTTP semantic conventions](https://opentelemetry.io/docs/specs/semconv/http/http-spans/)
+//! for reports of failed requests, with the `http` feature.
+//!
+//! The [`StatusCode`] of a report is the first one found in the report tree,
+//! either attached with [`HttpReportExt::attach_http_status`], as the context
+//! itself, or from a context type registered with [`register_http_status`].
+//! It is emitted as the `http.response.status_code` attribute, and decides the
+//! span status set by [`with_error_status`](crate::span_event::RecordErrorReport::with_error_status):
+//! - 5xx status codes set it to [`Error`](opentelemetry::trace::Status::Error).
+//! - 4xx status codes set it to [`Error`](opentelemetry::trace::Status::Error)
+//!   unless disabled with [`ExceptionEventSpec::http_client_errors`](crate::spec::ExceptionEventSpec::http_client_errors),
+//!   as server spans should.
+//! - Other status codes leave it unset.
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use http::StatusCode;
+use opentelemetry::{KeyValue, trace::Status};
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    Report, ReportRef,
+    markers::{Dynamic, Local, Mutable, ObjectMarkerFor, Uncloneable},
+};
+
+use crate::{spec::ExceptionEventSpec, utilities::AttachmentsExt};
+
+type StatusCodeFn =
+    Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<StatusCode> + Send + Sync>;
+
+static REGISTRY: LazyLock<RwLock<HashMap<TypeId, StatusCodeFn>>> = LazyLock::new(Default::default);
+
+/// Context types carrying the HTTP status code of a failed request, such as
+/// the error types of HTTP clients and frameworks.
+///
+/// Report contexts are type-erased, so implementations only take effect
+/// once registered with [`register_http_status`].
+pub trait OtelHttpStatus: 'static {
+    fn http_status(&self) -> StatusCode;
+}
+
+impl OtelHttpStatus for StatusCode {
+    fn http_status(&self) -> StatusCode {
+        *self
+    }
+}
+
+/// Use the [`OtelHttpStatus`] implementation of `C` for reports whose current
+/// context is a `C`.
+pub fn register_http_status<C: OtelHttpStatus>() {
+    register_http_status_with::<C>(|context| Some(context.http_status()));
+}
+
+/// Use `status` for reports whose current context is a `C`, for types which
+/// cannot implement [`OtelHttpStatus`] or only sometimes have a status code.
+///
+/// ```
+/// # #[derive(Debug)] struct ClientError { status: Option<u16> }
+/// use http::StatusCode;
+/// use rootcause_opentelemetry::http::register_http_status_with;
+///
+/// register_http_status_with::<ClientError>(|error| {
+///     error.status.and_then(|status| StatusCode::from_u16(status).ok())
+/// });
+/// ```
+pub fn register_http_status_with<C: 'static>(
+    status: impl Fn(&C) -> Option<StatusCode> + Send + Sync + 'static,
+) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .insert(
+            TypeId::of::<C>(),
+            Arc::new(move |rep| rep.downcast_current_context::<C>().and_then(&status)),
+        );
+}
+
+/// Extension trait for [`Report`]s of failed HTTP requests.
+pub trait HttpReportExt: Sized {
+    /// Attach the [`StatusCode`] the request failed with.
+    ///
+    /// ```
+    /// use http::StatusCode;
+    /// use rootcause::prelude::*;
+    /// use rootcause_opentelemetry::http::HttpReportExt;
+    ///
+    /// let report = report!("payment declined").attach_http_status(StatusCode::PAYMENT_REQUIRED);
+    /// ```
+    fn attach_http_status(self, status: StatusCode) -> Self;
+}
+
+impl<C: ?Sized, T> HttpReportExt for Report<C, Mutable, T>
+where
+    StatusCode: ObjectMarkerFor<T>,
+{
+    fn attach_http_status(self, status: StatusCode) -> Self {
+        self.attach(status)
+    }
+}
+
+/// The status code of `rep` or its first descendant with one.
+pub(crate) fn status_code(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<StatusCode> {
+    rep.iter_reports().find_map(|r| {
+        r.attachments()
+            .find_attachment_inner::<StatusCode>()
+            .copied()
+            .or_else(|| r.downcast_current_context::<StatusCode>().copied())
+            .or_else(|| {
+                // The function is cloned out so that it can itself register ones.
+                let status = REGISTRY
+                    .read()
+                    .unwrap_or_else(|poison| poison.into_inner())
+                    .get(&r.current_context_type_id())
+                    .cloned();
+                status.and_then(|status| status(r))
+            })
+    })
+}
+
+/// The `http.response.status_code` attribute of `rep`, if it has a status code.
+pub(crate) fn status_code_attribute(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+) -> Option<KeyValue> {
+    status_code(rep).map(|status| {
+        KeyValue::new(
+            attribute::HTTP_RESPONSE_STATUS_CODE,
+            i64::from(status.as_u16()),
+        )
+    })
+}
+
+/// [`Status::Unset`] if the status code of `rep` does not indicate an error
+/// under `spec`, or `None` to set the error status as usual.
+pub(crate) fn span_status(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> Option<Status> {
+    let status = status_code(rep)?;
+    let error = status.is_server_error() || (status.is_client_error() && spec.http_client_errors);
+    (!error).then_some(Status::Unset)
+}
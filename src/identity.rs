@@ -0,0 +1,208 @@
+//! Identity of a report captured at creation, for correlating the span event,
+//! log record and metric measurements emitted for the same error.
+
+use std::{
+    fmt::{self, Display},
+    hash::{BuildHasher, RandomState},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::UNIX_EPOCH,
+};
+
+use opentelemetry::KeyValue;
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::{attachments::Hidden, clock, fingerprint::Fingerprint};
+
+pub const EXCEPTION_ID: &str = "exception.id";
+pub const EXCEPTION_FINGERPRINT: &str = "exception.fingerprint";
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+static GENERATOR: RwLock<Option<Arc<dyn ReportIdGenerator>>> = RwLock::new(None);
+
+/// Identifier uniquely identifying a report, by default a
+/// [ULID](https://github.com/ulid/spec) sortable by creation time.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReportId(Repr);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Repr {
+    Ulid(u128),
+    Custom(Arc<str>),
+}
+
+impl ReportId {
+    /// Generate a new id with the [installed](install_id_generator) generator,
+    /// or a ULID if none was installed.
+    pub fn generate() -> Self {
+        // The generator is cloned out so that it can itself create reports or
+        // install a generator.
+        let generator = GENERATOR
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone();
+        match generator {
+            Some(generator) => generator.generate(),
+            None => Self::ulid(),
+        }
+    }
+
+    /// Generate a new ULID from the [current time](clock::now) and 80 random
+    /// bits from the operating system.
+    pub fn ulid() -> Self {
+        let millis = clock::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u128
+            & ((1 << 48) - 1);
+        let mut random = [0u8; 16];
+        if getrandom::fill(&mut random[6..]).is_err() {
+            random = fallback_random().to_be_bytes();
+        }
+        let random = u128::from_be_bytes(random) & ((1 << 80) - 1);
+        Self(Repr::Ulid((millis << 80) | random))
+    }
+
+    /// An id in a format of its own, such as a UUIDv7 or a Snowflake id,
+    /// emitted as is.
+    pub fn custom(id: impl Into<Arc<str>>) -> Self {
+        Self(Repr::Custom(id.into()))
+    }
+
+    /// The id as a 128-bit integer, if it is a ULID.
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.0 {
+            Repr::Ulid(ulid) => Some(ulid),
+            Repr::Custom(_) => None,
+        }
+    }
+}
+
+/// Random bits for when the operating system has none to give, from the
+/// randomly keyed hasher of the standard library and a counter, so that ids
+/// stay unique within the process.
+fn fallback_random() -> u128 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let state = RandomState::new();
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+    (u128::from(state.hash_one(counter)) << 64) | u128::from(state.hash_one(!counter))
+}
+
+impl Display for ReportId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ulid = match &self.0 {
+            Repr::Ulid(ulid) => *ulid,
+            Repr::Custom(id) => return f.write_str(id),
+        };
+        let mut encoded = [0u8; 26];
+        for (index, byte) in encoded.iter_mut().enumerate() {
+            let shift = 5 * (25 - index);
+            *byte = CROCKFORD[((ulid >> shift) & 0x1f) as usize];
+        }
+        f.write_str(std::str::from_utf8(&encoded).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Source of the [`ReportId`]s given to new reports, for ids compatible with
+/// existing correlation tooling, such as UUIDv7s, Snowflake ids, or ids
+/// derived from the current trace id.
+///
+/// Implemented for closures returning a [`ReportId`].
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use rootcause_opentelemetry::identity::{ReportId, install_id_generator, reset_id_generator};
+///
+/// static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+/// install_id_generator(|| {
+///     ReportId::custom(format!("worker-7-{}", SEQUENCE.fetch_add(1, Ordering::Relaxed)))
+/// });
+/// assert_eq!(ReportId::generate().to_string(), "worker-7-0");
+/// reset_id_generator();
+/// ```
+pub trait ReportIdGenerator: Send + Sync + 'static {
+    fn generate(&self) -> ReportId;
+}
+
+impl<F: Fn() -> ReportId + Send + Sync + 'static> ReportIdGenerator for F {
+    fn generate(&self) -> ReportId {
+        self()
+    }
+}
+
+/// Make `generator` the process-wide source of the ids attached by the
+/// [`ReportIdentityCollector`].
+pub fn install_id_generator(generator: impl ReportIdGenerator) {
+    *GENERATOR
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = Some(Arc::new(generator));
+}
+
+/// Go back to generating ULIDs.
+pub fn reset_id_generator() {
+    *GENERATOR
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = None;
+}
+
+/// The id and fingerprint of a report, as of its creation.
+///
+/// Emitted as the `exception.id` and `exception.fingerprint` attributes on
+/// exception events and log records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportIdentity {
+    pub id: ReportId,
+    pub fingerprint: Fingerprint,
+}
+
+impl ReportIdentity {
+    /// The attributes to add to a metric measurement to correlate it with the
+    /// telemetry emitted for the report.
+    pub fn attributes(&self) -> [KeyValue; 2] {
+        [
+            KeyValue::new(EXCEPTION_ID, self.id.to_string()),
+            KeyValue::new(
+                EXCEPTION_FINGERPRINT,
+                String::from(self.fingerprint.clone()),
+            ),
+        ]
+    }
+}
+
+/// Report creation hook attaching a [`ReportIdentity`] to new reports.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReportIdentityCollector {
+    _priv: (),
+}
+
+impl ReportIdentityCollector {
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl ReportCreationHook for ReportIdentityCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        let identity = ReportIdentity {
+            id: ReportId::generate(),
+            fingerprint: Fingerprint::of(&report),
+        };
+        let _ = report.attach_custom::<Hidden, _>(identity);
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        let identity = ReportIdentity {
+            id: ReportId::generate(),
+            fingerprint: Fingerprint::of(&report),
+        };
+        let _ = report.attach_custom::<Hidden, _>(identity);
+    }
+}
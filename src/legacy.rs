@@ -0,0 +1,81 @@
+//! Mirroring of emitted error logs into pre-OpenTelemetry logging, for use
+//! while migrating away from console-based alerting.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use opentelemetry::logs::Severity;
+use opentelemetry_semantic_conventions::attribute;
+
+use crate::{pipeline::ExceptionSnapshot, utilities::ERROR_MESSAGE};
+
+static MIRROR: AtomicU8 = AtomicU8::new(LegacyMirror::Off as u8);
+
+/// Where [`LoggerExt`](crate::log_event::LoggerExt) additionally writes a
+/// single-line rendering of every error report it emits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LegacyMirror {
+    /// Only emit OpenTelemetry log records.
+    Off = 0,
+    /// Also write to standard error with [`eprintln!`].
+    Stderr = 1,
+    /// Also log through the [`log`] crate facade, at the level matching the
+    /// record's severity.
+    #[cfg(feature = "log")]
+    Log = 2,
+}
+
+impl LegacyMirror {
+    /// Change the mirroring mode at runtime.
+    pub fn set(self) {
+        MIRROR.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// The currently active mirroring mode.
+    pub fn current() -> Self {
+        match MIRROR.load(Ordering::Relaxed) {
+            1 => Self::Stderr,
+            #[cfg(feature = "log")]
+            2 => Self::Log,
+            _ => Self::Off,
+        }
+    }
+}
+
+/// Mirror the log record of `snapshot`, as left by the [`EmitLayer`](crate::pipeline::EmitLayer)s.
+pub(crate) fn mirror(snapshot: &ExceptionSnapshot<'_>, severity: Severity) {
+    match LegacyMirror::current() {
+        LegacyMirror::Off => {}
+        LegacyMirror::Stderr => eprintln!("{} {}", severity.name(), single_line(snapshot)),
+        #[cfg(feature = "log")]
+        LegacyMirror::Log => {
+            let level = match severity as i32 {
+                ..=4 => log::Level::Trace,
+                5..=8 => log::Level::Debug,
+                9..=12 => log::Level::Info,
+                13..=16 => log::Level::Warn,
+                _ => log::Level::Error,
+            };
+            log::log!(level, "{}", single_line(snapshot));
+        }
+    }
+}
+
+/// `exception <type>: <message>`, with line breaks in the message collapsed.
+///
+/// The type and message are those of the `exception.type` and `exception.message`
+/// attributes, or `error.type` and `error.message`, of `snapshot`.
+fn single_line(snapshot: &ExceptionSnapshot<'_>) -> String {
+    let value_of = |keys: [&str; 2]| {
+        keys.into_iter()
+            .find_map(|key| snapshot.attribute(key))
+            .map(|value| value.as_str().into_owned())
+            .unwrap_or_default()
+    };
+    let message = value_of([attribute::EXCEPTION_MESSAGE, ERROR_MESSAGE]);
+    format!(
+        "exception {}: {}",
+        value_of([attribute::EXCEPTION_TYPE, attribute::ERROR_TYPE]),
+        message.lines().collect::<Vec<_>>().join(" ⏎ ")
+    )
+}
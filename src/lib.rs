@@ -1,5 +1,77 @@
+pub mod aggregation;
 pub mod attachments;
-#[cfg(any(feature = "logs"))]
+pub mod baggage;
+pub mod cbor;
+#[cfg(feature = "regex")]
+pub mod classification;
+pub mod classifier;
+pub mod clock;
+pub mod code;
+pub mod conversion;
+pub mod correlation;
+pub mod db;
+pub mod deferred;
+pub mod emission_guard;
+pub mod error_type;
+#[cfg(feature = "metrics")]
+pub mod exemplar;
+#[cfg(feature = "logs")]
+pub mod extra;
+#[cfg(unix)]
+pub mod fatal;
+pub mod fingerprint;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hooks;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod identity;
+#[cfg(feature = "logs")]
+pub mod legacy;
+pub mod library;
+#[cfg(feature = "logs")]
 pub mod log_event;
+#[cfg(feature = "metrics")]
+pub mod meter;
+#[cfg(feature = "metrics")]
+pub mod outstanding;
+pub mod panic;
+pub mod pipeline;
+#[cfg(feature = "process-metrics")]
+pub mod process_metrics;
+pub mod rehydrate;
+pub mod resource;
+#[cfg(feature = "logs")]
+pub mod routing;
+#[cfg(feature = "tokio-metrics")]
+pub mod runtime_metrics;
+pub mod sampling;
+pub mod schema;
+#[cfg(feature = "sdk")]
+pub mod sdk_error;
+#[cfg(feature = "setup")]
+pub mod setup;
+#[cfg(feature = "logs")]
+pub mod severity;
 pub mod span_event;
+pub mod spec;
+#[cfg(feature = "serde")]
+pub mod structured;
+#[cfg(feature = "tokio-task")]
+pub mod task;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod thread;
+pub mod traceparent;
 mod utilities;
+
+pub use utilities::AsReportRef;
+
+#[cfg(feature = "derive")]
+pub use rootcause_opentelemetry_derive::OtelError;
+
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use opentelemetry;
+}
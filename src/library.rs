@@ -0,0 +1,50 @@
+//! Emission for library crates, which should not choose telemetry providers
+//! on behalf of the applications using them.
+//!
+//! Everything here goes through the [global](opentelemetry::global) tracer
+//! provider and the logger the application installed with
+//! [`install_fallback_logger`](crate::log_event::install_fallback_logger), and
+//! never constructs SDK types. This crate does not depend on `opentelemetry_sdk`,
+//! so libraries can depend on it with `default-features = false` and only the
+//! features they use, leaving provider setup to the application.
+//!
+//! See the `examples/library` crate of the workspace for the pattern.
+
+use std::borrow::Cow;
+
+use opentelemetry::{Context, global, trace::Tracer};
+
+use crate::{span_event::SpanReportExt, utilities::AsReportRef};
+
+/// Record `rep` as an `exception` event on the current span if it is recording.
+///
+/// Otherwise it is emitted as a log record with the logger installed by the
+/// application, if any and with the `logs` feature enabled.
+pub fn report(rep: &impl AsReportRef) {
+    #[cfg(feature = "logs")]
+    crate::log_event::ReportLogExt::record_or_log_fallback(rep);
+    #[cfg(not(feature = "logs"))]
+    {
+        use crate::span_event::SpanRefReportExt;
+        use opentelemetry::trace::TraceContextExt;
+
+        let _ = Context::current()
+            .span()
+            .record_error_report(rep)
+            .as_event();
+    }
+}
+
+/// Record `rep` on a new span named `name`, from the global tracer for the
+/// instrumentation scope `scope`, and end that span as failed.
+///
+/// The span is a child of the current one, if any. This is meant for failures
+/// in library code running outside of any span of its own.
+pub fn report_in_span(
+    scope: impl Into<Cow<'static, str>>,
+    name: impl Into<Cow<'static, str>>,
+    rep: &impl AsReportRef,
+) {
+    let mut span = global::tracer(scope).start_with_context(name, &Context::current());
+    let _ = span.record_error_report(rep).end_span_with_error();
+}
@@ -1,12 +1,36 @@
-use std::time::SystemTime;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use opentelemetry::{
-    Array, Context, Value,
-    logs::{AnyValue, LogRecord, Logger, Severity},
+    Array, Context, InstrumentationScope, Key, KeyValue, Value,
+    logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity},
     trace::{SpanContext, TraceContextExt},
 };
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+    report_attachment::ReportAttachmentRef,
+};
 
-use crate::utilities::{AsReportRef, AttachmentsExt, EXCEPTION, attributes, timestamp};
+use crate::{
+    clock, conversion, correlation,
+    extra::{self, Extra},
+    legacy,
+    pipeline::{self, Destination, ExceptionSnapshot},
+    routing,
+    sampling::{self, Decision},
+    severity::severity_of,
+    span_event::{SpanRefReportExt, target_span},
+    spec::{ExceptionEventSpec, MessageLines, SemconvProfile},
+    utilities::{
+        AsReportRef, ERROR_MESSAGE, EXCEPTION, attributes, attributes_brief, format_contained,
+        format_report, split_message, strip_ansi, timestamp, truncate_middle, visible_attachments,
+    },
+};
 
 /// Extension trait for loggers to format [`Report`](rootcause::Report)s as
 /// log records.
@@ -14,52 +38,718 @@ pub trait LoggerExt: Sized {
     /// Emit a log event corresponding to a [`Report`](rootcause::Report).
     ///
     /// ## Attributes & Details
-    /// - Event name is `exception`
-    /// - Severity is set to `ERROR` ()
-    /// - Observed timestamp of the event is given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or defaults to [`now()`](std::time::SystemTime::now) if not found.
+    /// - Event name is `exception`, see [`LogRecordReportBuilder::event_name`]
+    /// - Severity is given by a [`Severity`]-typed attachment, or the severity [registered](crate::severity::register_severity) for the context type, or defaults to `ERROR`.
+    /// - Observed timestamp of the event is given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or defaults to [the current time](crate::clock::now) if not found.
     /// - The trace context is taken
     /// - `exception.type` is [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
     /// - `exception.message` is [`.format_current_context().to_string()`](rootcause::Report::format_current_context).
     /// - `exception.stacktrace` is just `.to_string()` of the [`Report`](rootcause::Report) itself
+    /// - [`KeyValue`](opentelemetry::KeyValue)-typed attachments are added as attributes, see [`ExceptionEventSpec::attachment_attributes`](crate::spec::ExceptionEventSpec::attachment_attributes).
     ///
     /// [`SystemTime`](std::time::SystemTime) attachments are
     /// provided report creation hook [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector).
+    ///
+    /// The [global `ExceptionEventSpec`](crate::spec::ExceptionEventSpec::install) is applied.
+    ///
+    /// The report is also mirrored to the active [`LegacyMirror`](crate::legacy::LegacyMirror), if any.
     fn emit_error_report(&self, rep: &impl AsReportRef);
+
+    /// Emit a log event corresponding to a [`Report`](rootcause::Report) as in
+    /// [`Self::emit_error_report`], additionally setting the body of the record
+    /// to a structured map of the whole report tree.
+    ///
+    /// ## Body
+    /// - `type` is [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
+    /// - `message` is [`.format_current_context().to_string()`](rootcause::Report::format_current_context).
+    /// - `stacktrace` is just `.to_string()` of the [`Report`](rootcause::Report) itself, and only present at the top level.
+    /// - `children` is a list of maps of the same shape for each child report, if any.
+    /// - `attachments` maps the type names of the non-hidden attachments to their formatted values, if any. Attachments sharing a type are collected in a list.
+    fn emit_error_report_structured(&self, rep: &impl AsReportRef);
+
+    /// Emit a log event corresponding to a [`Report`](rootcause::Report) as in
+    /// [`Self::emit_error_report`], additionally setting the body of the record
+    /// to the whole formatted report as plain text.
+    ///
+    /// ANSI escape sequences, such as colors added by formatting hooks, are
+    /// stripped from the body, and it is truncated as the `exception.stacktrace`
+    /// attribute is.
+    fn emit_error_report_plain(&self, rep: &impl AsReportRef);
+
+    /// Emit one log event per report in the tree of a [`Report`](rootcause::Report),
+    /// instead of a single record for the whole tree.
+    ///
+    /// Each record is built as in [`Self::emit_error_report`] from its own report,
+    /// taking its severity, timestamp and trace context from that report's attachments,
+    /// except that `exception.stacktrace` is omitted since it would repeat the
+    /// records of the child reports.
+    fn emit_error_report_granular(&self, rep: &impl AsReportRef);
+
+    /// Emit a log event corresponding to a [`Report`](rootcause::Report) as in
+    /// [`Self::emit_error_report`], with the logger the report is
+    /// [routed](crate::routing) to by its context type, or with this logger if
+    /// there is no matching route.
+    fn emit_error_report_routed(&self, rep: &impl AsReportRef);
 }
 
 impl<L: Logger + Sized> LoggerExt for L {
     fn emit_error_report(&self, rep: &impl AsReportRef) {
-        let rep = rep.as_report_ref();
-        let mut record = self.create_log_record();
-        record.set_event_name(EXCEPTION);
-        record.set_observed_timestamp(timestamp(rep));
-        record.set_timestamp(SystemTime::now());
-
-        let severity = rep
-            .find_attachment_inner()
-            .cloned()
-            .unwrap_or(Severity::Error);
-        record.set_severity_number(severity);
-        record.set_severity_text(severity.name());
+        rep.otel_log(self).emit();
+    }
+
+    fn emit_error_report_structured(&self, rep: &impl AsReportRef) {
+        rep.otel_log(self).structured_body().emit();
+    }
+
+    fn emit_error_report_plain(&self, rep: &impl AsReportRef) {
+        rep.otel_log(self).plain_body().emit();
+    }
+
+    fn emit_error_report_granular(&self, rep: &impl AsReportRef) {
+        rep.otel_log(self).granular().emit();
+    }
+
+    fn emit_error_report_routed(&self, rep: &impl AsReportRef) {
+        match routing::route(rep.as_report_ref()) {
+            Some(emit) => emit(rep.as_report_ref()),
+            None => self.emit_error_report(rep),
+        }
+    }
+}
+
+/// Obtain a logger whose instrumentation scope is named after `subsystem`,
+/// so that errors reported by different subsystems land under distinct scopes.
+pub fn scoped_logger<P: LoggerProvider>(
+    provider: &P,
+    subsystem: impl Into<Cow<'static, str>>,
+) -> P::Logger {
+    provider.logger_with_scope(InstrumentationScope::builder(subsystem).build())
+}
+
+/// Logger emitting every record to several loggers, e.g. to both an OTLP
+/// backend and stdout during a backend migration.
+///
+/// Records are created by the primary logger and cloned to the others, so the
+/// [`EmitLayer`](crate::pipeline::EmitLayer)s run once per report rather than
+/// once per logger.
+#[derive(Debug, Clone)]
+pub struct MultiLogger<L> {
+    primary: L,
+    others: Vec<L>,
+}
+
+impl<L: Logger> MultiLogger<L> {
+    pub fn new(primary: L) -> Self {
+        Self {
+            primary,
+            others: Vec::new(),
+        }
+    }
+
+    /// Emit to `logger` as well.
+    pub fn with(mut self, logger: L) -> Self {
+        self.others.push(logger);
+        self
+    }
+}
+
+impl<L: Logger> Logger for MultiLogger<L>
+where
+    L::LogRecord: Clone,
+{
+    type LogRecord = L::LogRecord;
+
+    fn create_log_record(&self) -> Self::LogRecord {
+        self.primary.create_log_record()
+    }
+
+    fn emit(&self, record: Self::LogRecord) {
+        for logger in &self.others {
+            logger.emit(record.clone());
+        }
+        self.primary.emit(record);
+    }
+}
+
+/// Extension trait for [`Report`](rootcause::Report)s and references to them,
+/// for emitting them as log records.
+pub trait ReportLogExt: AsReportRef {
+    /// Returns a builder-pattern for turning the report into log records.
+    ///
+    /// See [`LogRecordReportBuilder`]
+    fn otel_log<'a, L: Logger>(&'a self, logger: &'a L) -> LogRecordReportBuilder<'a, L>;
+
+    /// Record the report as an `exception` event on the current span if it is
+    /// [recording](crate::span_event::RecordErrorReport::is_effective), and
+    /// otherwise emit it as a log record with `logger`.
+    ///
+    /// Errors from background tasks or startup code often have no active span,
+    /// in which case recording them on it would silently discard them.
+    fn record_or_log<L: Logger>(&self, logger: &L);
+
+    /// As [`Self::record_or_log`], falling back to the logger installed with
+    /// [`install_fallback_logger`]. If none is installed, nothing is emitted
+    /// without a recording span.
+    fn record_or_log_fallback(&self);
+}
+
+impl<R: AsReportRef> ReportLogExt for R {
+    fn otel_log<'a, L: Logger>(&'a self, logger: &'a L) -> LogRecordReportBuilder<'a, L> {
+        LogRecordReportBuilder {
+            logger,
+            report: self.as_report_ref(),
+            spec: ExceptionEventSpec::global(),
+            event_name: EXCEPTION,
+            target: None,
+            message: None,
+            body: Body::None,
+            granular: false,
+            group_siblings: false,
+            extra_attributes: Vec::new(),
+            span_context: None,
+            decision: None,
+            #[cfg(feature = "metrics")]
+            counter: None,
+        }
+    }
+
+    fn record_or_log<L: Logger>(&self, logger: &L) {
+        if !record_on_current_span(self) {
+            logger.emit_error_report(self);
+        }
+    }
+
+    fn record_or_log_fallback(&self) {
+        if !record_on_current_span(self) {
+            // The logger is cloned out so that its emission can install another one.
+            let fallback = FALLBACK_LOGGER
+                .read()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .clone();
+            if let Some(emit) = fallback {
+                emit(self.as_report_ref());
+            }
+        }
+    }
+}
+
+/// Extension trait for [`Result`]s of [`Report`](rootcause::Report)s, for
+/// logging the error in passing.
+pub trait ResultLogExt: Sized {
+    /// [Emit](LoggerExt::emit_error_report) the error, if any, with `logger`,
+    /// and return the result unchanged.
+    ///
+    /// ```
+    /// # use opentelemetry::logs::Logger;
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::log_event::ResultLogExt;
+    /// fn connect() -> Result<(), Report> {
+    ///     Err(report!("connection refused"))
+    /// }
+    ///
+    /// fn run(logger: &impl Logger) -> Result<(), Report> {
+    ///     connect().emit_err_report(logger)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    fn emit_err_report<L: Logger>(self, logger: &L) -> Self;
+}
+
+impl<V, E: AsReportRef> ResultLogExt for Result<V, E> {
+    fn emit_err_report<L: Logger>(self, logger: &L) -> Self {
+        if let Err(rep) = &self {
+            logger.emit_error_report(rep);
+        }
+        self
+    }
+}
+
+type FallbackFn = Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) + Send + Sync>;
+
+static FALLBACK_LOGGER: RwLock<Option<FallbackFn>> = RwLock::new(None);
+
+/// Set the logger used by [`ReportLogExt::record_or_log_fallback`] when there
+/// is no recording span, replacing any previous one.
+pub fn install_fallback_logger<L: Logger + Send + Sync + 'static>(logger: L) {
+    *FALLBACK_LOGGER
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) =
+        Some(Arc::new(move |rep| logger.emit_error_report(&rep)));
+}
+
+/// Record `rep` on the current span, returning whether it is recording.
+fn record_on_current_span(rep: &impl AsReportRef) -> bool {
+    let ctx = Context::current();
+    let span = ctx.span();
+    let recorder = span.record_error_report(rep);
+    let effective = recorder.is_effective();
+    if effective {
+        let _ = recorder.as_event();
+    }
+    effective
+}
+
+/// Builder for configuring how a [`Report`](rootcause::Report) is emitted as log records.
+///
+/// Nothing is emitted until [`Self::emit`] is called. The records are built as
+/// described on [`LoggerExt::emit_error_report`].
+#[must_use]
+pub struct LogRecordReportBuilder<'a, L: Logger> {
+    logger: &'a L,
+    report: ReportRef<'a, Dynamic, Uncloneable, Local>,
+    spec: ExceptionEventSpec,
+    event_name: &'static str,
+    target: Option<Cow<'static, str>>,
+    message: Option<String>,
+    body: Body,
+    granular: bool,
+    group_siblings: bool,
+    extra_attributes: Vec<KeyValue>,
+    span_context: Option<SpanContext>,
+    decision: Option<Decision>,
+    #[cfg(feature = "metrics")]
+    counter: Option<crate::meter::ExceptionCounter>,
+}
+
+impl<'a, L: Logger> LogRecordReportBuilder<'a, L> {
+    /// Use the given [`ExceptionEventSpec`] instead of the
+    /// [global](ExceptionEventSpec::install) one.
+    pub fn with_spec(mut self, spec: ExceptionEventSpec) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    /// Use `name` as the event name of the records instead of `exception`,
+    /// e.g. to tell apart errors of different subsystems.
+    pub fn event_name(mut self, name: &'static str) -> Self {
+        self.event_name = name;
+        self
+    }
+
+    /// Set the target of the records, e.g. the module path the error is reported from.
+    pub fn target(mut self, target: impl Into<Cow<'static, str>>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
 
-        let span_context = rep
-            .find_attachment_inner::<SpanContext>()
+    /// Correlate the records with `span_context`, instead of the [`TargetSpan`](crate::span_event::TargetSpan)
+    /// or [`SpanContext`] attached to the report, or the current span.
+    pub fn span_context(mut self, span_context: SpanContext) -> Self {
+        self.span_context = Some(span_context);
+        self
+    }
+
+    /// Also increment the [`exceptions` counter](crate::meter::MeterExt::count_error_report)
+    /// of a meter on [`Self::emit`], with the same `error.type` as the records.
+    ///
+    /// Takes a [`Meter`](opentelemetry::metrics::Meter), or an [`ExceptionCounter`](crate::meter::ExceptionCounter)
+    /// obtained once from it so that the instrument is not built for every report.
+    ///
+    /// The report is counted even when [sampling](crate::sampling) emits only
+    /// its metrics, but not when it drops the report altogether.
+    #[cfg(feature = "metrics")]
+    pub fn count_metric(mut self, counter: impl Into<crate::meter::ExceptionCounter>) -> Self {
+        self.counter = Some(counter.into());
+        self
+    }
+
+    /// Use `decision` instead of consulting the [`ReportSampler`](crate::sampling::ReportSampler),
+    /// for builders emitting the same report elsewhere as well.
+    pub(crate) fn with_decision(mut self, decision: Decision) -> Self {
+        self.decision = Some(decision);
+        self
+    }
+
+    /// Use `message` as the `exception.message` of the top-level record instead
+    /// of the formatted context of the report.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Whether [`KeyValue`]-typed attachments are added as attributes,
+    /// as in [`ExceptionEventSpec::attachment_attributes`].
+    pub fn attachment_attributes(mut self, enabled: bool) -> Self {
+        self.spec = self.spec.attachment_attributes(enabled);
+        self
+    }
+
+    /// Add the entries of the current baggage as attributes,
+    /// as in [`ExceptionEventSpec::baggage_attributes`].
+    pub fn with_baggage(mut self) -> Self {
+        self.spec = self.spec.baggage_attributes(true);
+        self
+    }
+
+    /// Emit attribute keys and shapes according to `profile`,
+    /// as in [`ExceptionEventSpec::semconv_profile`].
+    pub fn semconv_profile(mut self, profile: SemconvProfile) -> Self {
+        self.spec = self.spec.semconv_profile(profile);
+        self
+    }
+
+    /// Emit multi-line messages according to `policy`,
+    /// as in [`ExceptionEventSpec::message_lines`].
+    pub fn message_lines(mut self, policy: MessageLines) -> Self {
+        self.spec = self.spec.message_lines(policy);
+        self
+    }
+
+    /// Set the body of the record to a structured map of the whole report tree,
+    /// as in [`LoggerExt::emit_error_report_structured`].
+    pub fn structured_body(mut self) -> Self {
+        self.body = Body::Structured;
+        self
+    }
+
+    /// Set the body of the record to the whole formatted report as plain text,
+    /// as in [`LoggerExt::emit_error_report_plain`].
+    pub fn plain_body(mut self) -> Self {
+        self.body = Body::Plain;
+        self
+    }
+
+    /// Emit one record per report in the tree,
+    /// as in [`LoggerExt::emit_error_report_granular`].
+    pub fn granular(mut self) -> Self {
+        self.granular = true;
+        self
+    }
+
+    /// Emit one record per report in the tree as in [`Self::granular`], except
+    /// that aggregated reports whose children all originate in the same trace
+    /// are emitted as a single record, reducing the log volume of batched failures.
+    ///
+    /// The body of that record is a list holding a map of each child report
+    /// and its descendants, shaped as in [`LoggerExt::emit_error_report_structured`].
+    pub fn group_siblings(mut self) -> Self {
+        self.granular = true;
+        self.group_siblings = true;
+        self
+    }
+
+    /// Add an attribute to every emitted record.
+    pub fn attribute(mut self, kv: KeyValue) -> Self {
+        self.extra_attributes.push(kv);
+        self
+    }
+
+    /// Add attributes to every emitted record.
+    pub fn attributes(mut self, kvs: impl IntoIterator<Item = KeyValue>) -> Self {
+        self.extra_attributes.extend(kvs);
+        self
+    }
+
+    /// Emit the record(s).
+    pub fn emit(mut self) {
+        let decision = self
+            .decision
+            .unwrap_or_else(|| sampling::decide(self.report));
+        #[cfg(feature = "metrics")]
+        if let Some(counter) = &self.counter
+            && decision != Decision::Drop
+        {
+            counter.record(&self.report, &[]);
+        }
+        let brief = match decision {
+            Decision::Full => false,
+            Decision::Brief => true,
+            Decision::MetricsOnly | Decision::Drop => {
+                #[cfg(feature = "metrics")]
+                crate::meter::record_dropped(crate::meter::DropReason::Sampled);
+                return;
+            }
+        };
+        if brief {
+            self.body = Body::None;
+        }
+        if self.group_siblings {
+            self.emit_grouped(self.report, true, brief);
+        } else if self.granular {
+            for (index, sub_rep) in self.report.iter_reports().enumerate() {
+                let mut attributes = attributes_brief(sub_rep, &self.spec);
+                if index == 0 {
+                    self.override_message(&mut attributes);
+                }
+                self.emit_record(sub_rep, attributes);
+            }
+        } else {
+            let mut attributes = if brief {
+                attributes_brief(self.report, &self.spec)
+            } else {
+                attributes(self.report, &self.spec)
+            };
+            self.override_message(&mut attributes);
+            self.emit_record(self.report, attributes);
+        }
+    }
+
+    /// Emit `rep` and its descendants as in [`Self::granular`], grouping the
+    /// children of reports for which [`shared_trace_id`] holds.
+    fn emit_grouped(
+        &self,
+        rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+        top: bool,
+        brief: bool,
+    ) {
+        let mut attributes = attributes_brief(rep, &self.spec);
+        if top {
+            self.override_message(&mut attributes);
+        }
+        if shared_trace_id(rep) {
+            let body = (!brief).then(|| {
+                let children = rep
+                    .children()
+                    .iter()
+                    .map(|child| report_map(child.into_uncloneable()))
+                    .collect();
+                AnyValue::ListAny(Box::new(children))
+            });
+            self.emit_record_with_body(rep, attributes, body);
+            return;
+        }
+        self.emit_record(rep, attributes);
+        for child in rep.children().iter() {
+            self.emit_grouped(child.into_uncloneable(), false, brief);
+        }
+    }
+
+    fn override_message(&self, attributes: &mut Vec<KeyValue>) {
+        if let Some(message) = &self.message
+            && let Some(kv) = attributes.iter_mut().find(|kv| {
+                matches!(
+                    kv.key.as_str(),
+                    attribute::EXCEPTION_MESSAGE | ERROR_MESSAGE
+                )
+            })
+        {
+            kv.value = message.clone().into();
+            split_message(attributes, self.spec.message_lines);
+        }
+    }
+
+    fn emit_record(
+        &self,
+        rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+        attributes: Vec<KeyValue>,
+    ) {
+        let body = match self.body {
+            Body::None => None,
+            Body::Structured => Some(structured_body(rep, &self.spec)),
+            Body::Plain => Some(plain_body(rep, &self.spec)),
+        };
+        self.emit_record_with_body(rep, attributes, body);
+    }
+
+    fn emit_record_with_body(
+        &self,
+        rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+        mut attributes: Vec<KeyValue>,
+        body: Option<AnyValue>,
+    ) {
+        attributes.extend(self.extra_attributes.iter().cloned());
+        let mut snapshot =
+            ExceptionSnapshot::new(rep, Destination::LogRecord, timestamp(rep), attributes);
+        snapshot.body = body;
+        #[cfg(feature = "serde")]
+        let snapshot = ExceptionSnapshot {
+            log_attributes: if self.spec.attachment_attributes {
+                crate::structured::attributes(rep)
+            } else {
+                Vec::new()
+            },
+            ..snapshot
+        };
+        let span_context = self
+            .span_context
+            .as_ref()
+            .or_else(|| target_span(rep))
+            .or_else(|| correlation::span_context(&rep))
             .cloned()
             .unwrap_or_else(|| Context::current().span().span_context().clone());
-
-        if span_context.is_valid() {
-            record.set_trace_context(
-                span_context.trace_id(),
-                span_context.span_id(),
-                Some(span_context.trace_flags()),
-            );
+        let Some(snapshot) = pipeline::process(snapshot, &self.spec, &span_context) else {
+            return;
+        };
+        let severity = severity_of(rep);
+        legacy::mirror(&snapshot, severity);
+        let mut record = exception_record(
+            self.logger,
+            snapshot,
+            severity,
+            self.event_name,
+            &span_context,
+        );
+        if let Some(target) = &self.target {
+            record.set_target(target.clone());
         }
+        self.logger.emit(record);
+    }
+}
+
+enum Body {
+    None,
+    Structured,
+    Plain,
+}
+
+fn exception_record<L: Logger>(
+    logger: &L,
+    snapshot: ExceptionSnapshot<'_>,
+    severity: Severity,
+    event_name: &'static str,
+    span_context: &SpanContext,
+) -> L::LogRecord {
+    let mut record = logger.create_log_record();
+    record.set_event_name(event_name);
+    record.set_observed_timestamp(snapshot.timestamp);
+    record.set_timestamp(clock::now());
+
+    record.set_severity_number(severity);
+    record.set_severity_text(severity.name());
+
+    if span_context.is_valid() {
+        record.set_trace_context(
+            span_context.trace_id(),
+            span_context.span_id(),
+            Some(span_context.trace_flags()),
+        );
+    }
 
-        for kv in attributes(rep) {
-            record.add_attribute(kv.key, kv.value.into_anyvalue());
+    for kv in snapshot.attributes {
+        record.add_attribute(kv.key, kv.value.into_anyvalue());
+    }
+    for (key, value) in snapshot.log_attributes {
+        record.add_attribute(key, value);
+    }
+    if let Some(body) = snapshot.body {
+        record.set_body(body);
+    }
+
+    record
+}
+
+/// Whether `rep` has several children, all originating in the same valid trace.
+fn shared_trace_id(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> bool {
+    if rep.children().iter().nth(1).is_none() {
+        return false;
+    }
+    let mut trace_ids = rep.children().iter().map(|child| {
+        let child = child.into_uncloneable();
+        target_span(child)
+            .or_else(|| correlation::span_context(&child))
+            .filter(|span_context| span_context.is_valid())
+            .map(|span_context| span_context.trace_id())
+    });
+    let Some(Some(first)) = trace_ids.next() else {
+        return false;
+    };
+    trace_ids.all(|trace_id| trace_id == Some(first))
+}
+
+fn structured_body(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> AnyValue {
+    let mut body = report_map(rep);
+    if let AnyValue::Map(map) = &mut body {
+        let mut stacktrace = format_report(rep);
+        if let Some(limit) = spec.max_stacktrace_len {
+            truncate_middle(&mut stacktrace, limit);
         }
+        map.insert(Key::from_static_str("stacktrace"), stacktrace.into());
+    }
+    body
+}
+
+fn plain_body(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> AnyValue {
+    let mut text = strip_ansi(&format_report(rep));
+    if let Some(limit) = spec.max_stacktrace_len {
+        truncate_middle(&mut text, limit);
+    }
+    text.into()
+}
+
+fn report_map(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> AnyValue {
+    let mut map = HashMap::new();
+    map.insert(
+        Key::from_static_str("type"),
+        AnyValue::from(crate::error_type::type_name(rep)),
+    );
+    map.insert(
+        Key::from_static_str("message"),
+        format_contained(rep.current_context_type_name(), || {
+            rep.format_current_context().to_string()
+        })
+        .into(),
+    );
 
-        self.emit(record);
+    let children: Vec<AnyValue> = rep
+        .children()
+        .iter()
+        .map(|child| report_map(child.into_uncloneable()))
+        .collect();
+    if !children.is_empty() {
+        map.insert(
+            Key::from_static_str("children"),
+            AnyValue::ListAny(Box::new(children)),
+        );
+    }
+
+    let mut attachments: HashMap<Key, AnyValue> = HashMap::new();
+    for attachment in visible_attachments(rep) {
+        let Some((key, value)) = attachment_entry(attachment) else {
+            continue;
+        };
+        match attachments.remove(&key) {
+            None => attachments.insert(key, value),
+            Some(AnyValue::ListAny(mut values)) => {
+                values.push(value);
+                attachments.insert(key, AnyValue::ListAny(values))
+            }
+            Some(previous) => attachments.insert(key, vec![previous, value].into_anyvalue()),
+        };
+    }
+    if !attachments.is_empty() {
+        map.insert(
+            Key::from_static_str("attachments"),
+            AnyValue::Map(Box::new(attachments)),
+        );
+    }
+
+    AnyValue::Map(Box::new(map))
+}
+
+/// The key and structured value of an [`AnyValueAttachment`](crate::structured::AnyValueAttachment),
+/// or the type name and [converted attributes](crate::conversion::IntoOtelAttributes),
+/// [`ExtraEntry`](crate::extra::ExtraEntry) or formatted value of any other
+/// attachment, unless its `ExtraEntry` leaves it out.
+fn attachment_entry(attachment: ReportAttachmentRef<'_, Dynamic>) -> Option<(Key, AnyValue)> {
+    #[cfg(feature = "serde")]
+    if let Some(structured) = attachment.downcast_inner::<crate::structured::AnyValueAttachment>()
+        && let Some(value) = structured.to_anyvalue()
+    {
+        return Some((structured.key().clone(), value));
+    }
+    let key = Key::from_static_str(attachment.inner_type_name());
+    if let Some(attributes) = conversion::attributes_of(attachment) {
+        let map = attributes
+            .into_iter()
+            .map(|kv| (kv.key, kv.value.into_anyvalue()))
+            .collect();
+        return Some((key, AnyValue::Map(Box::new(map))));
+    }
+    match extra::extra_of(attachment) {
+        Some(Extra::Value(value)) => Some((key, AnyValue::String(value))),
+        Some(Extra::Omit) => None,
+        None => Some((
+            key,
+            AnyValue::from(format_contained(attachment.inner_type_name(), || {
+                attachment.format_inner().to_string()
+            })),
+        )),
     }
 }
 
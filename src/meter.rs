@@ -0,0 +1,307 @@
+//! Counting error reports as metrics, which unlike exception events are not
+//! subject to trace sampling, so error rates stay accurate in sampled traffic,
+//! measuring the shape of emitted report trees, and counting what the bridge
+//! itself emits and loses.
+
+use std::{borrow::Cow, sync::RwLock};
+
+use opentelemetry::{
+    KeyValue,
+    metrics::{Counter, Histogram, Meter},
+};
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::{
+    error_type::error_type_of,
+    exemplar::ExemplarReportExt,
+    pipeline::Destination,
+    sampling::{self, Decision},
+    utilities::AsReportRef,
+};
+
+pub const EXCEPTIONS: &str = "exceptions";
+pub const REPORT_DEPTH: &str = "rootcause.report.depth";
+pub const REPORT_CHILDREN: &str = "rootcause.report.children";
+pub const REPORT_ATTACHMENTS: &str = "rootcause.report.attachments";
+pub const BRIDGE_EMITTED: &str = "rootcause.bridge.emitted";
+pub const BRIDGE_DROPPED: &str = "rootcause.bridge.dropped";
+pub const BRIDGE_ATTRIBUTES_TRUNCATED: &str = "rootcause.bridge.attributes_truncated";
+pub const BRIDGE_LINKS_ELIDED: &str = "rootcause.bridge.links_elided";
+pub const BRIDGE_DESTINATION: &str = "rootcause.bridge.destination";
+pub const BRIDGE_DROP_REASON: &str = "rootcause.bridge.drop_reason";
+
+static SHAPE_METRICS: RwLock<Option<ShapeMetrics>> = RwLock::new(None);
+static BRIDGE_METRICS: RwLock<Option<BridgeMetrics>> = RwLock::new(None);
+
+/// Extension trait for [`Meter`]s, for counting error reports.
+pub trait MeterExt {
+    /// The `exceptions` counter, for counting many reports without looking up
+    /// the instrument each time.
+    fn exception_counter(&self) -> ExceptionCounter;
+
+    /// Increment the `exceptions` counter for `rep`, see [`ExceptionCounter::count`].
+    ///
+    /// ```
+    /// # use opentelemetry::global;
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::meter::MeterExt;
+    /// let report = report!("payment declined");
+    /// global::meter("checkout").count_error_report(&report);
+    /// ```
+    fn count_error_report(&self, rep: &impl AsReportRef) {
+        self.exception_counter().count(rep);
+    }
+}
+
+impl MeterExt for Meter {
+    fn exception_counter(&self) -> ExceptionCounter {
+        ExceptionCounter(
+            self.u64_counter(EXCEPTIONS)
+                .with_description("Error reports, by error type")
+                .with_unit("{exception}")
+                .build(),
+        )
+    }
+}
+
+/// The `exceptions` counter, see [`MeterExt::exception_counter`].
+#[derive(Clone)]
+pub struct ExceptionCounter(Counter<u64>);
+
+impl From<&Meter> for ExceptionCounter {
+    fn from(meter: &Meter) -> Self {
+        meter.exception_counter()
+    }
+}
+
+impl ExceptionCounter {
+    /// Count `rep` with its `error.type` as the attribute.
+    ///
+    /// ## Attributes & Details
+    /// - `error.type` is the [`OtelErrorType`](crate::error_type::OtelErrorType) of the context,
+    ///   or its [normalized](crate::error_type::TypeNameNormalizer) type name.
+    /// - The measurement is recorded in the [exemplar context](ExemplarReportExt::exemplar_context)
+    ///   of the report, so that exemplars point at the failing trace.
+    pub fn count(&self, rep: &impl AsReportRef) {
+        self.count_with(rep, &[]);
+    }
+
+    /// Count `rep` as in [`Self::count`], with `dimensions` as additional
+    /// attributes, which should be of low cardinality.
+    ///
+    /// Reports which the [`ReportSampler`](crate::sampling::ReportSampler)
+    /// decides to [`Drop`](Decision::Drop) are not counted.
+    pub fn count_with(&self, rep: &impl AsReportRef, dimensions: &[KeyValue]) {
+        if sampling::decide(rep.as_report_ref()) != Decision::Drop {
+            self.record(rep, dimensions);
+        }
+    }
+
+    /// Count `rep` as in [`Self::count_with`], regardless of sampling, for
+    /// builders which have already consulted the sampler.
+    pub(crate) fn record(&self, rep: &impl AsReportRef, dimensions: &[KeyValue]) {
+        let mut attributes = Vec::with_capacity(dimensions.len() + 1);
+        attributes.push(KeyValue::new(
+            attribute::ERROR_TYPE,
+            error_type_of(rep.as_report_ref()),
+        ));
+        attributes.extend(dimensions.iter().cloned());
+        rep.in_exemplar_context(|| self.0.add(1, &attributes));
+    }
+
+    /// Add `count` reports of `error_type`, counted elsewhere.
+    pub(crate) fn add_for_type(&self, error_type: Cow<'static, str>, count: u64) {
+        self.0
+            .add(count, &[KeyValue::new(attribute::ERROR_TYPE, error_type)]);
+    }
+}
+
+/// Record the shape of every emitted report tree with `meter`, so that
+/// operators notice when trees get pathologically deep or attachment-heavy.
+///
+/// ## Instruments
+/// - `rootcause.report.depth` is a histogram of the number of levels of the tree.
+/// - `rootcause.report.children` is a histogram of the number of reports below the top-level one.
+/// - `rootcause.report.attachments` is a histogram of the number of attachments in the whole tree.
+///
+/// They are recorded once per emission which is not aborted by an
+/// [`EmitLayer`](crate::pipeline::EmitLayer), so a report emitted both as a
+/// span event and as a log record is recorded twice.
+pub fn enable_shape_metrics(meter: &Meter) {
+    let metrics = ShapeMetrics {
+        depth: meter
+            .u64_histogram(REPORT_DEPTH)
+            .with_description("Levels of emitted report trees")
+            .build(),
+        children: meter
+            .u64_histogram(REPORT_CHILDREN)
+            .with_description("Reports below the top-level one in emitted report trees")
+            .build(),
+        attachments: meter
+            .u64_histogram(REPORT_ATTACHMENTS)
+            .with_description("Attachments in emitted report trees")
+            .build(),
+    };
+    *SHAPE_METRICS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = Some(metrics);
+}
+
+/// Stop recording the metrics enabled by [`enable_shape_metrics`].
+pub fn disable_shape_metrics() {
+    *SHAPE_METRICS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = None;
+}
+
+struct ShapeMetrics {
+    depth: Histogram<u64>,
+    children: Histogram<u64>,
+    attachments: Histogram<u64>,
+}
+
+/// Record the shape of `rep` if [enabled](enable_shape_metrics).
+pub(crate) fn record_shape(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) {
+    let metrics = SHAPE_METRICS
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner());
+    let Some(metrics) = &*metrics else {
+        return;
+    };
+    let (mut reports, mut attachments) = (0u64, 0u64);
+    for r in rep.iter_reports() {
+        reports += 1;
+        attachments += r.attachments().iter().count() as u64;
+    }
+    metrics.depth.record(depth(rep), &[]);
+    metrics.children.record(reports - 1, &[]);
+    metrics.attachments.record(attachments, &[]);
+}
+
+fn depth(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> u64 {
+    1 + rep
+        .children()
+        .iter()
+        .map(|child| depth(child.into_uncloneable()))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Count what the bridge emits and loses with `meter`, so that operators can
+/// tell when the error-reporting pipeline itself is lossy.
+///
+/// ## Instruments
+/// - `rootcause.bridge.emitted` counts span events, span attribute sets and log records emitted,
+///   with `rootcause.bridge.destination` as `span_event`, `span_attributes` or `log_record`.
+/// - `rootcause.bridge.dropped` counts emissions which did not happen, with
+///   `rootcause.bridge.drop_reason` as one of
+///   - `not_recording`, when there is no span or it is not recording,
+///   - `sampled`, when the [`ReportSampler`](crate::sampling::ReportSampler) decided against it,
+///   - `layer`, when an [`EmitLayer`](crate::pipeline::EmitLayer) aborted it, e.g. for rate limiting.
+/// - `rootcause.bridge.attributes_truncated` counts attributes dropped by
+///   [`ExceptionEventSpec::max_attributes`](crate::spec::ExceptionEventSpec::max_attributes).
+/// - `rootcause.bridge.links_elided` counts span links not added by
+///   [`link_child_report_spans`](crate::span_event::RecordErrorReport::link_child_report_spans).
+pub fn enable_bridge_metrics(meter: &Meter) {
+    let metrics = BridgeMetrics {
+        emitted: meter
+            .u64_counter(BRIDGE_EMITTED)
+            .with_description("Exceptions emitted, by destination")
+            .build(),
+        dropped: meter
+            .u64_counter(BRIDGE_DROPPED)
+            .with_description("Exception emissions which did not happen, by reason")
+            .build(),
+        attributes_truncated: meter
+            .u64_counter(BRIDGE_ATTRIBUTES_TRUNCATED)
+            .with_description("Exception attributes dropped by the attribute limit")
+            .build(),
+        links_elided: meter
+            .u64_counter(BRIDGE_LINKS_ELIDED)
+            .with_description("Span links to child reports which were not added")
+            .build(),
+    };
+    *BRIDGE_METRICS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = Some(metrics);
+}
+
+/// Stop recording the metrics enabled by [`enable_bridge_metrics`].
+pub fn disable_bridge_metrics() {
+    *BRIDGE_METRICS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = None;
+}
+
+struct BridgeMetrics {
+    emitted: Counter<u64>,
+    dropped: Counter<u64>,
+    attributes_truncated: Counter<u64>,
+    links_elided: Counter<u64>,
+}
+
+/// Why an emission did not happen, see [`enable_bridge_metrics`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum DropReason {
+    NotRecording,
+    Sampled,
+    Layer,
+}
+
+impl DropReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::NotRecording => "not_recording",
+            Self::Sampled => "sampled",
+            Self::Layer => "layer",
+        }
+    }
+}
+
+fn with_bridge_metrics(f: impl FnOnce(&BridgeMetrics)) {
+    let metrics = BRIDGE_METRICS
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner());
+    if let Some(metrics) = &*metrics {
+        f(metrics);
+    }
+}
+
+/// Count an emission to `destination` if [enabled](enable_bridge_metrics).
+pub(crate) fn record_emitted(destination: Destination) {
+    let destination = match destination {
+        Destination::SpanEvent => "span_event",
+        Destination::SpanAttributes => "span_attributes",
+        Destination::LogRecord => "log_record",
+        Destination::SpanLink => "span_link",
+        Destination::SpanStatus => "span_status",
+    };
+    with_bridge_metrics(|metrics| {
+        metrics
+            .emitted
+            .add(1, &[KeyValue::new(BRIDGE_DESTINATION, destination)]);
+    });
+}
+
+/// Count an emission which did not happen if [enabled](enable_bridge_metrics).
+pub(crate) fn record_dropped(reason: DropReason) {
+    with_bridge_metrics(|metrics| {
+        metrics
+            .dropped
+            .add(1, &[KeyValue::new(BRIDGE_DROP_REASON, reason.as_str())]);
+    });
+}
+
+/// Count `count` attributes dropped by the limit if [enabled](enable_bridge_metrics).
+pub(crate) fn record_attributes_truncated(count: u64) {
+    with_bridge_metrics(|metrics| metrics.attributes_truncated.add(count, &[]));
+}
+
+/// Count `count` span links not added if [enabled](enable_bridge_metrics).
+pub(crate) fn record_links_elided(count: u64) {
+    with_bridge_metrics(|metrics| metrics.links_elided.add(count, &[]));
+}
@@ -0,0 +1,134 @@
+//! Counting reports which were created but not yet emitted, surfacing errors
+//! which are swallowed without ever reaching telemetry.
+//!
+//! The [`OutstandingReportCollector`] increments the
+//! `rootcause.reports.outstanding` up-down counter when a report is created,
+//! and attaches an [`OutstandingReport`] which decrements it again when the
+//! report is first emitted, or when it is dropped without having been emitted.
+//! The up-down counter thus tracks the reports currently alive and unreported.
+//!
+//! Reports dropped without having been emitted, on their own or as part of a
+//! report tree which never was, are also counted by the
+//! `rootcause.reports.discarded` counter. A steadily rising count means reports are being discarded, e.g. by
+//! `.ok()` or `let _ =`, rather than handled.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use opentelemetry::metrics::{Counter, Meter, UpDownCounter};
+use rootcause::{
+    ReportMut, ReportRef,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync, Uncloneable},
+};
+
+use crate::{attachments::Hidden, utilities::AttachmentsExt};
+
+pub const REPORTS_OUTSTANDING: &str = "rootcause.reports.outstanding";
+pub const REPORTS_DISCARDED: &str = "rootcause.reports.discarded";
+
+/// Attachment holding a report's share of the `rootcause.reports.outstanding`
+/// counter until it is settled by emission or dropped, counting the report as
+/// discarded in the latter case.
+pub struct OutstandingReport {
+    counter: UpDownCounter<i64>,
+    discarded: Counter<u64>,
+    settled: AtomicBool,
+}
+
+impl OutstandingReport {
+    fn new(collector: &OutstandingReportCollector) -> Self {
+        collector.counter.add(1, &[]);
+        Self {
+            counter: collector.counter.clone(),
+            discarded: collector.discarded.clone(),
+            settled: AtomicBool::new(false),
+        }
+    }
+
+    /// Decrement the counter for the report, unless it already was.
+    pub fn settle(&self) {
+        if !self.settled.swap(true, Ordering::AcqRel) {
+            self.counter.add(-1, &[]);
+        }
+    }
+
+    /// Whether the report has been emitted.
+    pub fn is_settled(&self) -> bool {
+        self.settled.load(Ordering::Acquire)
+    }
+}
+
+impl fmt::Debug for OutstandingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutstandingReport")
+            .field("settled", &self.is_settled())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for OutstandingReport {
+    fn drop(&mut self) {
+        if !self.is_settled() {
+            self.discarded.add(1, &[]);
+        }
+        self.settle();
+    }
+}
+
+/// Report creation hook attaching an [`OutstandingReport`] to new reports.
+///
+/// Both thread-local and thread-safe reports are counted.
+#[derive(Clone)]
+pub struct OutstandingReportCollector {
+    counter: UpDownCounter<i64>,
+    discarded: Counter<u64>,
+}
+
+impl OutstandingReportCollector {
+    /// Count outstanding reports on the `rootcause.reports.outstanding`
+    /// up-down counter of `meter`, and discarded ones on its
+    /// `rootcause.reports.discarded` counter.
+    pub fn new(meter: &Meter) -> Self {
+        Self {
+            counter: meter
+                .i64_up_down_counter(REPORTS_OUTSTANDING)
+                .with_description("Reports created but neither emitted nor dropped yet")
+                .with_unit("{report}")
+                .build(),
+            discarded: meter
+                .u64_counter(REPORTS_DISCARDED)
+                .with_description("Reports dropped without having been emitted")
+                .with_unit("{report}")
+                .build(),
+        }
+    }
+}
+
+impl fmt::Debug for OutstandingReportCollector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutstandingReportCollector")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReportCreationHook for OutstandingReportCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        let _ = report.attach_custom::<Hidden, _>(OutstandingReport::new(self));
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        let _ = report.attach_custom::<Hidden, _>(OutstandingReport::new(self));
+    }
+}
+
+/// Settle every report in the tree of `rep`, which is being emitted.
+pub(crate) fn settle(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) {
+    for sub_rep in rep.iter_reports() {
+        if let Some(outstanding) = sub_rep.find_attachment_inner::<OutstandingReport>() {
+            outstanding.settle();
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Recording panics as exception events, so that they show up in traces and
+//! not only on standard error.
+//!
+//! [`install_panic_hook`] chains a panic hook turning each panic into a
+//! [`Report<Panic>`] and recording it on the current span, before running the
+//! previously installed hook, so the usual panic message is still printed.
+
+use std::{
+    any::Any,
+    backtrace::{Backtrace, BacktraceStatus},
+    borrow::Cow,
+    cell::Cell,
+    error::Error,
+    fmt,
+    panic::{self, PanicHookInfo},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::Report;
+
+use crate::{
+    attachments::AttributeReportExt,
+    error_type::{OtelErrorType, register_error_type},
+    utilities::EXCEPTION_ESCAPED,
+};
+
+/// The `error.type` of panics.
+pub const PANIC_ERROR_TYPE: &str = "panic";
+
+static HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Depth of [`without_panic_recording`] calls on this thread.
+    static SUPPRESSED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Context of a report of a panic.
+#[derive(Debug, Clone)]
+pub struct Panic {
+    /// The panic message, or `Box<dyn Any>` if the payload is not a string.
+    pub message: String,
+    /// Where the panic occurred, as `file:line:column`.
+    pub location: Option<String>,
+    /// The name of the panicking thread, if it has one.
+    pub thread: Option<String>,
+}
+
+impl Panic {
+    fn new(info: &PanicHookInfo<'_>) -> Self {
+        Self {
+            location: info.location().map(ToString::to_string),
+            ..Self::from_payload(info.payload())
+        }
+    }
+
+    fn from_payload(payload: &(dyn Any + Send)) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| (*message).to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_owned());
+        Self {
+            message,
+            location: None,
+            thread: thread::current().name().map(ToOwned::to_owned),
+        }
+    }
+}
+
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(thread) = &self.thread {
+            write!(f, "thread '{thread}' ")?;
+        }
+        f.write_str("panicked")?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Error for Panic {}
+
+impl OtelErrorType for Panic {
+    fn otel_error_type(&self) -> Cow<'static, str> {
+        Cow::Borrowed(PANIC_ERROR_TYPE)
+    }
+}
+
+#[cfg(feature = "logs")]
+impl crate::severity::OtelSeverity for Panic {
+    fn otel_severity(&self) -> opentelemetry::logs::Severity {
+        opentelemetry::logs::Severity::Fatal
+    }
+}
+
+/// Turn the panic described by `info` into a report, as done by the hook of
+/// [`install_panic_hook`].
+///
+/// ## Attributes & Details
+/// - The context is a [`Panic`] with the message, location and thread name.
+/// - `code.file.path`, `code.line.number` and `code.column.number` are the
+///   location of the panic, rather than where the report was created.
+/// - `exception.escaped` is `true`, as the panic unwinds out of the span.
+/// - A [`Backtrace`] is attached if enabled by `RUST_BACKTRACE` or
+///   `RUST_LIB_BACKTRACE`, as for the default panic message.
+pub fn panic_report(info: &PanicHookInfo<'_>) -> Report<Panic> {
+    let mut rep = Report::new(Panic::new(info)).attach_attribute(EXCEPTION_ESCAPED, true);
+    if let Some(location) = info.location() {
+        rep = rep.attach_attributes([
+            KeyValue::new(attribute::CODE_FILE_PATH, location.file().to_owned()),
+            KeyValue::new(attribute::CODE_LINE_NUMBER, i64::from(location.line())),
+            KeyValue::new(attribute::CODE_COLUMN_NUMBER, i64::from(location.column())),
+        ]);
+    }
+    let backtrace = Backtrace::capture();
+    if backtrace.status() == BacktraceStatus::Captured {
+        rep = rep.attach(backtrace);
+    }
+    rep
+}
+
+/// Turn a panic caught by [`catch_unwind`](std::panic::catch_unwind) into a
+/// report as [`panic_report`] would, except that the location and backtrace
+/// are lost by then. The `error.type` of [`Panic`] is registered as well.
+pub(crate) fn caught_panic_report(payload: &(dyn Any + Send)) -> Report<Panic> {
+    register_error_type::<Panic>();
+    Report::new(Panic::from_payload(payload)).attach_attribute(EXCEPTION_ESCAPED, true)
+}
+
+/// Record every panic as an `exception` event on the current span, or as a
+/// log record with the [fallback logger](crate::log_event::install_fallback_logger)
+/// if no span is recording, see [`library::report`](crate::library::report).
+///
+/// The report is built by [`panic_report`], and the `error.type` of [`Panic`]
+/// is registered. The previously installed panic hook runs afterwards, so the
+/// panic is still printed to standard error. Panics escaping the span do not
+/// end it, so spans ended by unwinding, such as those held by guards, carry
+/// the event when exported.
+///
+/// Panics raised while this crate is emitting a report, or formatting an
+/// attachment whose panic it contains, are not recorded, so that contained
+/// panics leave no spurious events and the hook never re-enters emission.
+///
+/// Calling this more than once has no further effect.
+///
+/// ```no_run
+/// rootcause_opentelemetry::panic::install_panic_hook();
+/// ```
+pub fn install_panic_hook() {
+    if HOOK_INSTALLED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    register_error_type::<Panic>();
+    #[cfg(feature = "logs")]
+    crate::severity::register_severity::<Panic>();
+
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !recording_suppressed() {
+            without_panic_recording(|| crate::library::report(&panic_report(info)));
+        }
+        previous(info);
+    }));
+}
+
+/// Whether [`install_panic_hook`] was called, in which case panics are
+/// recorded already and must not be recorded again when caught.
+pub(crate) fn hook_installed() -> bool {
+    HOOK_INSTALLED.load(Ordering::Acquire)
+}
+
+/// Run `f` without the hook of [`install_panic_hook`] recording the panics it
+/// raises on this thread, for emission code and contained formatting.
+pub(crate) fn without_panic_recording<R>(f: impl FnOnce() -> R) -> R {
+    struct Restore;
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let _ = SUPPRESSED.try_with(|depth| depth.set(depth.get().saturating_sub(1)));
+        }
+    }
+
+    let _ = SUPPRESSED.try_with(|depth| depth.set(depth.get() + 1));
+    let _restore = Restore;
+    f()
+}
+
+/// Whether the current thread is within [`without_panic_recording`].
+fn recording_suppressed() -> bool {
+    SUPPRESSED.try_with(|depth| depth.get() > 0).unwrap_or(true)
+}
@@ -0,0 +1,424 @@
+//! Middleware chain through which every emitted exception passes, for
+//! cross-cutting policies such as redaction, rate limiting and routing.
+
+use std::{
+    any::type_name,
+    borrow::Cow,
+    ops::ControlFlow,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use opentelemetry::{KeyValue, trace::SpanContext};
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::{
+    spec::{ExceptionEventSpec, SemconvProfile},
+    utilities::{
+        ERROR_MESSAGE, EXCEPTION_DROPPED_ATTRIBUTE_COUNT, EXCEPTION_ESCAPED, forward_trace_ids,
+    },
+};
+
+static LAYERS: RwLock<Vec<Arc<dyn EmitLayer>>> = RwLock::new(Vec::new());
+
+#[cfg(feature = "metrics")]
+static LAYER_METRICS: RwLock<Option<LayerMetrics>> = RwLock::new(None);
+
+#[cfg(feature = "metrics")]
+pub const EMIT_LAYER_DROPPED: &str = "rootcause.emit_layer.dropped";
+#[cfg(feature = "metrics")]
+pub const EMIT_LAYER_MUTATED: &str = "rootcause.emit_layer.mutated";
+#[cfg(feature = "metrics")]
+pub const EMIT_LAYER_DURATION: &str = "rootcause.emit_layer.duration";
+#[cfg(feature = "metrics")]
+pub const EMIT_LAYER_NAME: &str = "rootcause.emit_layer.name";
+
+/// Where an [`ExceptionSnapshot`] is about to be emitted to.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Destination {
+    /// An `exception` event on a span.
+    SpanEvent,
+    /// Attributes on the span itself.
+    SpanAttributes,
+    /// A log record.
+    LogRecord,
+    /// The attributes of a link from a span to the span a child report
+    /// originated in.
+    SpanLink,
+    /// The status of a span, and its `error.type` attribute.
+    SpanStatus,
+}
+
+impl Destination {
+    /// Whether the destination records the exception itself, rather than
+    /// pointing at or summarizing it.
+    pub(crate) fn is_exception(self) -> bool {
+        matches!(
+            self,
+            Self::SpanEvent | Self::SpanAttributes | Self::LogRecord
+        )
+    }
+}
+
+/// An exception about to be emitted, as passed through the [`EmitLayer`]s.
+///
+/// The attributes, timestamp, body, status description and report tree have
+/// already been derived from the report and the [`ExceptionEventSpec`](crate::spec::ExceptionEventSpec),
+/// and are emitted as left by the last layer.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct ExceptionSnapshot<'a> {
+    /// The report being emitted.
+    pub report: ReportRef<'a, Dynamic, Uncloneable, Local>,
+    pub destination: Destination,
+    pub timestamp: SystemTime,
+    pub attributes: Vec<KeyValue>,
+    /// Structured attributes of log records which have no [`KeyValue`]
+    /// representation, such as those of [`AnyValueAttachment`](crate::structured::AnyValueAttachment)s.
+    /// Always empty for other destinations.
+    #[cfg(feature = "logs")]
+    pub log_attributes: Vec<(opentelemetry::Key, opentelemetry::logs::AnyValue)>,
+    /// The body of log records, if any. Always `None` for other destinations.
+    #[cfg(feature = "logs")]
+    pub body: Option<opentelemetry::logs::AnyValue>,
+    /// The description of the [`Error`](opentelemetry::trace::Status::Error)
+    /// span status. Always `None` for other destinations.
+    pub description: Option<Cow<'static, str>>,
+    /// The report tree added as the `exception.report.cbor` attribute once the
+    /// layers ran, with [`ExceptionEventSpec::report_cbor`](crate::spec::ExceptionEventSpec::report_cbor).
+    pub report_tree: Option<crate::cbor::ReportTree>,
+    /// Whether a [`DeduplicateLayer`](crate::emission_guard::DeduplicateLayer)
+    /// marked the report as emitted, to be undone if a later layer aborts.
+    pub(crate) claimed: bool,
+}
+
+impl<'a> ExceptionSnapshot<'a> {
+    pub(crate) fn new(
+        report: ReportRef<'a, Dynamic, Uncloneable, Local>,
+        destination: Destination,
+        timestamp: SystemTime,
+        attributes: Vec<KeyValue>,
+    ) -> Self {
+        Self {
+            report,
+            destination,
+            timestamp,
+            attributes,
+            #[cfg(feature = "logs")]
+            log_attributes: Vec::new(),
+            #[cfg(feature = "logs")]
+            body: None,
+            description: None,
+            report_tree: None,
+            claimed: false,
+        }
+    }
+
+    /// The value of the attribute with the given key, if any.
+    pub fn attribute(&self, key: &str) -> Option<&opentelemetry::Value> {
+        self.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| &kv.value)
+    }
+
+    /// Set the attribute with the given key, replacing any previous value.
+    pub fn set_attribute(&mut self, kv: KeyValue) {
+        match self
+            .attributes
+            .iter_mut()
+            .find(|existing| existing.key == kv.key)
+        {
+            Some(existing) => *existing = kv,
+            None => self.attributes.push(kv),
+        }
+    }
+
+    /// Remove the attribute with the given key, if any, from the
+    /// `log_attributes` as well.
+    pub fn remove_attribute(&mut self, key: &str) {
+        self.attributes.retain(|kv| kv.key.as_str() != key);
+        #[cfg(feature = "logs")]
+        self.log_attributes.retain(|(k, _)| k.as_str() != key);
+    }
+}
+
+/// A step of the emission pipeline, which may inspect and modify each
+/// [`ExceptionSnapshot`] or abort its emission by returning [`ControlFlow::Break`].
+///
+/// Installed layers run in installation order, and a layer aborting emission
+/// prevents the later ones from running.
+///
+/// Closures taking a `&mut ExceptionSnapshot` are layers too.
+pub trait EmitLayer: Send + Sync + 'static {
+    fn on_emit(&self, snapshot: &mut ExceptionSnapshot<'_>) -> ControlFlow<()>;
+
+    /// Name identifying the layer, defaulting to its type name.
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(type_name::<Self>())
+    }
+}
+
+impl<F> EmitLayer for F
+where
+    F: Fn(&mut ExceptionSnapshot<'_>) -> ControlFlow<()> + Send + Sync + 'static,
+{
+    fn on_emit(&self, snapshot: &mut ExceptionSnapshot<'_>) -> ControlFlow<()> {
+        self(snapshot)
+    }
+}
+
+/// Append `layer` to the process-wide emission pipeline.
+pub fn install_layer(layer: impl EmitLayer) {
+    LAYERS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .push(Arc::new(layer));
+}
+
+/// Remove all layers from the process-wide emission pipeline.
+pub fn clear_layers() {
+    let layers = std::mem::take(&mut *LAYERS.write().unwrap_or_else(|poison| poison.into_inner()));
+    // The layers are dropped without the lock, as dropping one can emit, e.g.
+    // the last window of an `ErrorAggregator`.
+    drop(layers);
+}
+
+/// Record metrics on each installed layer with `meter`, so that operators can
+/// tell which policy is responsible for missing or altered telemetry.
+///
+/// ## Instruments
+/// - `rootcause.emit_layer.dropped` counts the snapshots for which the layer aborted emission.
+/// - `rootcause.emit_layer.mutated` counts the snapshots whose attributes or timestamp the layer changed.
+/// - `rootcause.emit_layer.duration` is a histogram of the time spent in the layer, in seconds.
+///
+/// Each is recorded with the [name](EmitLayer::name) of the layer as the
+/// `rootcause.emit_layer.name` attribute.
+#[cfg(feature = "metrics")]
+pub fn enable_layer_metrics(meter: &opentelemetry::metrics::Meter) {
+    let metrics = LayerMetrics {
+        dropped: meter
+            .u64_counter(EMIT_LAYER_DROPPED)
+            .with_description("Exception emissions aborted by the layer")
+            .build(),
+        mutated: meter
+            .u64_counter(EMIT_LAYER_MUTATED)
+            .with_description("Exception emissions modified by the layer")
+            .build(),
+        duration: meter
+            .f64_histogram(EMIT_LAYER_DURATION)
+            .with_description("Time spent in the layer per exception emission")
+            .with_unit("s")
+            .build(),
+    };
+    *LAYER_METRICS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = Some(metrics);
+}
+
+/// Stop recording the metrics enabled by [`enable_layer_metrics`].
+#[cfg(feature = "metrics")]
+pub fn disable_layer_metrics() {
+    *LAYER_METRICS
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = None;
+}
+
+#[cfg(feature = "metrics")]
+#[derive(Clone)]
+struct LayerMetrics {
+    dropped: opentelemetry::metrics::Counter<u64>,
+    mutated: opentelemetry::metrics::Counter<u64>,
+    duration: opentelemetry::metrics::Histogram<f64>,
+}
+
+#[cfg(feature = "metrics")]
+impl LayerMetrics {
+    fn on_emit(
+        &self,
+        layer: &dyn EmitLayer,
+        snapshot: &mut ExceptionSnapshot<'_>,
+    ) -> ControlFlow<()> {
+        let before = (
+            snapshot.timestamp,
+            snapshot.attributes.clone(),
+            snapshot.description.clone(),
+            snapshot.report_tree.clone(),
+        );
+        #[cfg(feature = "logs")]
+        let log_before = (snapshot.log_attributes.clone(), snapshot.body.clone());
+        let start = std::time::Instant::now();
+        let flow = layer.on_emit(snapshot);
+        let elapsed = start.elapsed();
+
+        let mutated = before.0 != snapshot.timestamp
+            || before.1 != snapshot.attributes
+            || before.2 != snapshot.description
+            || before.3 != snapshot.report_tree;
+        #[cfg(feature = "logs")]
+        let mutated =
+            mutated || log_before.0 != snapshot.log_attributes || log_before.1 != snapshot.body;
+        let attributes = [KeyValue::new(EMIT_LAYER_NAME, layer.name())];
+        self.duration.record(elapsed.as_secs_f64(), &attributes);
+        if flow.is_break() {
+            self.dropped.add(1, &attributes);
+        } else if mutated {
+            self.mutated.add(1, &attributes);
+        }
+        flow
+    }
+}
+
+/// Run `snapshot` through the installed layers, returning it unless emission
+/// was aborted, with its attributes capped as in [`ExceptionEventSpec::max_attributes`].
+///
+/// With [`ExceptionEventSpec::forward_trace_ids`], the traces other than the
+/// one of `target`, the span the exception is recorded in association with,
+/// are listed before the layers run.
+///
+/// With [`ExceptionEventSpec::report_cbor`], the [`ReportTree`](crate::cbor::ReportTree)
+/// of full attributes is derived before the layers run, and encoded after them.
+///
+/// Span events of the [`Legacy`](SemconvProfile::Legacy) profile are marked
+/// with `exception.escaped` before the layers run, unless the report already
+/// carries the attribute.
+///
+/// Panics of the layers are not recorded by the [panic hook](crate::panic::install_panic_hook),
+/// which would otherwise re-enter emission.
+pub(crate) fn process<'a>(
+    mut snapshot: ExceptionSnapshot<'a>,
+    spec: &ExceptionEventSpec,
+    target: &SpanContext,
+) -> Option<ExceptionSnapshot<'a>> {
+    if spec.forward_trace_ids
+        && snapshot.destination.is_exception()
+        && let Some(kv) = forward_trace_ids(snapshot.report, target)
+    {
+        snapshot.attributes.push(kv);
+    }
+    if spec.semconv_profile == SemconvProfile::Legacy
+        && snapshot.destination == Destination::SpanEvent
+        && snapshot.attribute(EXCEPTION_ESCAPED).is_none()
+    {
+        snapshot
+            .attributes
+            .push(KeyValue::new(EXCEPTION_ESCAPED, true));
+    }
+    // Only the full attributes, which carry the stacktrace, carry the tree.
+    if spec.report_cbor
+        && snapshot
+            .attribute(attribute::EXCEPTION_STACKTRACE)
+            .is_some()
+    {
+        snapshot.report_tree = Some(crate::cbor::ReportTree::new(snapshot.report));
+    }
+    crate::panic::without_panic_recording(|| run_layers(snapshot, spec.max_attributes))
+}
+
+fn run_layers(
+    mut snapshot: ExceptionSnapshot<'_>,
+    max_attributes: Option<usize>,
+) -> Option<ExceptionSnapshot<'_>> {
+    // Layers are cloned out so that they can themselves emit or install layers.
+    let layers = LAYERS
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    #[cfg(feature = "metrics")]
+    let metrics = LAYER_METRICS
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    for layer in layers {
+        #[cfg(feature = "metrics")]
+        let flow = match &metrics {
+            Some(metrics) => metrics.on_emit(&*layer, &mut snapshot),
+            None => layer.on_emit(&mut snapshot),
+        };
+        #[cfg(not(feature = "metrics"))]
+        let flow = layer.on_emit(&mut snapshot);
+        if flow.is_break() {
+            if snapshot.claimed {
+                crate::emission_guard::release(&snapshot);
+            }
+            #[cfg(feature = "metrics")]
+            if snapshot.destination.is_exception() {
+                crate::meter::record_dropped(crate::meter::DropReason::Layer);
+            }
+            return None;
+        }
+    }
+    if let Some(tree) = snapshot.report_tree.take() {
+        let kv = KeyValue::new(crate::cbor::EXCEPTION_REPORT_CBOR, tree.encode_base64());
+        match snapshot
+            .attributes
+            .iter()
+            .position(|existing| existing.key.as_str() == attribute::EXCEPTION_STACKTRACE)
+        {
+            Some(index) => snapshot.attributes.insert(index + 1, kv),
+            None => snapshot.attributes.push(kv),
+        }
+    }
+    cap_attributes(&mut snapshot, max_attributes);
+    #[cfg(feature = "metrics")]
+    if snapshot.destination.is_exception() {
+        crate::meter::record_shape(snapshot.report);
+        crate::meter::record_emitted(snapshot.destination);
+        crate::outstanding::settle(snapshot.report);
+    }
+    Some(snapshot)
+}
+
+/// Replace the trailing attributes of `snapshot` beyond `limit` by an
+/// `exception.dropped_attribute_count` attribute, log attributes first, never
+/// dropping the exception type, message and stacktrace.
+fn cap_attributes(snapshot: &mut ExceptionSnapshot<'_>, limit: Option<usize>) {
+    const KEPT: [&str; 5] = [
+        attribute::EXCEPTION_TYPE,
+        attribute::EXCEPTION_MESSAGE,
+        attribute::EXCEPTION_STACKTRACE,
+        attribute::ERROR_TYPE,
+        ERROR_MESSAGE,
+    ];
+
+    let Some(limit) = limit else {
+        return;
+    };
+    #[cfg(feature = "logs")]
+    let mut len = snapshot.attributes.len() + snapshot.log_attributes.len();
+    #[cfg(not(feature = "logs"))]
+    let mut len = snapshot.attributes.len();
+    if len <= limit {
+        return;
+    }
+
+    let target = limit.saturating_sub(1);
+    let mut dropped = 0;
+    #[cfg(feature = "logs")]
+    while len > target && snapshot.log_attributes.pop().is_some() {
+        len -= 1;
+        dropped += 1;
+    }
+    let mut index = snapshot.attributes.len();
+    while len > target && index > 0 {
+        index -= 1;
+        if !KEPT.contains(&snapshot.attributes[index].key.as_str()) {
+            snapshot.attributes.remove(index);
+            len -= 1;
+            dropped += 1;
+        }
+    }
+    if dropped > 0 {
+        snapshot.attributes.push(KeyValue::new(
+            EXCEPTION_DROPPED_ATTRIBUTE_COUNT,
+            dropped as i64,
+        ));
+        #[cfg(feature = "metrics")]
+        crate::meter::record_attributes_truncated(dropped as u64);
+    }
+}
@@ -0,0 +1,145 @@
+//! Process memory and CPU statistics captured at report creation, read from `/proc`.
+//!
+//! Only Linux provides the statistics: on other targets the module compiles,
+//! but the [`ProcessCollector`] attaches nothing.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use opentelemetry::KeyValue;
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::attachments::Hidden;
+
+pub const PROCESS_MEMORY_USAGE: &str = "process.memory.usage";
+pub const PROCESS_CPU_UTILIZATION: &str = "process.cpu.utilization";
+
+/// Resource usage of the process at the time a report was created.
+///
+/// Emitted as the `process.memory.usage` and `process.cpu.utilization`
+/// attributes on exception events and log records.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessSnapshot {
+    /// Resident set size in bytes.
+    pub memory_usage: u64,
+    /// CPU time spent by the process since the previous snapshot, divided by
+    /// the elapsed wall-clock time and the number of CPUs, in the range `0.0..=1.0`.
+    pub cpu_utilization: Option<f64>,
+}
+
+impl ProcessSnapshot {
+    pub(crate) fn attributes(&self) -> Vec<KeyValue> {
+        let mut attributes = vec![KeyValue::new(
+            PROCESS_MEMORY_USAGE,
+            self.memory_usage as i64,
+        )];
+        if let Some(utilization) = self.cpu_utilization {
+            attributes.push(KeyValue::new(PROCESS_CPU_UTILIZATION, utilization));
+        }
+        attributes
+    }
+}
+
+/// Report creation hook attaching a [`ProcessSnapshot`] to new reports,
+/// for triaging failures adjacent to memory pressure or CPU starvation.
+///
+/// CPU utilization is measured between consecutive reports, starting from
+/// when the collector was created.
+#[derive(Debug)]
+pub struct ProcessCollector {
+    previous: Mutex<Option<(Instant, Duration)>>,
+}
+
+impl Default for ProcessCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessCollector {
+    pub fn new() -> Self {
+        Self {
+            previous: Mutex::new(cpu_time().map(|cpu| (Instant::now(), cpu))),
+        }
+    }
+
+    fn snapshot(&self) -> Option<ProcessSnapshot> {
+        let memory_usage = resident_set_size()?;
+
+        let now = Instant::now();
+        let cpu = cpu_time();
+        let mut previous = self
+            .previous
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        let cpu_utilization = match (*previous, cpu) {
+            (Some((then, cpu_then)), Some(cpu_now)) if now > then => {
+                let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+                let busy = cpu_now.saturating_sub(cpu_then).as_secs_f64();
+                Some(busy / (now - then).as_secs_f64() / cpus as f64)
+            }
+            _ => None,
+        };
+        if let Some(cpu) = cpu {
+            *previous = Some((now, cpu));
+        }
+
+        Some(ProcessSnapshot {
+            memory_usage,
+            cpu_utilization,
+        })
+    }
+}
+
+impl ReportCreationHook for ProcessCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        if let Some(snapshot) = self.snapshot() {
+            let _ = report.attach_custom::<Hidden, _>(snapshot);
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        if let Some(snapshot) = self.snapshot() {
+            let _ = report.attach_custom::<Hidden, _>(snapshot);
+        }
+    }
+}
+
+/// `VmRSS` from `/proc/self/status`, in bytes.
+fn resident_set_size() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// `utime + stime` from `/proc/self/stat`.
+fn cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name may contain spaces, so fields are counted from its closing paren,
+    // after which `utime` and `stime` are the 12th and 13th fields.
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    let ticks = clock_ticks()?;
+    Some(Duration::from_secs_f64((utime + stime) as f64 / ticks))
+}
+
+/// Clock ticks per second used by `/proc/<pid>/stat`.
+#[cfg(unix)]
+fn clock_ticks() -> Option<f64> {
+    // SAFETY: `sysconf` has no preconditions.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    (ticks > 0).then_some(ticks as f64)
+}
+
+#[cfg(not(unix))]
+fn clock_ticks() -> Option<f64> {
+    None
+}
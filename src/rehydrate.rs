@@ -0,0 +1,263 @@
+//! Re-creation of [`Report`]s from exception data captured elsewhere, e.g. by a
+//! collector webhook, for local analysis and re-formatting.
+
+use core::fmt;
+use std::fmt::Debug;
+
+use opentelemetry::{KeyValue, Value, trace::Event};
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    Report,
+    handlers::{
+        AttachmentFormattingPlacement, AttachmentFormattingStyle, AttachmentHandler,
+        FormattingFunction,
+    },
+};
+
+use crate::{
+    cbor::{EXCEPTION_REPORT_CBOR, ReportTree},
+    utilities::{ERROR_MESSAGE, EXCEPTION, EXCEPTION_DROPPED_ATTRIBUTE_COUNT, EXCEPTION_ESCAPED},
+};
+
+/// Context of a report re-created from an `exception` event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExceptionEvent {
+    /// The `exception.type` attribute, if present.
+    pub type_name: Option<String>,
+    /// The `exception.message` attribute, if present.
+    pub message: Option<String>,
+}
+
+impl fmt::Display for ExceptionEvent {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.type_name, &self.message) {
+            (Some(type_name), Some(message)) => write!(formatter, "{type_name}: {message}"),
+            (Some(text), None) | (None, Some(text)) => formatter.write_str(text),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ExceptionEvent {}
+
+/// The `exception.stacktrace` attribute of a re-created report, i.e. the
+/// formatted report tree as it was at the origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteStacktrace(pub String);
+
+/// Create a [`Report`] from the name and attributes of an `exception` event,
+/// as recorded by [`RecordErrorReport::as_event`](crate::span_event::RecordErrorReport::as_event)
+/// or emitted as a log record.
+///
+/// ## Attributes & Details
+/// - `exception.type` and `exception.message` become the [`ExceptionEvent`] context.
+/// - `exception.stacktrace` becomes a [`RemoteStacktrace`] attachment.
+/// - Any other attribute becomes a [`KeyValue`] attachment, so it is emitted again
+///   as an attribute if the report is, see [`ExceptionEventSpec::attachment_attributes`](crate::spec::ExceptionEventSpec::attachment_attributes).
+///
+/// Returns `None` unless the event is named `exception` and has at least one of
+/// `exception.type` and `exception.message`, as the semantic conventions require.
+#[track_caller]
+pub fn report_from_exception_event(
+    name: &str,
+    attributes: impl IntoIterator<Item = KeyValue>,
+) -> Option<Report<ExceptionEvent>> {
+    if name != EXCEPTION {
+        return None;
+    }
+
+    let mut context = ExceptionEvent {
+        type_name: None,
+        message: None,
+    };
+    let mut stacktrace = None;
+    let mut others = Vec::new();
+    for kv in attributes {
+        match kv.key.as_str() {
+            attribute::EXCEPTION_TYPE => context.type_name = Some(kv.value.as_str().into_owned()),
+            attribute::EXCEPTION_MESSAGE => context.message = Some(kv.value.as_str().into_owned()),
+            attribute::EXCEPTION_STACKTRACE => {
+                stacktrace = Some(RemoteStacktrace(kv.value.as_str().into_owned()))
+            }
+            _ => others.push(kv),
+        }
+    }
+    if context.type_name.is_none() && context.message.is_none() {
+        return None;
+    }
+
+    let mut report = Report::new(context);
+    if let Some(stacktrace) = stacktrace {
+        report = report.attach_custom::<Rehydrated, _>(stacktrace);
+    }
+    for kv in others {
+        report = report.attach_custom::<Rehydrated, _>(kv);
+    }
+    Some(report)
+}
+
+/// [`AttachmentHandler`] for the attachments of re-created reports.
+#[derive(Debug, Clone, Copy)]
+pub struct Rehydrated;
+
+impl AttachmentHandler<KeyValue> for Rehydrated {
+    fn display(value: &KeyValue, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{} = {}", value.key, value.value)
+    }
+
+    fn debug(value: &KeyValue, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(value, formatter)
+    }
+}
+
+impl AttachmentHandler<RemoteStacktrace> for Rehydrated {
+    fn display(value: &RemoteStacktrace, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&value.0)
+    }
+
+    fn debug(value: &RemoteStacktrace, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(value, formatter)
+    }
+
+    fn preferred_formatting_style(
+        _value: &RemoteStacktrace,
+        function: FormattingFunction,
+    ) -> AttachmentFormattingStyle {
+        AttachmentFormattingStyle {
+            placement: AttachmentFormattingPlacement::InlineWithHeader {
+                header: "REMOTE STACKTRACE",
+            },
+            function,
+            priority: 0,
+        }
+    }
+}
+
+/// Structured view of the attributes of an `exception` event or log record,
+/// for inspecting emitted exceptions without searching the attributes by key.
+///
+/// ```
+/// use opentelemetry::KeyValue;
+/// use rootcause_opentelemetry::rehydrate::ExceptionEventData;
+///
+/// let data = ExceptionEventData::from_attributes([
+///     KeyValue::new("exception.type", "std::io::Error"),
+///     KeyValue::new("exception.message", "connection refused"),
+///     KeyValue::new("exception.dropped_attribute_count", 2),
+///     KeyValue::new("http.route", "/orders"),
+/// ]);
+/// assert_eq!(data.exception_type(), Some("std::io::Error"));
+/// assert_eq!(data.dropped_attribute_count(), Some(2));
+/// assert_eq!(data.attribute("http.route").map(|v| v.as_str()), Some("/orders".into()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExceptionEventData {
+    exception_type: Option<String>,
+    message: Option<String>,
+    stacktrace: Option<String>,
+    error_type: Option<String>,
+    extras: Vec<KeyValue>,
+    attributes: Vec<KeyValue>,
+}
+
+impl ExceptionEventData {
+    /// Sort `attributes` into the fields.
+    ///
+    /// `error.message`, emitted instead of `exception.message` with
+    /// [`SemconvProfile::ErrorAttributes`](crate::spec::SemconvProfile::ErrorAttributes),
+    /// is taken as the message as well.
+    pub fn from_attributes(attributes: impl IntoIterator<Item = KeyValue>) -> Self {
+        let mut data = Self::default();
+        for kv in attributes {
+            let text = || Some(kv.value.as_str().into_owned());
+            match kv.key.as_str() {
+                attribute::EXCEPTION_TYPE => data.exception_type = text(),
+                attribute::EXCEPTION_MESSAGE | ERROR_MESSAGE => data.message = text(),
+                attribute::EXCEPTION_STACKTRACE => data.stacktrace = text(),
+                attribute::ERROR_TYPE => data.error_type = text(),
+                key if key.starts_with("exception.") => data.extras.push(kv),
+                _ => data.attributes.push(kv),
+            }
+        }
+        data
+    }
+
+    /// The data of `event`, or `None` unless it is named `exception`.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        (event.name == EXCEPTION).then(|| Self::from_attributes(event.attributes.iter().cloned()))
+    }
+
+    /// The `exception.type` attribute.
+    pub fn exception_type(&self) -> Option<&str> {
+        self.exception_type.as_deref()
+    }
+
+    /// The `exception.message` or `error.message` attribute.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The `exception.stacktrace` attribute, i.e. the formatted report tree.
+    pub fn stacktrace(&self) -> Option<&str> {
+        self.stacktrace.as_deref()
+    }
+
+    /// The `error.type` attribute.
+    pub fn error_type(&self) -> Option<&str> {
+        self.error_type.as_deref()
+    }
+
+    /// The other `exception.*` attributes, such as `exception.escaped` or
+    /// `exception.dropped_attribute_count`.
+    pub fn extras(&self) -> &[KeyValue] {
+        &self.extras
+    }
+
+    /// The value of the `exception.*` attribute `key` among the [extras](Self::extras).
+    pub fn extra(&self, key: &str) -> Option<&Value> {
+        find(&self.extras, key)
+    }
+
+    /// The custom attributes, i.e. those outside of the `exception.*`
+    /// namespace and other than `error.type` and `error.message`.
+    pub fn attributes(&self) -> &[KeyValue] {
+        &self.attributes
+    }
+
+    /// The value of the custom attribute `key`.
+    pub fn attribute(&self, key: &str) -> Option<&Value> {
+        find(&self.attributes, key)
+    }
+
+    /// The `exception.escaped` attribute.
+    pub fn escaped(&self) -> Option<bool> {
+        match self.extra(EXCEPTION_ESCAPED)? {
+            Value::Bool(escaped) => Some(*escaped),
+            _ => None,
+        }
+    }
+
+    /// The `exception.dropped_attribute_count` attribute.
+    pub fn dropped_attribute_count(&self) -> Option<i64> {
+        match self.extra(EXCEPTION_DROPPED_ATTRIBUTE_COUNT)? {
+            Value::I64(count) => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// The report tree of the `exception.report.cbor` attribute, if present
+    /// and well-formed.
+    pub fn report_tree(&self) -> Option<ReportTree> {
+        match self.extra(EXCEPTION_REPORT_CBOR)? {
+            Value::String(encoded) => ReportTree::decode_base64(encoded.as_str()),
+            _ => None,
+        }
+    }
+}
+
+fn find<'a>(attributes: &'a [KeyValue], key: &str) -> Option<&'a Value> {
+    attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| &kv.value)
+}
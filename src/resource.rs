@@ -0,0 +1,77 @@
+//! Identity of the emitting service captured at report creation.
+
+use std::sync::Arc;
+
+use opentelemetry::{Key, KeyValue};
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::attachments::Hidden;
+
+/// Resource attribute keys captured by default by the [`ResourceCollector`].
+pub const DEFAULT_RESOURCE_KEYS: &[&str] = &[
+    "service.name",
+    "service.version",
+    "deployment.environment",
+    "deployment.environment.name",
+];
+
+/// Selected resource attributes of the service a report was created in.
+///
+/// Emitted as attributes on exception events and log records, without
+/// overriding attributes with the same key, so that reports serialized or
+/// logged outside the SDK pipeline still carry their origin's identity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceSnapshot(pub Arc<[KeyValue]>);
+
+/// Report creation hook attaching a [`ResourceSnapshot`] to new reports.
+///
+/// The resource is given by the application, since this crate does not depend
+/// on the SDK, e.g. from an `opentelemetry_sdk::Resource` as
+///
+/// ```ignore
+/// ResourceCollector::new(resource.iter().map(|(k, v)| KeyValue::new(k.clone(), v.clone())))
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResourceCollector {
+    snapshot: ResourceSnapshot,
+}
+
+impl ResourceCollector {
+    /// Capture the attributes among `resource` with one of the [`DEFAULT_RESOURCE_KEYS`].
+    pub fn new(resource: impl IntoIterator<Item = KeyValue>) -> Self {
+        Self::with_keys(resource, DEFAULT_RESOURCE_KEYS.iter().copied())
+    }
+
+    /// Capture the attributes among `resource` with one of the given keys.
+    pub fn with_keys<K: Into<Key>>(
+        resource: impl IntoIterator<Item = KeyValue>,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Self {
+        let keys: Vec<Key> = keys.into_iter().map(Into::into).collect();
+        let snapshot = resource
+            .into_iter()
+            .filter(|kv| keys.contains(&kv.key))
+            .collect();
+        Self {
+            snapshot: ResourceSnapshot(snapshot),
+        }
+    }
+}
+
+impl ReportCreationHook for ResourceCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        if !self.snapshot.0.is_empty() {
+            let _ = report.attach_custom::<Hidden, _>(self.snapshot.clone());
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        if !self.snapshot.0.is_empty() {
+            let _ = report.attach_custom::<Hidden, _>(self.snapshot.clone());
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Routing of reports to loggers by their context type, so that e.g. database
+//! errors land under a `db` scope and HTTP errors under an `http` one.
+//!
+//! Routes are used by [`LoggerExt::emit_error_report_routed`].
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use opentelemetry::logs::Logger;
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+use crate::log_event::LoggerExt;
+
+type EmitFn = Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) + Send + Sync>;
+type ClassifierFn = Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> bool + Send + Sync>;
+
+#[derive(Default)]
+struct Routes {
+    by_type: HashMap<TypeId, EmitFn>,
+    classified: Vec<(ClassifierFn, EmitFn)>,
+}
+
+static ROUTES: LazyLock<RwLock<Routes>> = LazyLock::new(Default::default);
+
+fn emitter<L: Logger + Send + Sync + 'static>(logger: L) -> EmitFn {
+    Arc::new(move |rep| logger.emit_error_report(&rep))
+}
+
+/// Route reports whose current context is a `C` to `logger`, replacing any
+/// previous route for `C`.
+///
+/// Loggers with a scope per subsystem are obtained with
+/// [`scoped_logger`](crate::log_event::scoped_logger).
+pub fn route_context<C: 'static, L: Logger + Send + Sync + 'static>(logger: L) {
+    ROUTES
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .by_type
+        .insert(TypeId::of::<C>(), emitter(logger));
+}
+
+/// Route reports for which `classifier` returns `true` to `logger`.
+///
+/// Classifiers are consulted in registration order, and only for reports whose
+/// context type has no route registered with [`route_context`].
+pub fn route_with<L: Logger + Send + Sync + 'static>(
+    classifier: impl Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> bool + Send + Sync + 'static,
+    logger: L,
+) {
+    ROUTES
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .classified
+        .push((Arc::new(classifier), emitter(logger)));
+}
+
+/// Remove all routes.
+pub fn clear_routes() {
+    *ROUTES.write().unwrap_or_else(|poison| poison.into_inner()) = Routes::default();
+}
+
+/// The emitter of the logger `rep` is routed to, if any.
+pub(crate) fn route(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<EmitFn> {
+    // The classifiers are cloned out so that they can themselves emit or route.
+    let classified = {
+        let routes = ROUTES.read().unwrap_or_else(|poison| poison.into_inner());
+        if let Some(emit) = routes.by_type.get(&rep.current_context_type_id()) {
+            return Some(emit.clone());
+        }
+        routes.classified.clone()
+    };
+    classified
+        .into_iter()
+        .find(|(classifier, _)| classifier(rep))
+        .map(|(_, emit)| emit)
+}
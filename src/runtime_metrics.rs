@@ -0,0 +1,76 @@
+//! Tokio runtime statistics captured at report creation.
+
+use opentelemetry::KeyValue;
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+use tokio::runtime::Handle;
+
+use crate::attachments::Hidden;
+
+pub const RUNTIME_WORKERS: &str = "runtime.workers";
+pub const RUNTIME_ALIVE_TASKS: &str = "runtime.alive_tasks";
+pub const RUNTIME_GLOBAL_QUEUE_DEPTH: &str = "runtime.global_queue_depth";
+
+/// Statistics of the tokio runtime a report was created on.
+///
+/// Emitted as the `runtime.workers`, `runtime.alive_tasks` and
+/// `runtime.global_queue_depth` attributes on exception events and log records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeSnapshot {
+    /// Number of worker threads of the runtime.
+    pub workers: usize,
+    /// Number of tasks currently alive on the runtime.
+    pub alive_tasks: usize,
+    /// Number of tasks in the runtime's global queue, waiting for a worker.
+    pub global_queue_depth: usize,
+}
+
+impl RuntimeSnapshot {
+    /// Capture the statistics of the current runtime, if called from within one.
+    pub fn capture() -> Option<Self> {
+        let metrics = Handle::try_current().ok()?.metrics();
+        Some(Self {
+            workers: metrics.num_workers(),
+            alive_tasks: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+        })
+    }
+
+    pub(crate) fn attributes(&self) -> [KeyValue; 3] {
+        [
+            KeyValue::new(RUNTIME_WORKERS, self.workers as i64),
+            KeyValue::new(RUNTIME_ALIVE_TASKS, self.alive_tasks as i64),
+            KeyValue::new(RUNTIME_GLOBAL_QUEUE_DEPTH, self.global_queue_depth as i64),
+        ]
+    }
+}
+
+/// Report creation hook attaching a [`RuntimeSnapshot`] to reports created
+/// within a tokio runtime, for diagnosing saturation-related failures.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioRuntimeCollector {
+    _priv: (),
+}
+
+impl TokioRuntimeCollector {
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl ReportCreationHook for TokioRuntimeCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        if let Some(snapshot) = RuntimeSnapshot::capture() {
+            let _ = report.attach_custom::<Hidden, _>(snapshot);
+        }
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        if let Some(snapshot) = RuntimeSnapshot::capture() {
+            let _ = report.attach_custom::<Hidden, _>(snapshot);
+        }
+    }
+}
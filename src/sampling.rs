@@ -0,0 +1,77 @@
+//! Content-based decisions on whether and how reports are emitted, e.g. to drop
+//! `NotFound` errors of health checks.
+
+use std::sync::{Arc, RwLock};
+
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+
+static SAMPLER: RwLock<Option<Arc<dyn ReportSampler>>> = RwLock::new(None);
+
+/// How a report is emitted, as decided by a [`ReportSampler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+    /// Emit the report as requested.
+    Full,
+    /// Emit the report without the `exception.stacktrace` attribute and log record body.
+    Brief,
+    /// Emit no span events, span attributes or log records for the report.
+    /// Metric measurements recorded for it are unaffected.
+    MetricsOnly,
+    /// Emit nothing for the report, and leave it out of the
+    /// [`exceptions` counter](crate::meter::MeterExt::count_error_report).
+    Drop,
+}
+
+impl Decision {
+    /// Whether span events, span attributes and log records are emitted.
+    pub fn emits_telemetry(self) -> bool {
+        matches!(self, Self::Full | Self::Brief)
+    }
+}
+
+/// Decision on how each report is emitted, consulted by the span and log
+/// emission paths before any attributes are derived from the report.
+///
+/// The sampler is consulted once per [`RecordErrorReport`](crate::span_event::RecordErrorReport)
+/// or [`LogRecordReportBuilder`](crate::log_event::LogRecordReportBuilder), so
+/// that all steps of one builder agree even for a random sampler.
+///
+/// Closures taking a [`ReportRef`] and returning a [`Decision`] are samplers too.
+pub trait ReportSampler: Send + Sync + 'static {
+    fn should_emit(&self, rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Decision;
+}
+
+impl<F> ReportSampler for F
+where
+    F: Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Decision + Send + Sync + 'static,
+{
+    fn should_emit(&self, rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Decision {
+        self(rep)
+    }
+}
+
+/// Make `sampler` the process-wide sampler, replacing any previous one.
+pub fn install_sampler(sampler: impl ReportSampler) {
+    *SAMPLER.write().unwrap_or_else(|poison| poison.into_inner()) = Some(Arc::new(sampler));
+}
+
+/// Remove the process-wide sampler, so that all reports are emitted in full.
+pub fn clear_sampler() {
+    *SAMPLER.write().unwrap_or_else(|poison| poison.into_inner()) = None;
+}
+
+/// The decision of the installed sampler for `rep`, or [`Decision::Full`] if none is installed.
+pub(crate) fn decide(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Decision {
+    // The sampler is cloned out so that it can itself emit or install a sampler.
+    let sampler = SAMPLER
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    match sampler {
+        Some(sampler) => crate::panic::without_panic_recording(|| sampler.should_emit(rep)),
+        None => Decision::Full,
+    }
+}
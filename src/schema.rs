@@ -0,0 +1,139 @@
+//! Machine-readable listing of the attributes this crate can emit, for
+//! tooling which provisions backend indexes or dashboards ahead of time.
+
+use opentelemetry_semantic_conventions::attribute;
+
+use crate::utilities::{
+    ERROR_MESSAGE, EXCEPTION_DROPPED_ATTRIBUTE_COUNT, EXCEPTION_DROPPED_LINK_COUNT,
+    EXCEPTION_ESCAPED, EXCEPTION_MESSAGE_OVERFLOW, EXCEPTION_REPORT_AGE_MS, FORWARD_TO_TRACE_IDS,
+    PROCESS_ENVIRONMENT_VARIABLE,
+};
+
+/// Where an attribute can appear.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmissionPath {
+    /// An `exception` event on a span.
+    SpanEvent,
+    /// Attributes on the span itself.
+    SpanAttributes,
+    /// A link from a span to the span a child report originated in.
+    SpanLink,
+    /// A log record.
+    LogRecord,
+    /// A metric measurement.
+    Metric,
+}
+
+/// The type of the value of an attribute.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeType {
+    String,
+    Int,
+    Double,
+    Bool,
+    StringArray,
+}
+
+/// The key of an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeKey {
+    /// The attribute has exactly this key.
+    Exact(&'static str),
+    /// The attribute key is this prefix followed by `.` and a user-chosen suffix.
+    Prefix(&'static str),
+}
+
+/// An attribute this crate can emit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttributeSchema {
+    pub key: AttributeKey,
+    pub ty: AttributeType,
+    pub paths: &'static [EmissionPath],
+}
+
+macro_rules! schema {
+    ($($(#[$cfg:meta])* $kind:ident($key:expr): $ty:ident => [$($path:ident),*];)*) => {
+        /// All attributes this crate can emit with the enabled features.
+        ///
+        /// [`KeyValue`](opentelemetry::KeyValue)-typed attachments, baggage entries and
+        /// resource attributes, whose keys are chosen by the application, are not included.
+        pub fn schema() -> Vec<AttributeSchema> {
+            let mut schema = Vec::new();
+            $(
+                $(#[$cfg])*
+                schema.push(AttributeSchema {
+                    key: AttributeKey::$kind($key),
+                    ty: AttributeType::$ty,
+                    paths: &[$(EmissionPath::$path),*],
+                });
+            )*
+            schema
+        }
+    };
+}
+
+schema! {
+    Exact(attribute::EXCEPTION_TYPE): String => [SpanEvent, SpanAttributes, SpanLink, LogRecord];
+    Exact(attribute::EXCEPTION_MESSAGE): String => [SpanEvent, SpanAttributes, SpanLink, LogRecord];
+    Exact(EXCEPTION_MESSAGE_OVERFLOW): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::EXCEPTION_STACKTRACE): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(crate::cbor::EXCEPTION_REPORT_CBOR): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::ERROR_TYPE): String => [SpanEvent, SpanAttributes, SpanLink, LogRecord];
+    Exact(attribute::CODE_FILE_PATH): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::CODE_LINE_NUMBER): Int => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::CODE_COLUMN_NUMBER): Int => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::CODE_FUNCTION_NAME): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(ERROR_MESSAGE): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(EXCEPTION_ESCAPED): Bool => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::DB_SYSTEM_NAME): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::DB_RESPONSE_STATUS_CODE): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(crate::error_type::IO_ERROR_KIND): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(crate::identity::EXCEPTION_ID): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(crate::identity::EXCEPTION_FINGERPRINT): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(EXCEPTION_REPORT_AGE_MS): Int => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(EXCEPTION_DROPPED_LINK_COUNT): Int => [SpanAttributes];
+    Exact(EXCEPTION_DROPPED_ATTRIBUTE_COUNT): Int => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(crate::emission_guard::EXCEPTION_DUPLICATE): Bool => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(FORWARD_TO_TRACE_IDS): StringArray => [SpanEvent, SpanAttributes, LogRecord];
+    Prefix(PROCESS_ENVIRONMENT_VARIABLE): String => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "http")]
+    Exact(attribute::HTTP_RESPONSE_STATUS_CODE): Int => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "grpc")]
+    Exact(crate::grpc::RPC_SYSTEM): String => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "grpc")]
+    Exact(crate::grpc::RPC_GRPC_STATUS_CODE): Int => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "grpc")]
+    Prefix(crate::grpc::RPC_GRPC_RESPONSE_METADATA): StringArray => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "regex")]
+    Exact(crate::classification::ERROR_CATEGORY): String => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::THREAD_ID): Int => [SpanEvent, SpanAttributes, LogRecord];
+    Exact(attribute::THREAD_NAME): String => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "tokio-task")]
+    Exact(crate::thread::TOKIO_TASK_ID): String => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "tokio-metrics")]
+    Exact(crate::runtime_metrics::RUNTIME_WORKERS): Int => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "tokio-metrics")]
+    Exact(crate::runtime_metrics::RUNTIME_ALIVE_TASKS): Int => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "tokio-metrics")]
+    Exact(crate::runtime_metrics::RUNTIME_GLOBAL_QUEUE_DEPTH): Int => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "process-metrics")]
+    Exact(crate::process_metrics::PROCESS_MEMORY_USAGE): Int => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "process-metrics")]
+    Exact(crate::process_metrics::PROCESS_CPU_UTILIZATION): Double => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "sdk")]
+    Exact(crate::sdk_error::OTEL_SDK_OPERATION): String => [SpanEvent, SpanAttributes, LogRecord];
+    #[cfg(feature = "logs")]
+    Exact(crate::aggregation::EXCEPTION_SUMMARY_COUNT): Int => [LogRecord];
+    #[cfg(feature = "logs")]
+    Exact(crate::aggregation::EXCEPTION_SUMMARY_WINDOW): Double => [LogRecord];
+    #[cfg(feature = "metrics")]
+    Exact(attribute::ERROR_TYPE): String => [Metric];
+    #[cfg(feature = "metrics")]
+    Exact(crate::pipeline::EMIT_LAYER_NAME): String => [Metric];
+    #[cfg(feature = "metrics")]
+    Exact(crate::meter::BRIDGE_DESTINATION): String => [Metric];
+    #[cfg(feature = "metrics")]
+    Exact(crate::meter::BRIDGE_DROP_REASON): String => [Metric];
+}
@@ -0,0 +1,134 @@
+//! Reports of OpenTelemetry SDK failures, such as failed flushes or exporter
+//! setup, for handling them along with the rest of an application's errors.
+//!
+//! The SDK error types are foreign to both this crate and rootcause, so they
+//! are converted into an [`SdkError`] context naming the failed operation by
+//! [`SdkResultExt::sdk_context`], rather than being turned into strings.
+//!
+//! ```
+//! # use opentelemetry_sdk::trace::SdkTracerProvider;
+//! # use rootcause::prelude::*;
+//! use rootcause_opentelemetry::sdk_error::{SdkError, SdkResultExt};
+//!
+//! fn flush(provider: &SdkTracerProvider) -> Result<(), Report<SdkError>> {
+//!     provider.force_flush().sdk_context("flush the tracer provider")
+//! }
+//! ```
+
+use std::{borrow::Cow, error::Error, fmt, time::Duration};
+
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::error::OTelSdkError;
+use rootcause::Report;
+
+use crate::error_type::{OtelErrorType, register_error_type};
+
+pub const OTEL_SDK_OPERATION: &str = "otel.sdk.operation";
+
+/// Context of a report of a failed SDK operation.
+#[derive(Debug)]
+pub struct SdkError {
+    /// What was attempted, such as `flush the tracer provider`.
+    pub operation: Cow<'static, str>,
+    pub kind: SdkErrorKind,
+}
+
+/// Why an SDK operation failed.
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum SdkErrorKind {
+    /// The provider or exporter had already been shut down.
+    AlreadyShutdown,
+    /// The operation did not finish within the given time.
+    Timeout(Duration),
+    /// Any other failure, as described by the SDK.
+    InternalFailure(String),
+    /// An OTLP exporter could not be built.
+    #[cfg(feature = "otlp")]
+    ExporterBuild(opentelemetry_otlp::ExporterBuildError),
+}
+
+impl From<OTelSdkError> for SdkErrorKind {
+    fn from(error: OTelSdkError) -> Self {
+        match error {
+            OTelSdkError::AlreadyShutdown => Self::AlreadyShutdown,
+            OTelSdkError::Timeout(timeout) => Self::Timeout(timeout),
+            OTelSdkError::InternalFailure(message) => Self::InternalFailure(message),
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+impl From<opentelemetry_otlp::ExporterBuildError> for SdkErrorKind {
+    fn from(error: opentelemetry_otlp::ExporterBuildError) -> Self {
+        Self::ExporterBuild(error)
+    }
+}
+
+impl fmt::Display for SdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to {}: ", self.operation)?;
+        match &self.kind {
+            SdkErrorKind::AlreadyShutdown => f.write_str("already shut down"),
+            SdkErrorKind::Timeout(timeout) => write!(f, "timed out after {timeout:?}"),
+            SdkErrorKind::InternalFailure(message) => f.write_str(message),
+            #[cfg(feature = "otlp")]
+            SdkErrorKind::ExporterBuild(error) => fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+impl Error for SdkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            #[cfg(feature = "otlp")]
+            SdkErrorKind::ExporterBuild(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// `error.type` is `otel.sdk.` followed by the kind, such as `otel.sdk.timeout`,
+/// with the operation as the `otel.sdk.operation` attribute.
+impl OtelErrorType for SdkError {
+    fn otel_error_type(&self) -> Cow<'static, str> {
+        Cow::Borrowed(match self.kind {
+            SdkErrorKind::AlreadyShutdown => "otel.sdk.already_shutdown",
+            SdkErrorKind::Timeout(_) => "otel.sdk.timeout",
+            SdkErrorKind::InternalFailure(_) => "otel.sdk.internal_failure",
+            #[cfg(feature = "otlp")]
+            SdkErrorKind::ExporterBuild(_) => "otel.sdk.exporter_build",
+        })
+    }
+
+    fn otel_attributes(&self) -> Vec<KeyValue> {
+        vec![KeyValue::new(OTEL_SDK_OPERATION, self.operation.clone())]
+    }
+}
+
+/// Use the [`OtelErrorType`] implementation of [`SdkError`], for emitting
+/// reports of SDK failures through a still working signal.
+pub fn register_sdk_error() {
+    register_error_type::<SdkError>();
+}
+
+/// Extension trait for results of SDK operations.
+pub trait SdkResultExt<T> {
+    /// Convert the error into a report of an [`SdkError`] for `operation`,
+    /// phrased to follow "Failed to", such as `flush the tracer provider`.
+    fn sdk_context(self, operation: impl Into<Cow<'static, str>>) -> Result<T, Report<SdkError>>;
+}
+
+impl<T, E: Into<SdkErrorKind>> SdkResultExt<T> for Result<T, E> {
+    #[track_caller]
+    fn sdk_context(self, operation: impl Into<Cow<'static, str>>) -> Result<T, Report<SdkError>> {
+        // Not `map_err`, as the location of the report would be the closure's.
+        match self {
+            Ok(value) => Ok(value),
+            Err(error) => Err(Report::new(SdkError {
+                operation: operation.into(),
+                kind: error.into(),
+            })),
+        }
+    }
+}
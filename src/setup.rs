@@ -0,0 +1,607 @@
+//! Provider setup for applications, configuring the SDK tracer, logger and
+//! meter providers with a shared [`Resource`] and installing them globally.
+//!
+//! Libraries should not use this, see [`library`](crate::library).
+//!
+//! ```no_run
+//! # use rootcause::prelude::*;
+//! use rootcause_opentelemetry::setup::TelemetrySetup;
+//!
+//! fn main() -> Result<(), Report> {
+//!     let telemetry = TelemetrySetup::new("checkout")
+//!         .with_span_exporter(opentelemetry_stdout::SpanExporter::default())
+//!         .install()?;
+//!     // ...
+//!     telemetry.shutdown()
+//! }
+//! ```
+
+use std::env;
+
+use opentelemetry::global;
+use opentelemetry_sdk::{
+    Resource,
+    error::OTelSdkResult,
+    trace::{Sampler, SdkTracerProvider, SpanExporter, TracerProviderBuilder},
+};
+use rootcause::{Report, markers::SendSync, prelude::*};
+
+use crate::sdk_error::SdkResultExt;
+
+#[cfg(feature = "otlp")]
+pub use otlp::OtlpProtocol;
+
+/// Name of the logger installed as the [fallback logger](crate::log_event::install_fallback_logger).
+#[cfg(feature = "logs")]
+pub const FALLBACK_LOGGER_NAME: &str = "rootcause-opentelemetry";
+
+/// Builder for the SDK providers, see the [module documentation](self).
+///
+/// Providers without exporters are still installed, so that spans get valid
+/// contexts for correlation even when only logs are exported.
+#[must_use]
+pub struct TelemetrySetup {
+    resource: Resource,
+    tracer: TracerProviderBuilder,
+    #[cfg(feature = "logs")]
+    logger: opentelemetry_sdk::logs::LoggerProviderBuilder,
+    #[cfg(feature = "metrics")]
+    meter: opentelemetry_sdk::metrics::MeterProviderBuilder,
+    #[cfg(feature = "otlp")]
+    otlp: Option<otlp::OtlpConfig>,
+    sdk_disabled: bool,
+}
+
+impl TelemetrySetup {
+    /// Set up providers for the service named `service_name`.
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self::with_base_resource(
+            Resource::builder()
+                .with_service_name(service_name.into())
+                .build(),
+        )
+    }
+
+    /// Set up providers as configured by the standard `OTEL_*` environment
+    /// variables, for deployments configured the usual OpenTelemetry way.
+    ///
+    /// ## Variables
+    /// - `OTEL_SERVICE_NAME` and `OTEL_RESOURCE_ATTRIBUTES` make up the resource.
+    /// - `OTEL_TRACES_SAMPLER` and `OTEL_TRACES_SAMPLER_ARG` choose the sampler.
+    /// - With the `otlp` feature, any of `OTEL_EXPORTER_OTLP_ENDPOINT`,
+    ///   `OTEL_EXPORTER_OTLP_PROTOCOL` or an `OTEL_*_EXPORTER` set to `otlp`
+    ///   enables OTLP export. The exporters read the endpoints, headers and
+    ///   timeouts themselves, so the signal-specific variables apply as well.
+    /// - `OTEL_TRACES_EXPORTER`, `OTEL_LOGS_EXPORTER` and `OTEL_METRICS_EXPORTER`
+    ///   set to `none` disable OTLP export of the signal.
+    /// - `OTEL_SDK_DISABLED` set to `true` disables the SDK: [`Self::install`]
+    ///   then leaves the [`global`] providers at their no-op defaults and
+    ///   installs no fallback logger, and the providers of the guard have no
+    ///   exporters, whatever the builder methods configured.
+    ///
+    /// Otherwise, the builder methods override what the variables configured.
+    pub fn from_env() -> Self {
+        // The SDK's default resource detectors read the resource variables.
+        let mut setup = Self::with_base_resource(Resource::builder().build());
+        setup.sdk_disabled = env_is("OTEL_SDK_DISABLED", "true");
+        if let Some(sampler) = sampler_from_env() {
+            setup.tracer = setup.tracer.with_sampler(sampler);
+        }
+        #[cfg(feature = "otlp")]
+        {
+            setup.otlp = otlp::OtlpConfig::from_env();
+        }
+        setup
+    }
+
+    fn with_base_resource(resource: Resource) -> Self {
+        Self {
+            resource,
+            tracer: SdkTracerProvider::builder(),
+            #[cfg(feature = "logs")]
+            logger: opentelemetry_sdk::logs::SdkLoggerProvider::builder(),
+            #[cfg(feature = "metrics")]
+            meter: opentelemetry_sdk::metrics::SdkMeterProvider::builder(),
+            #[cfg(feature = "otlp")]
+            otlp: None,
+            sdk_disabled: false,
+        }
+    }
+
+    /// Use `resource` for all providers, replacing the one with just the
+    /// service name.
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        self.resource = resource;
+        self
+    }
+
+    /// Export spans to `exporter` in batches.
+    pub fn with_span_exporter<E: SpanExporter + 'static>(mut self, exporter: E) -> Self {
+        self.tracer = self.tracer.with_batch_exporter(exporter);
+        self
+    }
+
+    /// Export each span to `exporter` as it ends, e.g. for development.
+    pub fn with_simple_span_exporter<E: SpanExporter + 'static>(mut self, exporter: E) -> Self {
+        self.tracer = self.tracer.with_simple_exporter(exporter);
+        self
+    }
+
+    /// Export log records to `exporter` in batches.
+    #[cfg(feature = "logs")]
+    pub fn with_log_exporter<E: opentelemetry_sdk::logs::LogExporter + 'static>(
+        mut self,
+        exporter: E,
+    ) -> Self {
+        self.logger = self.logger.with_batch_exporter(exporter);
+        self
+    }
+
+    /// Export each log record to `exporter` as it is emitted, e.g. for development.
+    #[cfg(feature = "logs")]
+    pub fn with_simple_log_exporter<E: opentelemetry_sdk::logs::LogExporter + 'static>(
+        mut self,
+        exporter: E,
+    ) -> Self {
+        self.logger = self.logger.with_simple_exporter(exporter);
+        self
+    }
+
+    /// Export metrics to `exporter` periodically.
+    #[cfg(feature = "metrics")]
+    pub fn with_metric_exporter<E>(mut self, exporter: E) -> Self
+    where
+        E: opentelemetry_sdk::metrics::exporter::PushMetricExporter,
+    {
+        let reader = opentelemetry_sdk::metrics::PeriodicReader::builder(exporter).build();
+        self.meter = self.meter.with_reader(reader);
+        self
+    }
+
+    /// Export all signals over OTLP to the collector at `endpoint`, e.g.
+    /// `http://localhost:4317`.
+    ///
+    /// `https://` endpoints require the `otlp-tls` feature, which verifies the
+    /// server against the platform's root certificates.
+    ///
+    /// For the HTTP protocols, the signal paths such as `/v1/traces` are
+    /// appended. Without an endpoint, the exporters default to the
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT` variable or the local collector.
+    #[cfg(feature = "otlp")]
+    pub fn with_otlp_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.otlp.get_or_insert_default().endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Export all signals over OTLP with `protocol`, defaulting to
+    /// [`OtlpProtocol::Grpc`].
+    #[cfg(feature = "otlp")]
+    pub fn with_otlp_protocol(mut self, protocol: OtlpProtocol) -> Self {
+        self.otlp.get_or_insert_default().protocol = protocol;
+        self
+    }
+
+    /// Send the header `key: value` with every OTLP export, e.g. for
+    /// authenticating with a vendor's endpoint.
+    ///
+    /// Vendor endpoints are usually `https://` URLs, which require the
+    /// `otlp-tls` feature.
+    #[cfg(feature = "otlp")]
+    pub fn with_otlp_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.otlp
+            .get_or_insert_default()
+            .headers
+            .push((key.into(), value.into()));
+        self
+    }
+
+    /// Build the providers and install them globally.
+    ///
+    /// The tracer and meter providers become the [`global`] ones, and a logger
+    /// of the logger provider the [fallback logger](crate::log_event::install_fallback_logger).
+    ///
+    /// Fails if an exporter cannot be built, e.g. for an invalid OTLP endpoint
+    /// or header. Exporting over gRPC requires a tokio runtime.
+    ///
+    /// Installs nothing if the SDK is disabled, see [`Self::from_env`].
+    pub fn install(mut self) -> Result<TelemetryGuard, Report> {
+        let enabled = !self.sdk_disabled;
+        if !enabled {
+            self = Self::with_base_resource(self.resource);
+        }
+
+        #[cfg(feature = "otlp")]
+        if let Some(config) = self.otlp.take() {
+            self = config.add_exporters(self)?;
+        }
+
+        let tracer_provider = self.tracer.with_resource(self.resource.clone()).build();
+        if enabled {
+            global::set_tracer_provider(tracer_provider.clone());
+        }
+
+        #[cfg(feature = "logs")]
+        let logger_provider = {
+            use opentelemetry::logs::LoggerProvider;
+
+            let provider = self.logger.with_resource(self.resource.clone()).build();
+            if enabled {
+                crate::log_event::install_fallback_logger(provider.logger(FALLBACK_LOGGER_NAME));
+            }
+            provider
+        };
+
+        #[cfg(feature = "metrics")]
+        let meter_provider = {
+            let provider = self.meter.with_resource(self.resource).build();
+            if enabled {
+                global::set_meter_provider(provider.clone());
+            }
+            provider
+        };
+
+        Ok(TelemetryGuard {
+            tracer_provider,
+            #[cfg(feature = "logs")]
+            logger_provider,
+            #[cfg(feature = "metrics")]
+            meter_provider,
+            shut_down: false,
+        })
+    }
+}
+
+/// The sampler named by `OTEL_TRACES_SAMPLER`, with the ratio of
+/// `OTEL_TRACES_SAMPLER_ARG` defaulting to `1.0`.
+fn sampler_from_env() -> Option<Sampler> {
+    let ratio = || {
+        env::var("OTEL_TRACES_SAMPLER_ARG")
+            .ok()
+            .and_then(|arg| arg.trim().parse().ok())
+            .unwrap_or(1.0)
+    };
+    let sampler = match env::var("OTEL_TRACES_SAMPLER").ok()?.trim() {
+        "always_on" => Sampler::AlwaysOn,
+        "always_off" => Sampler::AlwaysOff,
+        "traceidratio" => Sampler::TraceIdRatioBased(ratio()),
+        "parentbased_always_on" => Sampler::ParentBased(Box::new(Sampler::AlwaysOn)),
+        "parentbased_always_off" => Sampler::ParentBased(Box::new(Sampler::AlwaysOff)),
+        "parentbased_traceidratio" => {
+            Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio())))
+        }
+        _ => return None,
+    };
+    Some(sampler)
+}
+
+/// Whether the environment variable `name` is set to `value`, ignoring case.
+fn env_is(name: &str, value: &str) -> bool {
+    env::var(name).is_ok_and(|var| var.trim().eq_ignore_ascii_case(value))
+}
+
+/// The providers installed by [`TelemetrySetup::install`], which are flushed
+/// and shut down by [`Self::shutdown`] or when the guard is dropped.
+///
+/// Failures on drop are printed to stderr, so call [`Self::shutdown`] to
+/// handle them.
+#[must_use = "dropping the guard shuts the providers down"]
+pub struct TelemetryGuard {
+    tracer_provider: SdkTracerProvider,
+    #[cfg(feature = "logs")]
+    logger_provider: opentelemetry_sdk::logs::SdkLoggerProvider,
+    #[cfg(feature = "metrics")]
+    meter_provider: opentelemetry_sdk::metrics::SdkMeterProvider,
+    shut_down: bool,
+}
+
+impl TelemetryGuard {
+    pub fn tracer_provider(&self) -> &SdkTracerProvider {
+        &self.tracer_provider
+    }
+
+    #[cfg(feature = "logs")]
+    pub fn logger_provider(&self) -> &opentelemetry_sdk::logs::SdkLoggerProvider {
+        &self.logger_provider
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn meter_provider(&self) -> &opentelemetry_sdk::metrics::SdkMeterProvider {
+        &self.meter_provider
+    }
+
+    /// Export everything buffered by the providers.
+    pub fn force_flush(&self) -> Result<(), Report> {
+        self.providers()
+            .into_iter()
+            .map(|provider| {
+                provider
+                    .force_flush()
+                    .sdk_context(format!("flush the {} provider", provider.kind()))
+            })
+            .collect_reports_vec::<SendSync>()
+            .context("Telemetry flush failed")
+            .map(drop)
+            .map_err(Report::into_dynamic)
+    }
+
+    /// Flush and shut down the providers, see [`shutdown_all`].
+    pub fn shutdown(mut self) -> Result<(), Report> {
+        self.shut_down = true;
+        shutdown_all(&self.providers())
+    }
+
+    fn providers(&self) -> Vec<&dyn SdkProvider> {
+        vec![
+            &self.tracer_provider,
+            #[cfg(feature = "logs")]
+            &self.logger_provider,
+            #[cfg(feature = "metrics")]
+            &self.meter_provider,
+        ]
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if !self.shut_down
+            && let Err(rep) = shutdown_all(&self.providers())
+        {
+            eprintln!("{rep}");
+        }
+    }
+}
+
+/// The SDK tracer, logger and meter providers, for [`shutdown_all`].
+pub trait SdkProvider {
+    /// The signal of the provider, such as `tracer`, for error messages.
+    fn kind(&self) -> &'static str;
+
+    fn force_flush(&self) -> OTelSdkResult;
+
+    fn shutdown(&self) -> OTelSdkResult;
+}
+
+impl SdkProvider for SdkTracerProvider {
+    fn kind(&self) -> &'static str {
+        "tracer"
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        SdkTracerProvider::force_flush(self)
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        SdkTracerProvider::shutdown(self)
+    }
+}
+
+#[cfg(feature = "logs")]
+impl SdkProvider for opentelemetry_sdk::logs::SdkLoggerProvider {
+    fn kind(&self) -> &'static str {
+        "logger"
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        opentelemetry_sdk::logs::SdkLoggerProvider::force_flush(self)
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        opentelemetry_sdk::logs::SdkLoggerProvider::shutdown(self)
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl SdkProvider for opentelemetry_sdk::metrics::SdkMeterProvider {
+    fn kind(&self) -> &'static str {
+        "meter"
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        opentelemetry_sdk::metrics::SdkMeterProvider::force_flush(self)
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        opentelemetry_sdk::metrics::SdkMeterProvider::shutdown(self)
+    }
+}
+
+/// Flush and shut down each of `providers`, even when others fail, returning
+/// a single report with a child for every flush or shutdown which failed.
+///
+/// ```no_run
+/// # use opentelemetry_sdk::{logs::SdkLoggerProvider, trace::SdkTracerProvider};
+/// # use rootcause::prelude::*;
+/// # use rootcause_opentelemetry::setup::shutdown_all;
+/// # fn f(tracer_provider: SdkTracerProvider, logger_provider: SdkLoggerProvider) -> Result<(), Report> {
+/// shutdown_all(&[&tracer_provider, &logger_provider])
+/// # }
+/// ```
+pub fn shutdown_all(providers: &[&dyn SdkProvider]) -> Result<(), Report> {
+    providers
+        .iter()
+        .flat_map(|provider| {
+            [
+                provider
+                    .force_flush()
+                    .sdk_context(format!("flush the {} provider", provider.kind())),
+                provider
+                    .shutdown()
+                    .sdk_context(format!("shut down the {} provider", provider.kind())),
+            ]
+        })
+        .collect_reports_vec::<SendSync>()
+        .context("Telemetry shutdown failed")
+        .map(drop)
+        .map_err(Report::into_dynamic)
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use std::env;
+
+    use opentelemetry_otlp::{
+        Protocol, SpanExporter, WithExportConfig, WithHttpConfig, WithTonicConfig,
+    };
+    use rootcause::{Report, prelude::*};
+    use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+
+    use super::{TelemetrySetup, env_is};
+    use crate::sdk_error::SdkResultExt;
+
+    /// Transport of the OTLP exporters, see [`TelemetrySetup::with_otlp_protocol`].
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum OtlpProtocol {
+        /// OTLP/gRPC, usually on port 4317.
+        #[default]
+        Grpc,
+        /// OTLP/HTTP with binary protobuf payloads, usually on port 4318.
+        HttpProtobuf,
+        /// OTLP/HTTP with JSON payloads, usually on port 4318.
+        HttpJson,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub(super) struct OtlpConfig {
+        pub(super) endpoint: Option<String>,
+        pub(super) protocol: OtlpProtocol,
+        pub(super) headers: Vec<(String, String)>,
+        skip_traces: bool,
+        #[cfg(feature = "logs")]
+        skip_logs: bool,
+        #[cfg(feature = "metrics")]
+        skip_metrics: bool,
+    }
+
+    /// Build an OTLP exporter of type `$exporter` for `$config`, with
+    /// `$path` appended to the endpoint over HTTP.
+    macro_rules! exporter {
+        ($config:expr, $exporter:ty, $path:literal) => {{
+            let config: &OtlpConfig = $config;
+            match config.protocol {
+                OtlpProtocol::Grpc => {
+                    let mut builder = <$exporter>::builder()
+                        .with_tonic()
+                        .with_metadata(config.metadata()?);
+                    if let Some(endpoint) = &config.endpoint {
+                        builder = builder.with_endpoint(endpoint.clone());
+                    }
+                    #[cfg(feature = "otlp-tls")]
+                    if config.is_https() {
+                        builder = builder.with_tls_config(
+                            tonic::transport::ClientTlsConfig::new().with_enabled_roots(),
+                        );
+                    }
+                    builder.build()
+                }
+                OtlpProtocol::HttpProtobuf | OtlpProtocol::HttpJson => {
+                    let mut builder = <$exporter>::builder()
+                        .with_http()
+                        .with_protocol(config.http_protocol())
+                        .with_headers(config.headers.iter().cloned().collect());
+                    if let Some(endpoint) = &config.endpoint {
+                        builder = builder.with_endpoint(format!(
+                            "{}{}",
+                            endpoint.trim_end_matches('/'),
+                            $path
+                        ));
+                    }
+                    builder.build()
+                }
+            }
+            .sdk_context(concat!("build the OTLP exporter for ", $path))
+            .map_err(Report::into_dynamic)?
+        }};
+    }
+
+    impl OtlpConfig {
+        /// The configuration of `OTEL_EXPORTER_OTLP_PROTOCOL` and the
+        /// `OTEL_*_EXPORTER`s, if OTLP export is enabled at all.
+        pub(super) fn from_env() -> Option<Self> {
+            let protocol = match env::var("OTEL_EXPORTER_OTLP_PROTOCOL")
+                .as_deref()
+                .map(str::trim)
+            {
+                Ok("grpc") => Some(OtlpProtocol::Grpc),
+                Ok("http/protobuf") => Some(OtlpProtocol::HttpProtobuf),
+                Ok("http/json") => Some(OtlpProtocol::HttpJson),
+                _ => None,
+            };
+            let exporters = [
+                "OTEL_TRACES_EXPORTER",
+                "OTEL_LOGS_EXPORTER",
+                "OTEL_METRICS_EXPORTER",
+            ];
+            let enabled = protocol.is_some()
+                || env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some()
+                || exporters.iter().any(|name| env_is(name, "otlp"));
+            enabled.then(|| Self {
+                protocol: protocol.unwrap_or_default(),
+                skip_traces: env_is("OTEL_TRACES_EXPORTER", "none"),
+                #[cfg(feature = "logs")]
+                skip_logs: env_is("OTEL_LOGS_EXPORTER", "none"),
+                #[cfg(feature = "metrics")]
+                skip_metrics: env_is("OTEL_METRICS_EXPORTER", "none"),
+                ..Self::default()
+            })
+        }
+
+        pub(super) fn add_exporters(
+            &self,
+            setup: TelemetrySetup,
+        ) -> Result<TelemetrySetup, Report> {
+            let mut setup = setup;
+            if !self.skip_traces {
+                setup = setup.with_span_exporter(exporter!(self, SpanExporter, "/v1/traces"));
+            }
+            #[cfg(feature = "logs")]
+            if !self.skip_logs {
+                setup = setup.with_log_exporter(exporter!(
+                    self,
+                    opentelemetry_otlp::LogExporter,
+                    "/v1/logs"
+                ));
+            }
+            #[cfg(feature = "metrics")]
+            if !self.skip_metrics {
+                setup = setup.with_metric_exporter(exporter!(
+                    self,
+                    opentelemetry_otlp::MetricExporter,
+                    "/v1/metrics"
+                ));
+            }
+            Ok(setup)
+        }
+
+        /// Whether the endpoint, or `OTEL_EXPORTER_OTLP_ENDPOINT` without one,
+        /// is an `https://` URL, for which gRPC needs a TLS configuration.
+        #[cfg(feature = "otlp-tls")]
+        fn is_https(&self) -> bool {
+            self.endpoint
+                .clone()
+                .or_else(|| env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+                .is_some_and(|endpoint| endpoint.trim().starts_with("https://"))
+        }
+
+        fn http_protocol(&self) -> Protocol {
+            match self.protocol {
+                OtlpProtocol::Grpc => Protocol::Grpc,
+                OtlpProtocol::HttpProtobuf => Protocol::HttpBinary,
+                OtlpProtocol::HttpJson => Protocol::HttpJson,
+            }
+        }
+
+        fn metadata(&self) -> Result<MetadataMap, Report> {
+            let mut metadata = MetadataMap::with_capacity(self.headers.len());
+            for (key, value) in &self.headers {
+                let key = MetadataKey::from_bytes(key.as_bytes())
+                    .context(format!("Invalid OTLP header name {key:?}"))
+                    .map_err(Report::into_dynamic)?;
+                let value = MetadataValue::try_from(value.as_str())
+                    .context(format!("Invalid OTLP value of header {key:?}"))
+                    .map_err(Report::into_dynamic)?;
+                metadata.insert(key, value);
+            }
+            Ok(metadata)
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Mapping of report context types to log [`Severity`].
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use opentelemetry::logs::Severity;
+use rootcause::{
+    Report, ReportRef,
+    markers::{Dynamic, Local, Mutable, ObjectMarkerFor, Uncloneable},
+};
+
+use crate::{attachments::Hidden, utilities::AttachmentsExt};
+
+type SeverityFn =
+    Arc<dyn Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Option<Severity> + Send + Sync>;
+
+static REGISTRY: LazyLock<RwLock<HashMap<TypeId, SeverityFn>>> = LazyLock::new(Default::default);
+
+/// Context types with an inherent log severity, e.g. `Warn` for an expected
+/// `RateLimited` failure or `Fatal` for a panic.
+///
+/// Report contexts are type-erased, so implementations only take effect
+/// once registered with [`register_severity`].
+pub trait OtelSeverity: 'static {
+    fn otel_severity(&self) -> Severity;
+}
+
+/// Make [`LoggerExt`](crate::log_event::LoggerExt) use the [`OtelSeverity`]
+/// implementation of `C` for reports whose current context is a `C`.
+pub fn register_severity<C: OtelSeverity>() {
+    register_severity_with::<C>(C::otel_severity);
+}
+
+/// Make [`LoggerExt`](crate::log_event::LoggerExt) use `severity` for reports
+/// whose current context is a `C`, for types which cannot implement [`OtelSeverity`].
+pub fn register_severity_with<C: 'static>(
+    severity: impl Fn(&C) -> Severity + Send + Sync + 'static,
+) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .insert(
+            TypeId::of::<C>(),
+            Arc::new(move |rep| rep.downcast_current_context::<C>().map(&severity)),
+        );
+}
+
+/// Extension trait for [`Report`]s, for tagging them with a log [`Severity`].
+pub trait SeverityReportExt: Sized {
+    /// Attach `severity`, overriding the severity registered for the context type.
+    fn attach_severity(self, severity: Severity) -> Self;
+}
+
+impl<C: ?Sized, T> SeverityReportExt for Report<C, Mutable, T>
+where
+    Severity: ObjectMarkerFor<T>,
+{
+    fn attach_severity(self, severity: Severity) -> Self {
+        self.attach_custom::<Hidden, _>(severity)
+    }
+}
+
+/// Extension trait for [`Result`]s of [`Report`]s, for tagging the error with
+/// a log [`Severity`] where it is created.
+pub trait SeverityResultExt: Sized {
+    /// [Attach](SeverityReportExt::attach_severity) `severity` to the error, if any.
+    ///
+    /// ```
+    /// # use opentelemetry::logs::Severity;
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::severity::SeverityResultExt;
+    /// let result: Result<(), Report> = Err(report!("cache miss")).severity(Severity::Debug);
+    /// ```
+    fn severity(self, severity: Severity) -> Self;
+}
+
+impl<V, C: ?Sized, T> SeverityResultExt for Result<V, Report<C, Mutable, T>>
+where
+    Severity: ObjectMarkerFor<T>,
+{
+    fn severity(self, severity: Severity) -> Self {
+        self.map_err(|report| report.attach_severity(severity))
+    }
+}
+
+/// The severity of a report: a [`Severity`]-typed attachment if present,
+/// then the severity registered for its context type, falling back to `Error`.
+pub(crate) fn severity_of(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Severity {
+    if let Some(severity) = rep.find_attachment_inner::<Severity>() {
+        return *severity;
+    }
+
+    // The function is cloned out so that it can itself emit or register severities.
+    let severity = REGISTRY
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .get(&rep.current_context_type_id())
+        .cloned();
+    severity
+        .and_then(|severity| severity(rep))
+        .unwrap_or(Severity::Error)
+}
@@ -1,17 +1,32 @@
-use std::time::SystemTime;
+use std::{borrow::Cow, cell::OnceCell, time::SystemTime};
 
+#[cfg(feature = "logs")]
+use opentelemetry::logs::Logger;
 use opentelemetry::{
-    KeyValue,
-    trace::{Span, SpanContext, SpanRef, Status, noop::NoopSpan},
+    Context, KeyValue, Value,
+    trace::{
+        Link, Span, SpanContext, SpanKind, SpanRef, Status, TraceContextExt, Tracer, noop::NoopSpan,
+    },
 };
 use opentelemetry_semantic_conventions::attribute;
 use rootcause::{
-    ReportRef,
-    markers::{Dynamic, Local, Uncloneable},
+    Report, ReportRef,
+    markers::{Dynamic, Local, Mutable, ObjectMarkerFor, Uncloneable},
 };
 
-use crate::utilities::{
-    AsReportRef, AttachmentsExt, EXCEPTION, attributes, attributes_brief, timestamp,
+#[cfg(feature = "logs")]
+use crate::log_event::ReportLogExt;
+use crate::{
+    attachments::{DEFAULT_MAX_SPAN_CONTEXTS, ElidedSpanContext, Hidden},
+    classifier, correlation,
+    error_type::{error_type_of, registered_error_type},
+    pipeline::{self, Destination, ExceptionSnapshot},
+    sampling::{self, Decision},
+    spec::{ExceptionEventSpec, MessageLines, SemconvProfile},
+    utilities::{
+        AsReportRef, AttachmentsExt, EXCEPTION, EXCEPTION_DROPPED_LINK_COUNT, format_contained,
+        merge_attributes, sampled_attributes, timestamp, type_and_message,
+    },
 };
 
 /// Extension trait for the [`SpanRef<'_>`] type
@@ -35,12 +50,21 @@ impl<'a> SpanRefReportExt for SpanRef<'a> {
         RecordErrorReport {
             spanish: SpanIsh::SpanRef(self),
             report: rep.as_report_ref(),
+            max_links: DEFAULT_MAX_SPAN_CONTEXTS,
+            spec: ExceptionEventSpec::global(),
+            error_type: ErrorTypeState::default(),
+            decision: OnceCell::new(),
+            #[cfg(feature = "metrics")]
+            counter: None,
         }
     }
 }
 
 /// Extension trait for types implementing [`Span`].
 pub trait SpanReportExt: Span + Sized {
+    /// Returns a builder-pattern for turning reports into events on a span.
+    ///
+    /// See [`RecordErrorReport`]
     #[must_use]
     fn record_error_report<'b>(
         &'b mut self,
@@ -49,11 +73,6 @@ pub trait SpanReportExt: Span + Sized {
 }
 
 impl<S: Span> SpanReportExt for S {
-    /// Returns a builder-pattern for turning reports into events on a span.
-    ///
-    /// ```rust
-    /// todo!()
-    /// ```
     fn record_error_report<'b>(
         &'b mut self,
         rep: &'b impl AsReportRef,
@@ -61,10 +80,74 @@ impl<S: Span> SpanReportExt for S {
         RecordErrorReport {
             spanish: SpanIsh::MutSpan(self),
             report: rep.as_report_ref(),
+            max_links: DEFAULT_MAX_SPAN_CONTEXTS,
+            spec: ExceptionEventSpec::global(),
+            error_type: ErrorTypeState::default(),
+            decision: OnceCell::new(),
+            #[cfg(feature = "metrics")]
+            counter: None,
         }
     }
 }
 
+/// Attachment directing the emission of a report to a span other than the
+/// current one, for code deciding after the fact which span an error belongs to.
+///
+/// See [`RecordErrorReport::as_event_on_target_span`]. Log records take their
+/// trace context from it as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetSpan(pub SpanContext);
+
+/// Extension trait for [`Report`]s, for directing them to a [`TargetSpan`].
+pub trait TargetSpanReportExt: Sized {
+    /// Attach `span_context` as the [`TargetSpan`] of the report.
+    fn attach_target_span(self, span_context: SpanContext) -> Self;
+}
+
+impl<C: ?Sized, T> TargetSpanReportExt for Report<C, Mutable, T>
+where
+    TargetSpan: ObjectMarkerFor<T>,
+{
+    fn attach_target_span(self, span_context: SpanContext) -> Self {
+        self.attach_custom::<Hidden, _>(TargetSpan(span_context))
+    }
+}
+
+/// The span context of the outermost [`TargetSpan`] in the report tree, if any.
+pub(crate) fn target_span<'a>(
+    rep: ReportRef<'a, Dynamic, Uncloneable, Local>,
+) -> Option<&'a SpanContext> {
+    rep.iter_reports()
+        .find_map(|r| r.attachments().find_attachment_inner::<TargetSpan>())
+        .map(|target| &target.0)
+}
+
+/// Which value wins when [`RecordErrorReport::with_error_status`] and
+/// [`RecordErrorReport::on_span_attributes`] would record different error types
+/// on the same span, see [`RecordErrorReport::error_type_precedence`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorTypePrecedence {
+    /// The `exception.type` span attribute, as rewritten by the
+    /// [`EmitLayer`](crate::pipeline::EmitLayer)s, is used for `error.type` as well,
+    /// unless the context type has an [`OtelErrorType`](crate::error_type::OtelErrorType),
+    /// which is then kept as `error.type`.
+    #[default]
+    ExceptionType,
+    /// The `error.type` span attribute, i.e. the [`OtelErrorType`](crate::error_type::OtelErrorType)
+    /// or type name of the current context, is used for `exception.type` as well.
+    ErrorType,
+}
+
+/// What the builder has recorded on the span so far, for reconciling the
+/// error types of the steps which follow.
+#[derive(Debug, Default)]
+struct ErrorTypeState {
+    precedence: ErrorTypePrecedence,
+    error_status: bool,
+    exception_type: Option<Value>,
+}
+
 /// Builder for configuring how [`Report`](rootcause::Report)s are recorded on a span.
 ///
 /// It contains either a [`SpanRef`] or some
@@ -73,16 +156,127 @@ impl<S: Span> SpanReportExt for S {
 pub struct RecordErrorReport<'a, S: Span> {
     spanish: SpanIsh<'a, S>,
     report: ReportRef<'a, Dynamic, Uncloneable, Local>,
+    max_links: usize,
+    spec: ExceptionEventSpec,
+    error_type: ErrorTypeState,
+    decision: OnceCell<Decision>,
+    #[cfg(feature = "metrics")]
+    counter: Option<crate::meter::ExceptionCounter>,
 }
 
 impl<'a, S: Span> RecordErrorReport<'a, S> {
+    /// Use the given [`ExceptionEventSpec`] instead of the
+    /// [global](ExceptionEventSpec::install) one for the following steps.
+    pub fn with_spec(mut self, spec: ExceptionEventSpec) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    /// Capture the allowlisted environment variables on the following steps,
+    /// as in [`ExceptionEventSpec::with_env_snapshot`].
+    pub fn with_env_snapshot<K: Into<Cow<'static, str>>>(
+        mut self,
+        allowlist: impl IntoIterator<Item = K>,
+    ) -> Self {
+        self.spec = self.spec.with_env_snapshot(allowlist);
+        self
+    }
+
+    /// Add the entries of the current baggage as attributes on the following steps,
+    /// as in [`ExceptionEventSpec::baggage_attributes`].
+    pub fn with_baggage(mut self) -> Self {
+        self.spec = self.spec.baggage_attributes(true);
+        self
+    }
+
+    /// Emit attribute keys and shapes according to `profile` on the following steps,
+    /// as in [`ExceptionEventSpec::semconv_profile`].
+    pub fn semconv_profile(mut self, profile: SemconvProfile) -> Self {
+        self.spec = self.spec.semconv_profile(profile);
+        self
+    }
+
+    /// Emit multi-line messages according to `policy` on the following steps,
+    /// as in [`ExceptionEventSpec::message_lines`].
+    pub fn message_lines(mut self, policy: MessageLines) -> Self {
+        self.spec = self.spec.message_lines(policy);
+        self
+    }
+
+    /// Cap the number of span links added by [`Self::link_child_report_spans`]
+    /// and [`Self::link_child_report_spans_brief`].
+    ///
+    /// Links beyond the cap, and reports whose [`SpanContext`] was elided by the
+    /// [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector),
+    /// are counted in the `exception.dropped_link_count` span attribute instead.
+    ///
+    /// Defaults to [`DEFAULT_MAX_SPAN_CONTEXTS`].
+    pub fn max_links(mut self, max: usize) -> Self {
+        self.max_links = max;
+        self
+    }
+
+    /// Which of `error.type` and `exception.type` is the single source of truth
+    /// for the error type, when both [`Self::with_error_status`] and
+    /// [`Self::on_span_attributes`] (or [`Self::as_span_attributes_brief`]) are used.
+    ///
+    /// The other attribute is rewritten to match before either is set on the
+    /// span, regardless of the order of the steps. Attachments with either key
+    /// are overridden as well.
+    ///
+    /// Defaults to [`ErrorTypePrecedence::ExceptionType`].
+    pub fn error_type_precedence(mut self, precedence: ErrorTypePrecedence) -> Self {
+        self.error_type.precedence = precedence;
+        self
+    }
+
+    /// Whether recording on the span has any effect, i.e. whether it is recording.
+    ///
+    /// When it is not, e.g. for a [`NoopSpan`] or a span in an unsampled trace,
+    /// the steps recording on the span return immediately without formatting
+    /// the report or walking its attachments. Callers can check this to skip
+    /// preparing the report as well.
+    pub fn is_effective(&self) -> bool {
+        self.spanish.is_recording()
+    }
+
+    /// Whether the steps emitting an event or attributes should proceed, i.e.
+    /// [`Self::is_effective`], counting the emission as dropped by the
+    /// [bridge metrics](crate::meter::enable_bridge_metrics) when it is not.
+    fn is_emitting(&self) -> bool {
+        let effective = self.is_effective();
+        #[cfg(feature = "metrics")]
+        if !effective {
+            crate::meter::record_dropped(crate::meter::DropReason::NotRecording);
+        }
+        effective
+    }
+
+    /// The decision of the [`ReportSampler`](crate::sampling::ReportSampler)
+    /// on the report, made once per builder so that all steps agree.
+    fn decision(&self) -> Decision {
+        *self.decision.get_or_init(|| sampling::decide(self.report))
+    }
+
+    /// Whether the [decision](Self::decision) allows emitting telemetry other
+    /// than metrics, counting the emission as dropped when it does not.
+    fn sampler_allows(&self) -> bool {
+        let allowed = self.decision().emits_telemetry();
+        #[cfg(feature = "metrics")]
+        if !allowed {
+            crate::meter::record_dropped(crate::meter::DropReason::Sampled);
+        }
+        allowed
+    }
+
     /// Record the [`Report`](rootcause::Report) as an `exception` event on the span.
     ///
     /// ## Attributes & Details
-    /// - The timestamp of the event is given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or defaults to [`now()`](std::time::SystemTime::now) if not found.
+    /// - The timestamp of the event is given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or defaults to [the current time](crate::clock::now) if not found.
     /// - `exception.type` is [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
     /// - `exception.message` is [`.format_current_context().to_string()`](rootcause::Report::format_current_context).
     /// - `exception.stacktrace` is just `.to_string()` of the [`Report`](rootcause::Report) itself
+    /// - [`KeyValue`](opentelemetry::KeyValue)-typed attachments are added as attributes, see [`ExceptionEventSpec::attachment_attributes`](crate::spec::ExceptionEventSpec::attachment_attributes).
     ///
     /// [`SystemTime`](std::time::SystemTime) attachments are
     /// provided report creation hook [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector).
@@ -90,22 +284,132 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     /// ## Spec   
     /// [Semantic conventions for exceptions on spans](https://opentelemetry.io/docs/specs/semconv/exceptions/exceptions-spans/)
     pub fn as_event(mut self) -> Self {
-        self.spanish.add_event_with_timestamp(
-            EXCEPTION,
-            timestamp(self.report),
-            attributes(self.report),
-        );
+        if !self.is_emitting() {
+            return self;
+        }
+        if let Some(attributes) = sampled_attributes(self.report, &self.spec, self.decision(), true)
+        {
+            self.add_event(attributes);
+        }
+        self
+    }
+
+    /// Also increment the [`exceptions` counter](crate::meter::MeterExt::count_error_report)
+    /// of a meter on [`Self::send`], with the same `error.type` as [`Self::with_error_status`].
+    ///
+    /// Takes a [`Meter`](opentelemetry::metrics::Meter), or an [`ExceptionCounter`](crate::meter::ExceptionCounter)
+    /// obtained once from it so that the instrument is not built for every report.
+    ///
+    /// The report is counted even when the span is not recording, but not when
+    /// [sampling](crate::sampling) drops it.
+    ///
+    /// ```
+    /// # use opentelemetry::{Context, global, trace::TraceContextExt};
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::span_event::SpanRefReportExt;
+    /// let rep = report!("payment declined");
+    /// Context::current()
+    ///     .span()
+    ///     .record_error_report(&rep)
+    ///     .as_event()
+    ///     .count_metric(&global::meter("checkout"))
+    ///     .send();
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn count_metric(mut self, counter: impl Into<crate::meter::ExceptionCounter>) -> Self {
+        self.counter = Some(counter.into());
+        self
+    }
+
+    /// Finish recording the report, incrementing the counter given to
+    /// [`Self::count_metric`], if any.
+    ///
+    /// The other steps take effect as they are called, so this is only needed
+    /// for counting the report.
+    pub fn send(self) {
+        #[cfg(feature = "metrics")]
+        if let Some(counter) = &self.counter
+            && self.decision() != Decision::Drop
+        {
+            counter.record(&self.report, &[]);
+        }
+    }
+
+    /// Emit the [`Report`] as an `exception` event through the Logs bridge
+    /// instead of on the span, for backends standardizing on the Events signal.
+    ///
+    /// The event is a log record named `exception` with the attributes of
+    /// [`Self::as_event`], a [structured body](crate::log_event::LoggerExt::emit_error_report_structured)
+    /// and the trace context of this span, as emitted by [`LoggerExt`](crate::log_event::LoggerExt).
+    #[cfg(feature = "logs")]
+    pub fn as_log_event<L: Logger>(self, logger: &L) -> Self {
+        self.report
+            .otel_log(logger)
+            .with_spec(self.spec.clone())
+            .span_context(self.spanish.span_context().clone())
+            .with_decision(self.decision())
+            .structured_body()
+            .emit();
         self
     }
 
     /// Record the [`Report`] as an `exception` event on the span, as in [`Self::as_event`],
     /// but omit the optional `exception.stacktrace` attribute for brevity.
     pub fn as_event_brief(mut self) -> Self {
-        self.spanish.add_event_with_timestamp(
-            EXCEPTION,
-            timestamp(self.report),
-            attributes_brief(self.report),
-        );
+        if !self.is_emitting() {
+            return self;
+        }
+        if let Some(attributes) =
+            sampled_attributes(self.report, &self.spec, self.decision(), false)
+        {
+            self.add_event(attributes);
+        }
+        self
+    }
+
+    /// Record an informational event named `name` on the span, such as
+    /// `cache.fallback` or `circuit_breaker.open`, derived from a non-fatal report.
+    ///
+    /// The event carries the attributes of [`Self::as_event`] for which `select`
+    /// returns `true`, after they have passed through the
+    /// [`EmitLayer`](crate::pipeline::EmitLayer)s.
+    ///
+    /// ```
+    /// # use opentelemetry::{Context, trace::TraceContextExt};
+    /// # use rootcause::prelude::*;
+    /// # use rootcause_opentelemetry::span_event::SpanRefReportExt;
+    /// let rep = report!("primary cache unavailable");
+    /// let _ = Context::current()
+    ///     .span()
+    ///     .record_error_report(&rep)
+    ///     .as_named_event("cache.fallback", |kv| kv.key.as_str() == "exception.message");
+    /// ```
+    pub fn as_named_event(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        select: impl Fn(&KeyValue) -> bool,
+    ) -> Self {
+        if !self.is_emitting() {
+            return self;
+        }
+        let Some(attributes) = sampled_attributes(self.report, &self.spec, self.decision(), true)
+        else {
+            return self;
+        };
+        if let Some(mut snapshot) = pipeline::process(
+            ExceptionSnapshot::new(
+                self.report,
+                Destination::SpanEvent,
+                timestamp(self.report),
+                attributes,
+            ),
+            &self.spec,
+            self.spanish.span_context(),
+        ) {
+            snapshot.attributes.retain(&select);
+            self.spanish
+                .add_event_with_timestamp(name, snapshot.timestamp, snapshot.attributes);
+        }
         self
     }
 
@@ -113,33 +417,85 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     ///
     /// ## Attributes & Details
     /// - `description` of the status itself is [`.format_current_context().to_string()`](rootcause::Report::format_current_context)
-    /// - `error.type` attribute is [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
+    /// - The status is replaced by the one [classified](crate::classifier::ErrorClassifier::span_status) for the context type, if any.
+    /// - With the `http` feature, it is left unset for reports whose [HTTP status code](crate::http)
+    ///   does not indicate an error, and likewise for [gRPC status codes](crate::grpc) with the `grpc` feature.
+    /// - `error.type` attribute is the [`OtelErrorType`](crate::error_type::OtelErrorType) of the context,
+    ///   or [`.current_context_type_name()`](rootcause::Report::current_context_type_name),
+    ///   unless reconciled with `exception.type` as in [`Self::error_type_precedence`].
+    /// - The description and `error.type` pass through the [`EmitLayer`](crate::pipeline::EmitLayer)s
+    ///   as a [`Destination::SpanStatus`], and the status is left unset if a layer aborts.
     ///
     /// ## Spec
     /// [Recording errors > Recording errors on spans](https://opentelemetry.io/docs/specs/semconv/general/recording-errors/#recording-errors-on-spans)
     pub fn with_error_status(mut self) -> Self {
-        self.spanish.set_attributes([KeyValue::new(
-            attribute::ERROR_TYPE,
-            self.report.current_context_type_name(),
-        )]);
-        self.spanish.set_status(Status::Error {
-            description: self.report.format_current_context().to_string().into(),
+        if !self.is_effective() {
+            return self;
+        }
+        let error_type = match self.error_type.precedence {
+            ErrorTypePrecedence::ExceptionType if registered_error_type(self.report).is_none() => {
+                self.error_type.exception_type.clone()
+            }
+            _ => None,
+        }
+        .unwrap_or_else(|| error_type_of(self.report).into());
+        let status = classifier::span_status(self.report);
+        #[cfg(feature = "http")]
+        let status = status.or_else(|| crate::http::span_status(self.report, &self.spec));
+        #[cfg(feature = "grpc")]
+        let status = status.or_else(|| crate::grpc::span_status(self.report, &self.spec));
+        let mut status = status.unwrap_or_else(|| Status::Error {
+            description: format_contained(self.report.current_context_type_name(), || {
+                self.report.format_current_context().to_string()
+            })
+            .into(),
         });
+
+        let mut snapshot = ExceptionSnapshot::new(
+            self.report,
+            Destination::SpanStatus,
+            timestamp(self.report),
+            vec![KeyValue::new(attribute::ERROR_TYPE, error_type)],
+        );
+        if let Status::Error { description } = &mut status {
+            snapshot.description = Some(std::mem::take(description));
+        }
+        let Some(snapshot) = pipeline::process(snapshot, &self.spec, self.spanish.span_context())
+        else {
+            return self;
+        };
+        if let Status::Error { description } = &mut status {
+            *description = snapshot.description.unwrap_or_default();
+        }
+        self.error_type.error_status = true;
+        self.spanish.set_attributes(snapshot.attributes);
+        self.spanish.set_status(status);
         self
     }
 
     /// End the span.
     ///
     /// ## Attributes & Details
-    /// - The timestamp of the event is given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or defaults to [`now()`](std::time::SystemTime::now) if not found.
+    /// - The timestamp of the event is given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or defaults to [the current time](crate::clock::now) if not found.
     ///
     /// [`SystemTime`](std::time::SystemTime) attachments are
     /// provided report creation hook [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector).
     pub fn end_span(mut self) -> Self {
+        if !self.is_effective() {
+            return self;
+        }
         self.spanish.end_with_timestamp(timestamp(self.report));
         self
     }
 
+    /// Terminate the span as failed: record the `exception` event,
+    /// set the [`Error`](Status::Error) status, and end the span.
+    ///
+    /// Equivalent to `.as_event().with_error_status().end_span()`.
+    pub fn end_span_with_error(self) -> Self {
+        self.as_event().with_error_status().end_span()
+    }
+
     /// ⚠️ This is speculative functionality
     ///
     /// Record the exception-related attributes on the span itself
@@ -156,7 +512,13 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     ///
     /// Attributes taken from: [Semantic conventions for exceptions on spans](https://opentelemetry.io/docs/specs/semconv/exceptions/exceptions-spans/)
     pub fn on_span_attributes(mut self) -> Self {
-        self.spanish.set_attributes(attributes(self.report));
+        if !self.is_emitting() {
+            return self;
+        }
+        if let Some(attributes) = sampled_attributes(self.report, &self.spec, self.decision(), true)
+        {
+            self.set_span_attributes(attributes);
+        }
         self
     }
 
@@ -166,7 +528,14 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     /// as in [`Self::on_span_attributes`], but omit the `exception.stacktrace`
     /// attribute for brevity.
     pub fn as_span_attributes_brief(mut self) -> Self {
-        self.spanish.set_attributes(attributes_brief(self.report));
+        if !self.is_emitting() {
+            return self;
+        }
+        if let Some(attributes) =
+            sampled_attributes(self.report, &self.spec, self.decision(), false)
+        {
+            self.set_span_attributes(attributes);
+        }
         self
     }
 
@@ -176,9 +545,12 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     ///
     /// ## Attributes & Details
     /// - The linked spans' tracing contexts are taken from [`SpanContext`]-typed attachments on the reports. Reports without such attachments are not linked, and reports originating in the current span are not linked either.
+    /// - Reports originating in the same span share a single link, whose attributes become string arrays of the distinct values of all those reports.
     /// - `exception.type` is [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
     /// - `exception.message` is [`.format_current_context().to_string()`](rootcause::Report::format_current_context).
     /// - `exception.stacktrace` is omitted for brevity.
+    /// - The attributes of each report pass through the [`EmitLayer`](crate::pipeline::EmitLayer)s
+    ///   as a [`Destination::SpanLink`], and reports whose emission a layer aborts are not linked.
     ///
     /// [`SpanContext`] attachments are
     /// provided report creation hook [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector).
@@ -188,17 +560,10 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     ///
     /// Attributes taken from: [Semantic conventions for exceptions on spans](https://opentelemetry.io/docs/specs/semconv/exceptions/exceptions-spans/)
     pub fn link_child_report_spans(mut self) -> Self {
-        let curr_ctx = self.spanish.span_context().clone();
-
-        for sub_rep in self.report.iter_reports() {
-            if let Some(ctx) = sub_rep.find_attachment_inner::<SpanContext>()
-                && ctx != &curr_ctx
-            {
-                self.spanish
-                    .add_link(ctx.clone(), attributes_brief(sub_rep));
-            }
+        if !self.is_effective() || !self.sampler_allows() {
+            return self;
         }
-
+        self.add_links(type_and_message);
         self
     }
 
@@ -206,31 +571,241 @@ impl<'a, S: Span> RecordErrorReport<'a, S> {
     /// only set the `error.type` attribute for brevity.
     ///
     /// ## Attributes & Details
-    /// - `error.type` attribute is [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
+    /// - `error.type` attribute is the [`OtelErrorType`](crate::error_type::OtelErrorType) of the context,
+    ///   or [`.current_context_type_name()`](rootcause::Report::current_context_type_name).
+    /// - Reports originating in the same span share a single link, whose `error.type` becomes a string array of their distinct type names.
     ///
     /// ## Spec
     /// [Traces > Span Links](https://opentelemetry.io/docs/concepts/signals/traces/#span-links)
     ///
     /// Attributes taken from: [Recording errors > Recording errors on spans](https://opentelemetry.io/docs/specs/semconv/general/recording-errors/#recording-errors-on-spans)
     pub fn link_child_report_spans_brief(mut self) -> Self {
+        if !self.is_effective() || !self.sampler_allows() {
+            return self;
+        }
+        self.add_links(|sub_rep| {
+            vec![KeyValue::new(attribute::ERROR_TYPE, error_type_of(sub_rep))]
+        });
+        self
+    }
+
+    /// Traverse the report and all child reports looking for attachments
+    /// of type [`SpanContext`], and record an `exception` event in association
+    /// with each span the child reports originated in.
+    ///
+    /// Since a finished span cannot be amended, the event is recorded on a short
+    /// `exception` child span of the originating span, which starts and ends at the
+    /// report's timestamp and links back to the current span.
+    ///
+    /// ## Attributes & Details
+    /// - Only child reports from sampled traces are recorded, since the others would never be exported. Reports originating in the current span are skipped.
+    /// - The timestamps of the child span and event are given by a [`SystemTime`](std::time::SystemTime)-typed attachment, or default to [the current time](crate::clock::now) if not found.
+    /// - `exception.type` is [`.current_context_type_name()`](rootcause::Report::current_context_type_name) of the child report.
+    /// - `exception.message` is [`.format_current_context().to_string()`](rootcause::Report::format_current_context) of the child report.
+    /// - `exception.stacktrace` is just `.to_string()` of the child report.
+    ///
+    /// [`SpanContext`] attachments are
+    /// provided report creation hook [`OpenTelemetryMetadataCollector`](crate::attachments::OpenTelemetryMetadataCollector).
+    ///
+    /// ## Spec
+    /// [Semantic conventions for exceptions on spans](https://opentelemetry.io/docs/specs/semconv/exceptions/exceptions-spans/)
+    pub fn as_events_on_origin_spans(self, tracer: &impl Tracer) -> Self {
+        if !self.sampler_allows() {
+            return self;
+        }
         let curr_ctx = self.spanish.span_context().clone();
 
         for sub_rep in self.report.iter_reports() {
-            if let Some(ctx) = sub_rep.find_attachment_inner::<SpanContext>()
+            let sub_rep = sub_rep.as_report_ref();
+            if let Some(ctx) = correlation::span_context(&sub_rep)
                 && ctx != &curr_ctx
+                && ctx.is_sampled()
             {
-                self.spanish.add_link(
-                    ctx.clone(),
-                    [KeyValue::new(
-                        attribute::ERROR_TYPE,
-                        sub_rep.current_context_type_name(),
-                    )],
-                );
+                self.add_event_on_remote_span(tracer, sub_rep, ctx, &curr_ctx);
             }
         }
 
         self
     }
+
+    /// Record the [`Report`](rootcause::Report) as an `exception` event as in
+    /// [`Self::as_event`], but in association with the span given by a
+    /// [`TargetSpan`] attachment in the report tree, if any.
+    ///
+    /// As in [`Self::as_events_on_origin_spans`], the event is then recorded on a
+    /// short `exception` child span of the target span, linking back to this span.
+    /// Without a target span, or if it is this span, this is [`Self::as_event`].
+    pub fn as_event_on_target_span(self, tracer: &impl Tracer) -> Self {
+        let curr_ctx = self.spanish.span_context().clone();
+        match target_span(self.report) {
+            Some(target) if target != &curr_ctx => {
+                if target.is_sampled() {
+                    self.add_event_on_remote_span(tracer, self.report, target, &curr_ctx);
+                }
+                self
+            }
+            _ => self.as_event(),
+        }
+    }
+
+    fn add_event_on_remote_span(
+        &self,
+        tracer: &impl Tracer,
+        rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+        ctx: &SpanContext,
+        curr_ctx: &SpanContext,
+    ) {
+        let Some(attributes) = sampled_attributes(rep, &self.spec, self.decision(), true) else {
+            return;
+        };
+        let Some(snapshot) = pipeline::process(
+            ExceptionSnapshot::new(rep, Destination::SpanEvent, timestamp(rep), attributes),
+            &self.spec,
+            ctx,
+        ) else {
+            return;
+        };
+        let timestamp = snapshot.timestamp;
+        let parent = Context::new().with_remote_span_context(ctx.clone());
+        let mut span = tracer
+            .span_builder(EXCEPTION)
+            .with_kind(SpanKind::Internal)
+            .with_start_time(timestamp)
+            .with_links(vec![Link::with_context(curr_ctx.clone())])
+            .start_with_context(tracer, &parent);
+        span.add_event_with_timestamp(EXCEPTION, timestamp, snapshot.attributes);
+        span.end_with_timestamp(timestamp);
+    }
+
+    fn add_event(&mut self, attributes: Vec<KeyValue>) {
+        if let Some(snapshot) = pipeline::process(
+            ExceptionSnapshot::new(
+                self.report,
+                Destination::SpanEvent,
+                timestamp(self.report),
+                attributes,
+            ),
+            &self.spec,
+            self.spanish.span_context(),
+        ) {
+            self.spanish.add_event_with_timestamp(
+                EXCEPTION,
+                snapshot.timestamp,
+                snapshot.attributes,
+            );
+        }
+    }
+
+    fn set_span_attributes(&mut self, attributes: Vec<KeyValue>) {
+        if let Some(snapshot) = pipeline::process(
+            ExceptionSnapshot::new(
+                self.report,
+                Destination::SpanAttributes,
+                timestamp(self.report),
+                attributes,
+            ),
+            &self.spec,
+            self.spanish.span_context(),
+        ) {
+            let mut attributes = snapshot.attributes;
+            self.reconcile_error_type(&mut attributes);
+            self.spanish.set_attributes(attributes);
+        }
+    }
+
+    /// Make `exception.type` and `error.type` agree in span `attributes`, and
+    /// with the `error.type` already set by [`Self::with_error_status`], if any.
+    fn reconcile_error_type(&mut self, attributes: &mut Vec<KeyValue>) {
+        let registered = registered_error_type(self.report);
+        let keep_exception_type = registered.is_some();
+        let error_type = match (self.error_type.precedence, registered) {
+            (ErrorTypePrecedence::ErrorType, _) => Value::from(error_type_of(self.report)),
+            (ErrorTypePrecedence::ExceptionType, Some(registered)) => Value::from(registered),
+            (ErrorTypePrecedence::ExceptionType, None) => {
+                let Some(exception_type) = attributes
+                    .iter()
+                    .find(|kv| kv.key.as_str() == attribute::EXCEPTION_TYPE)
+                    .map(|kv| kv.value.clone())
+                else {
+                    return;
+                };
+                self.error_type.exception_type = Some(exception_type.clone());
+                exception_type
+            }
+        };
+
+        let mut has_error_type = false;
+        for kv in attributes.iter_mut() {
+            if kv.key.as_str() == attribute::ERROR_TYPE {
+                has_error_type = true;
+                kv.value = error_type.clone();
+            } else if kv.key.as_str() == attribute::EXCEPTION_TYPE && !keep_exception_type {
+                kv.value = error_type.clone();
+            }
+        }
+        if self.error_type.error_status && !has_error_type {
+            attributes.push(KeyValue::new(attribute::ERROR_TYPE, error_type));
+        }
+    }
+
+    fn add_links(
+        &mut self,
+        link_attributes: impl Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<KeyValue>,
+    ) {
+        let curr_ctx = self.spanish.span_context().clone();
+        let mut links: Vec<(SpanContext, Vec<KeyValue>)> = Vec::new();
+        let mut dropped: Vec<SpanContext> = Vec::new();
+        let mut elided = 0;
+
+        for sub_rep in self.report.iter_reports() {
+            if sub_rep.find_attachment::<ElidedSpanContext>().is_some() {
+                elided += 1;
+                continue;
+            }
+            let Some(ctx) = correlation::span_context(&sub_rep) else {
+                continue;
+            };
+            if ctx == &curr_ctx {
+                continue;
+            }
+
+            let processed = || {
+                pipeline::process(
+                    ExceptionSnapshot::new(
+                        sub_rep,
+                        Destination::SpanLink,
+                        timestamp(sub_rep),
+                        link_attributes(sub_rep),
+                    ),
+                    &self.spec,
+                    ctx,
+                )
+                .map(|snapshot| snapshot.attributes)
+            };
+            if let Some((_, attributes)) = links.iter_mut().find(|(linked, _)| linked == ctx) {
+                if let Some(processed) = processed() {
+                    merge_attributes(attributes, processed);
+                }
+            } else if links.len() < self.max_links {
+                if let Some(processed) = processed() {
+                    links.push((ctx.clone(), processed));
+                }
+            } else if !dropped.contains(ctx) {
+                dropped.push(ctx.clone());
+            }
+        }
+
+        let dropped = (dropped.len() + elided) as i64;
+        for (ctx, attributes) in links {
+            self.spanish.add_link(ctx, attributes);
+        }
+        if dropped > 0 {
+            self.spanish
+                .set_attributes([KeyValue::new(EXCEPTION_DROPPED_LINK_COUNT, dropped)]);
+            #[cfg(feature = "metrics")]
+            crate::meter::record_links_elided(dropped as u64);
+        }
+    }
 }
 
 enum SpanIsh<'a, S: Span> {
@@ -266,7 +841,7 @@ impl<'a, S: Span> SpanIsh<'a, S> {
 
     fn add_event_with_timestamp(
         &mut self,
-        name: &'static str,
+        name: impl Into<Cow<'static, str>>,
         timestamp: SystemTime,
         attributes: Vec<KeyValue>,
     ) {
@@ -276,6 +851,13 @@ impl<'a, S: Span> SpanIsh<'a, S> {
         }
     }
 
+    fn is_recording(&self) -> bool {
+        match self {
+            Self::SpanRef(span) => span.is_recording(),
+            Self::MutSpan(span) => span.is_recording(),
+        }
+    }
+
     fn span_context(&self) -> &SpanContext {
         match self {
             Self::SpanRef(span) => span.span_context(),
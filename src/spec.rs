@@ -0,0 +1,369 @@
+use std::{borrow::Cow, sync::RwLock};
+
+static GLOBAL_SPEC: RwLock<ExceptionEventSpec> = RwLock::new(ExceptionEventSpec::new());
+
+/// Configuration of the exception data emitted for a [`Report`](rootcause::Report),
+/// shared by the span and log emission paths.
+///
+/// A process-wide default can be set with [`ExceptionEventSpec::install`], and is
+/// picked up by every builder when it is created. Builders can then override it
+/// for individual emissions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExceptionEventSpec {
+    pub(crate) max_stacktrace_len: Option<usize>,
+    pub(crate) env_allowlist: Vec<Cow<'static, str>>,
+    pub(crate) attachment_attributes: bool,
+    pub(crate) baggage_attributes: bool,
+    pub(crate) max_attributes: Option<usize>,
+    pub(crate) message_lines: MessageLines,
+    pub(crate) message_source: MessageSource,
+    pub(crate) forward_trace_ids: bool,
+    pub(crate) typed_attachments: Vec<(PrimitiveAttachment, Cow<'static, str>)>,
+    pub(crate) report_cbor: bool,
+    pub(crate) semconv_profile: SemconvProfile,
+    #[cfg(feature = "http")]
+    pub(crate) http_client_errors: bool,
+    #[cfg(feature = "grpc")]
+    pub(crate) grpc_client_errors: bool,
+    #[cfg(feature = "grpc")]
+    pub(crate) grpc_metadata: Vec<Cow<'static, str>>,
+}
+
+/// How multi-line `exception.message` attributes are emitted, see
+/// [`ExceptionEventSpec::message_lines`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageLines {
+    /// Emit the message as is.
+    #[default]
+    Keep,
+    /// Join the lines into one, separated by ` ⏎ `.
+    Collapse,
+    /// Keep the first line in `exception.message`, and move the following ones
+    /// to the `exception.message_overflow` attribute.
+    Overflow,
+}
+
+/// Which reports of the chain the `exception.message` is taken from, see
+/// [`ExceptionEventSpec::message_from_root`] and [`ExceptionEventSpec::message_chain`].
+///
+/// The chain of a report follows its first child at each level, down to the
+/// root cause.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MessageSource {
+    /// The current context of the report.
+    #[default]
+    Current,
+    /// The context of the root cause.
+    Root,
+    /// The contexts of the whole chain, outermost first, joined by the separator.
+    Chain(Cow<'static, str>),
+}
+
+/// Which generation of the semantic conventions the attribute keys and shapes
+/// follow, see [`ExceptionEventSpec::semconv_profile`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SemconvProfile {
+    /// The `exception.*` attributes with the `exception.escaped` flag on span
+    /// events, set as reports are the errors their operation failed with unless
+    /// the report carries the flag itself, and the `code.*`
+    /// and `db.*` keys from before their renaming, such as `code.filepath`.
+    Legacy,
+    /// The `exception.*` attributes of the stable conventions.
+    #[default]
+    Stable,
+    /// The newer error recording style, with `error.type` and `error.message`
+    /// in place of `exception.type` and `exception.message`.
+    ErrorAttributes,
+}
+
+/// Attachment types emitted as typed attributes, see
+/// [`ExceptionEventSpec::typed_attachment`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveAttachment {
+    /// `i64` attachments, emitted as int attributes.
+    I64,
+    /// `f64` attachments, emitted as double attributes.
+    F64,
+    /// `bool` attachments, emitted as bool attributes.
+    Bool,
+    /// `&'static str` and `String` attachments, emitted as string attributes.
+    Str,
+}
+
+impl Default for ExceptionEventSpec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExceptionEventSpec {
+    /// The default spec, which emits everything in full.
+    pub const fn new() -> Self {
+        Self {
+            max_stacktrace_len: None,
+            env_allowlist: Vec::new(),
+            attachment_attributes: true,
+            baggage_attributes: false,
+            max_attributes: None,
+            message_lines: MessageLines::Keep,
+            message_source: MessageSource::Current,
+            forward_trace_ids: false,
+            typed_attachments: Vec::new(),
+            report_cbor: false,
+            semconv_profile: SemconvProfile::Stable,
+            #[cfg(feature = "http")]
+            http_client_errors: true,
+            #[cfg(feature = "grpc")]
+            grpc_client_errors: true,
+            #[cfg(feature = "grpc")]
+            grpc_metadata: Vec::new(),
+        }
+    }
+
+    /// The process-wide spec set by [`Self::install`], or [`Self::new`] if none was installed.
+    pub fn global() -> Self {
+        GLOBAL_SPEC
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone()
+    }
+
+    /// Make this the process-wide spec, returning the previous one.
+    pub fn install(self) -> Self {
+        let mut global = GLOBAL_SPEC
+            .write()
+            .unwrap_or_else(|poison| poison.into_inner());
+        std::mem::replace(&mut *global, self)
+    }
+
+    /// Cap the length of the `exception.stacktrace` attribute in bytes.
+    ///
+    /// Longer stacktraces keep their head and tail, with the middle replaced by a
+    /// `…[truncated N bytes]` marker, such that the attribute fits within `limit`.
+    /// Exporters commonly drop attributes exceeding their value length limit
+    /// silently, so this should be set at or below that limit.
+    pub fn max_stacktrace_len(mut self, limit: usize) -> Self {
+        self.max_stacktrace_len = Some(limit);
+        self
+    }
+
+    /// Remove the cap set by [`Self::max_stacktrace_len`].
+    pub fn unlimited_stacktrace(mut self) -> Self {
+        self.max_stacktrace_len = None;
+        self
+    }
+
+    /// Capture the values of the allowlisted environment variables at emission time,
+    /// as `process.environment_variable.<KEY>` attributes.
+    ///
+    /// Only the listed keys are ever read, so secrets elsewhere in the
+    /// environment cannot leak. Unset variables are omitted.
+    pub fn with_env_snapshot<K: Into<Cow<'static, str>>>(
+        mut self,
+        allowlist: impl IntoIterator<Item = K>,
+    ) -> Self {
+        self.env_allowlist
+            .extend(allowlist.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether [`KeyValue`](opentelemetry::KeyValue)-typed attachments anywhere in the
+    /// report tree are added as attributes on the emitted event or record.
+    ///
+    /// Attachments on outer reports take precedence over ones with the same key on
+    /// inner reports, and none of them override the `exception.*` attributes.
+    ///
+    /// Enabled by default.
+    pub fn attachment_attributes(mut self, enabled: bool) -> Self {
+        self.attachment_attributes = enabled;
+        self
+    }
+
+    /// Whether the entries of the [baggage](opentelemetry::baggage::Baggage) of the
+    /// current context at emission time are added as attributes, so that e.g.
+    /// tenant or request identifiers travel with the exception.
+    ///
+    /// Baggage entries do not override attributes with the same key.
+    ///
+    /// Disabled by default.
+    pub fn baggage_attributes(mut self, enabled: bool) -> Self {
+        self.baggage_attributes = enabled;
+        self
+    }
+
+    /// Cap the number of attributes on the emitted event or record.
+    ///
+    /// SDKs silently drop attributes beyond their per-event limit, so this
+    /// should be set at or below that limit. When there are more attributes,
+    /// the trailing ones are replaced by an `exception.dropped_attribute_count`
+    /// attribute counting them, such that the total fits within `limit`.
+    /// `exception.type`, `exception.message` and `exception.stacktrace` are
+    /// always kept, even if they alone exceed `limit`.
+    ///
+    /// The cap applies to the final attributes, including those added by
+    /// builders and [`EmitLayer`](crate::pipeline::EmitLayer)s and the
+    /// structured attributes of log records.
+    pub fn max_attributes(mut self, limit: usize) -> Self {
+        self.max_attributes = Some(limit);
+        self
+    }
+
+    /// Remove the cap set by [`Self::max_attributes`].
+    pub fn unlimited_attributes(mut self) -> Self {
+        self.max_attributes = None;
+        self
+    }
+
+    /// How messages spanning multiple lines, which render poorly in some trace
+    /// UIs, are emitted in the `exception.message` attribute.
+    ///
+    /// Defaults to [`MessageLines::Keep`].
+    pub fn message_lines(mut self, policy: MessageLines) -> Self {
+        self.message_lines = policy;
+        self
+    }
+
+    /// Take the `exception.message` from the root cause of the report, the
+    /// innermost report following the first child at each level, instead of
+    /// its current context.
+    pub fn message_from_root(mut self) -> Self {
+        self.message_source = MessageSource::Root;
+        self
+    }
+
+    /// Make the `exception.message` the messages of the whole chain down to the
+    /// root cause joined by `separator`, as in `outer: middle: root` for `": "`,
+    /// the way anyhow-style error chains are usually displayed.
+    ///
+    /// The `exception.type` stays the one of the current context.
+    pub fn message_chain(mut self, separator: impl Into<Cow<'static, str>>) -> Self {
+        self.message_source = MessageSource::Chain(separator.into());
+        self
+    }
+
+    /// Whether to add a `rootcause.forward_to_trace_ids` attribute listing the
+    /// ids of the traces other than the one of the span the exception is
+    /// recorded on which reports in the tree originated in, as given by their
+    /// [`SpanContext`](opentelemetry::trace::SpanContext) attachments.
+    ///
+    /// Exporters only deliver the exception to the trace it is emitted in. With
+    /// this hint, a collector processor can associate the exception with the
+    /// originating traces server-side. For example, the
+    /// [transform processor](https://github.com/open-telemetry/opentelemetry-collector-contrib/tree/main/processor/transformprocessor)
+    /// can lift the hex-encoded ids from the events onto their spans, where
+    /// backends can search for them by the id of an originating trace:
+    ///
+    /// ```yaml
+    /// processors:
+    ///   transform/forward-trace-ids:
+    ///     error_mode: ignore
+    ///     trace_statements:
+    ///       - context: spanevent
+    ///         conditions:
+    ///           - attributes["rootcause.forward_to_trace_ids"] != nil
+    ///         statements:
+    ///           - set(span.attributes["rootcause.forward_to_trace_ids"], attributes["rootcause.forward_to_trace_ids"])
+    /// ```
+    ///
+    /// Disabled by default.
+    pub fn forward_trace_ids(mut self, enabled: bool) -> Self {
+        self.forward_trace_ids = enabled;
+        self
+    }
+
+    /// Which attribute keys and shapes are emitted, for backends expecting an
+    /// older or newer style of recording errors than the stable conventions.
+    ///
+    /// [Rehydrating](crate::rehydrate) reports relies on the `exception.*`
+    /// attributes, so it does not recognize events emitted with
+    /// [`SemconvProfile::ErrorAttributes`].
+    ///
+    /// Defaults to [`SemconvProfile::Stable`].
+    pub fn semconv_profile(mut self, profile: SemconvProfile) -> Self {
+        self.semconv_profile = profile;
+        self
+    }
+
+    /// Emit attachments of the primitive type `kind` as an attribute named `key`,
+    /// keeping their numeric or boolean type for backends to aggregate on,
+    /// rather than only as part of the formatted report.
+    ///
+    /// The outermost such attachment in the report tree is used, and it does not
+    /// override attributes with the same key.
+    ///
+    /// ```
+    /// use rootcause_opentelemetry::spec::{ExceptionEventSpec, PrimitiveAttachment};
+    ///
+    /// let spec = ExceptionEventSpec::new()
+    ///     .typed_attachment(PrimitiveAttachment::I64, "app.retry_count")
+    ///     .typed_attachment(PrimitiveAttachment::Bool, "app.retryable");
+    /// # let _ = spec;
+    /// ```
+    pub fn typed_attachment(
+        mut self,
+        kind: PrimitiveAttachment,
+        key: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.typed_attachments.push((kind, key.into()));
+        self
+    }
+
+    /// Whether to add an `exception.report.cbor` attribute carrying a compact
+    /// [CBOR encoding](crate::cbor) of the whole report tree, base64-encoded, for
+    /// backends reconstructing reports losslessly while the other attributes stay
+    /// human-readable.
+    ///
+    /// Like `exception.stacktrace`, it is omitted from brief emissions.
+    ///
+    /// Disabled by default.
+    pub fn report_cbor(mut self, enabled: bool) -> Self {
+        self.report_cbor = enabled;
+        self
+    }
+
+    /// Whether reports with a 4xx [HTTP status code](crate::http) set the span
+    /// status to `Error` in [`with_error_status`](crate::span_event::RecordErrorReport::with_error_status).
+    ///
+    /// The HTTP semantic conventions leave the status of server spans unset for
+    /// 4xx responses, as the client is at fault, so disable this for specs used
+    /// on server spans.
+    ///
+    /// Enabled by default.
+    #[cfg(feature = "http")]
+    pub fn http_client_errors(mut self, enabled: bool) -> Self {
+        self.http_client_errors = enabled;
+        self
+    }
+
+    /// Whether reports with a [gRPC status code](crate::grpc) caused by the
+    /// client, such as `NOT_FOUND` or `INVALID_ARGUMENT`, set the span status to
+    /// `Error` in [`with_error_status`](crate::span_event::RecordErrorReport::with_error_status).
+    ///
+    /// The gRPC semantic conventions leave the status of server spans unset for
+    /// those codes, so disable this for specs used on server spans.
+    ///
+    /// Enabled by default.
+    #[cfg(feature = "grpc")]
+    pub fn grpc_client_errors(mut self, enabled: bool) -> Self {
+        self.grpc_client_errors = enabled;
+        self
+    }
+
+    /// Emit the values of the allowlisted keys of the [`GrpcMetadata`](crate::grpc::GrpcMetadata)
+    /// of the report as `rpc.grpc.response.metadata.<key>` attributes.
+    ///
+    /// Metadata often carries credentials, so no key is emitted unless listed.
+    #[cfg(feature = "grpc")]
+    pub fn grpc_metadata<K: Into<Cow<'static, str>>>(
+        mut self,
+        allowlist: impl IntoIterator<Item = K>,
+    ) -> Self {
+        self.grpc_metadata
+            .extend(allowlist.into_iter().map(Into::into));
+        self
+    }
+}
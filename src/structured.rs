@@ -0,0 +1,105 @@
+//! Attachments carrying structured data, emitted as nested [`AnyValue`]s on log
+//! records rather than as opaque strings.
+
+use core::fmt;
+use std::collections::HashMap;
+
+use opentelemetry::{Key, logs::AnyValue};
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+};
+use serde::Serialize;
+
+/// Attachment holding a [`Serialize`] value, which is added to log records
+/// as a structured attribute under its key, preserving queryable structure.
+///
+/// The value is also used, under its key, in place of the formatted attachment in the
+/// structured body of [`LoggerExt::emit_error_report_structured`](crate::log_event::LoggerExt::emit_error_report_structured).
+///
+/// Since span attributes cannot be nested, the attachment is not emitted on spans.
+///
+/// ```
+/// # use rootcause::prelude::*;
+/// # use rootcause_opentelemetry::structured::AnyValueAttachment;
+/// #[derive(serde::Serialize)]
+/// struct Order { id: u64, items: Vec<&'static str> }
+///
+/// let order = Order { id: 7, items: vec!["apple"] };
+/// let report = report!("checkout failed")
+///     .attach(AnyValueAttachment::new("app.order", &order).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnyValueAttachment {
+    key: Key,
+    value: serde_json::Value,
+}
+
+impl AnyValueAttachment {
+    /// Serialize `value` to be emitted under `key`.
+    pub fn new(key: impl Into<Key>, value: &impl Serialize) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            key: key.into(),
+            value: serde_json::to_value(value)?,
+        })
+    }
+
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The value as a log attribute value, or `None` if it serialized to `null`.
+    pub fn to_anyvalue(&self) -> Option<AnyValue> {
+        json_to_anyvalue(&self.value)
+    }
+}
+
+impl fmt::Display for AnyValueAttachment {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{} = {}", self.key, self.value)
+    }
+}
+
+/// The [`AnyValueAttachment`]s of the report tree as log attributes, outer
+/// reports first, skipping keys which are already present.
+pub(crate) fn attributes(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<(Key, AnyValue)> {
+    let mut attributes: Vec<(Key, AnyValue)> = Vec::new();
+    for sub_rep in rep.iter_reports() {
+        for attachment in sub_rep
+            .attachments()
+            .iter()
+            .filter_map(|a| a.downcast_inner::<AnyValueAttachment>())
+        {
+            if !attributes.iter().any(|(key, _)| key == &attachment.key)
+                && let Some(value) = attachment.to_anyvalue()
+            {
+                attributes.push((attachment.key.clone(), value));
+            }
+        }
+    }
+    attributes
+}
+
+/// Nulls are omitted, since [`AnyValue`] cannot represent them.
+fn json_to_anyvalue(value: &serde_json::Value) -> Option<AnyValue> {
+    use serde_json::Value;
+
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => AnyValue::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => AnyValue::Int(i),
+            None => AnyValue::Double(n.as_f64()?),
+        },
+        Value::String(s) => AnyValue::from(s.clone()),
+        Value::Array(items) => AnyValue::ListAny(Box::new(
+            items.iter().filter_map(json_to_anyvalue).collect(),
+        )),
+        Value::Object(fields) => AnyValue::Map(Box::new(
+            fields
+                .iter()
+                .filter_map(|(key, value)| Some((Key::from(key.clone()), json_to_anyvalue(value)?)))
+                .collect::<HashMap<_, _>>(),
+        )),
+    })
+}
@@ -0,0 +1,102 @@
+//! Spawning tokio tasks whose failures are recorded on the span that spawned
+//! them, rather than disappearing with the [`JoinHandle`] nobody awaits.
+//!
+//! ```
+//! # use rootcause::prelude::*;
+//! use rootcause_opentelemetry::task::spawn_traced;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let handle = spawn_traced(async {
+//!     Err::<(), Report>(report!("cache refresh failed"))
+//! });
+//! assert!(handle.await.unwrap().is_err());
+//! # }
+//! ```
+
+use std::{
+    any::Any,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{self, Poll},
+};
+
+use opentelemetry::{Context, context::FutureExt, trace::TraceContextExt};
+use tokio::task::JoinHandle;
+
+use crate::{
+    panic::{caught_panic_report, hook_installed},
+    span_event::SpanRefReportExt,
+    utilities::AsReportRef,
+};
+
+/// Spawn `future` on the current tokio runtime, in the [`Context`] current at
+/// the time of the call.
+///
+/// If the task fails with an error, or panics, the error is recorded as an
+/// `exception` event on the span of that context, linked to the spans the
+/// reports in it originated in, such as spans the task created itself, see
+/// [`link_child_report_spans`](crate::span_event::RecordErrorReport::link_child_report_spans).
+/// If that span has ended, the error is reported as by [`library::report`](crate::library::report)
+/// in that context instead.
+///
+/// The task's result is returned unchanged through the [`JoinHandle`], and
+/// panics resume unwinding after being recorded, so that they surface as a
+/// [`JoinError`](tokio::task::JoinError). Panics are not recorded here if
+/// [`install_panic_hook`](crate::panic::install_panic_hook) records them already.
+///
+/// Panics if called outside of a tokio runtime, as [`tokio::spawn`].
+pub fn spawn_traced<F, T, E>(future: F) -> JoinHandle<Result<T, E>>
+where
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: Send + 'static,
+    E: AsReportRef + Send + 'static,
+{
+    let cx = Context::current();
+    let task = CatchUnwind(Box::pin(future.with_context(cx.clone())));
+    tokio::spawn(async move {
+        match task.await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(rep)) => {
+                record(&cx, &rep);
+                Err(rep)
+            }
+            Err(payload) => {
+                if !hook_installed() {
+                    record(&cx, &caught_panic_report(&*payload));
+                }
+                panic::resume_unwind(payload)
+            }
+        }
+    })
+}
+
+/// Record `rep` on the span of `cx`, or report it in `cx` if that span is not
+/// recording anymore.
+fn record(cx: &Context, rep: &impl AsReportRef) {
+    let span = cx.span();
+    let recorder = span.record_error_report(rep);
+    if recorder.is_effective() {
+        let _ = recorder.as_event().link_child_report_spans();
+    } else {
+        let _guard = cx.clone().attach();
+        crate::library::report(rep);
+    }
+}
+
+/// Future catching panics of the inner future, as `Err` with the panic payload.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
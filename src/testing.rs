@@ -0,0 +1,299 @@
+//! Utilities for asserting on the telemetry emitted by this crate in tests.
+
+use std::fmt::{self, Debug, Display};
+
+use opentelemetry::{Key, KeyValue, Value, trace::Event};
+
+use crate::utilities::EXCEPTION;
+
+pub mod capturing;
+pub mod deterministic;
+#[cfg(all(feature = "testing-sdk", feature = "logs"))]
+pub mod logs;
+#[cfg(feature = "testing-sdk")]
+pub mod matrix;
+#[cfg(feature = "testing-sdk")]
+pub mod providers;
+
+/// Expectation on a single attribute, see [`EventMatcher::attr`].
+pub enum Expect {
+    /// The attribute is present with exactly this value.
+    Eq(Value),
+    /// The attribute is present and its string representation contains this substring.
+    Contains(String),
+    /// The attribute is present with any value.
+    Present,
+    /// The attribute is not present.
+    Absent,
+    /// The attribute is present and its value satisfies the predicate.
+    Satisfies(Box<dyn Fn(&Value) -> bool + Send + Sync>),
+}
+
+/// The attribute is present with exactly this value.
+pub fn eq(value: impl Into<Value>) -> Expect {
+    Expect::Eq(value.into())
+}
+
+/// The attribute is present and its string representation contains `needle`.
+pub fn contains(needle: impl Into<String>) -> Expect {
+    Expect::Contains(needle.into())
+}
+
+/// The attribute is present with any value.
+pub fn present() -> Expect {
+    Expect::Present
+}
+
+/// The attribute is not present.
+pub fn absent() -> Expect {
+    Expect::Absent
+}
+
+/// The attribute is present and its value satisfies `predicate`.
+pub fn satisfies(predicate: impl Fn(&Value) -> bool + Send + Sync + 'static) -> Expect {
+    Expect::Satisfies(Box::new(predicate))
+}
+
+impl Expect {
+    fn check(&self, value: Option<&Value>) -> bool {
+        match (self, value) {
+            (Self::Absent, value) => value.is_none(),
+            (_, None) => false,
+            (Self::Eq(expected), Some(value)) => expected == value,
+            (Self::Contains(needle), Some(value)) => value.as_str().contains(needle.as_str()),
+            (Self::Present, Some(_)) => true,
+            (Self::Satisfies(predicate), Some(value)) => predicate(value),
+        }
+    }
+}
+
+impl Debug for Expect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eq(value) => write!(f, "== {value:?}"),
+            Self::Contains(needle) => write!(f, "contains {needle:?}"),
+            Self::Present => f.write_str("present"),
+            Self::Absent => f.write_str("absent"),
+            Self::Satisfies(_) => f.write_str("satisfies <predicate>"),
+        }
+    }
+}
+
+/// Set of expectations on an event's name and attributes, checking only what
+/// was asked for instead of comparing the whole event.
+///
+/// ```
+/// use rootcause_opentelemetry::testing::{EventMatcher, contains, eq};
+///
+/// let matcher = EventMatcher::exception()
+///     .attr("exception.type", eq("&str"))
+///     .attr("exception.message", contains("went wrong"));
+/// # let _ = matcher;
+/// ```
+#[derive(Debug, Default)]
+pub struct EventMatcher {
+    name: Option<String>,
+    expectations: Vec<(Key, Expect)>,
+}
+
+impl EventMatcher {
+    /// Match events of any name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match events named `exception`.
+    pub fn exception() -> Self {
+        Self::new().name(EXCEPTION)
+    }
+
+    /// Require the event to have the given name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Add an expectation on the attribute `key`.
+    pub fn attr(mut self, key: impl Into<Key>, expect: Expect) -> Self {
+        self.expectations.push((key.into(), expect));
+        self
+    }
+
+    /// Check a captured span event against the expectations.
+    pub fn check(&self, event: &Event) -> Result<(), Mismatch> {
+        self.check_parts(Some(&event.name), &event.attributes)
+    }
+
+    /// Check a bare set of attributes, such as those of a log record,
+    /// against the attribute expectations. The name expectation is ignored.
+    pub fn check_attributes(&self, attributes: &[KeyValue]) -> Result<(), Mismatch> {
+        self.check_parts(None, attributes)
+    }
+
+    /// Whether a captured span event satisfies all expectations.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.check(event).is_ok()
+    }
+
+    fn check_parts(&self, name: Option<&str>, attributes: &[KeyValue]) -> Result<(), Mismatch> {
+        let mut failures = Vec::new();
+
+        if let (Some(expected), Some(actual)) = (&self.name, name)
+            && expected != actual
+        {
+            failures.push(format!("name: expected {expected:?}, got {actual:?}"));
+        }
+
+        for (key, expect) in &self.expectations {
+            let value = attributes
+                .iter()
+                .find(|kv| &kv.key == key)
+                .map(|kv| &kv.value);
+            if !expect.check(value) {
+                failures.push(match value {
+                    Some(value) => format!("{key}: expected {expect:?}, got {value:?}"),
+                    None => format!("{key}: expected {expect:?}, got nothing"),
+                });
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Mismatch {
+                failures,
+                attributes: attributes.to_vec(),
+            })
+        }
+    }
+}
+
+/// Description of why an event did not satisfy an [`EventMatcher`].
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    /// One line per failed expectation.
+    pub failures: Vec<String>,
+    /// All attributes of the checked event, for context.
+    pub attributes: Vec<KeyValue>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "event does not match:")?;
+        for failure in &self.failures {
+            writeln!(f, "  - {failure}")?;
+        }
+        writeln!(f, "attributes:")?;
+        for kv in &self.attributes {
+            writeln!(f, "  {} = {:?}", kv.key, kv.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Panic with a readable description unless `event` satisfies `matcher`.
+#[track_caller]
+pub fn assert_event_matches(event: &Event, matcher: &EventMatcher) {
+    if let Err(mismatch) = matcher.check(event) {
+        panic!("{mismatch}");
+    }
+}
+
+/// Find the first of the captured `events` satisfying `matcher`.
+pub fn find_matching_event<'e>(
+    events: impl IntoIterator<Item = &'e Event>,
+    matcher: &EventMatcher,
+) -> Option<&'e Event> {
+    events.into_iter().find(|event| matcher.matches(event))
+}
+
+/// Panic with a readable description unless one of the captured `events` satisfies `matcher`.
+#[track_caller]
+pub fn assert_any_event_matches<'e>(
+    events: impl IntoIterator<Item = &'e Event>,
+    matcher: &EventMatcher,
+) {
+    let events: Vec<&Event> = events.into_iter().collect();
+    if find_matching_event(events.iter().copied(), matcher).is_none() {
+        let mut message = format!("none of {} events match {matcher:?}\n", events.len());
+        for event in events {
+            if let Err(mismatch) = matcher.check(event) {
+                message.push_str(&mismatch.to_string());
+            }
+        }
+        panic!("{message}");
+    }
+}
+
+/// Panic with a readable description unless `span` has an `exception` event
+/// whose `exception.type` contains `type_contains` and whose
+/// `exception.message` contains `message_contains`.
+///
+/// ```
+/// # use opentelemetry::trace::{Span, Tracer, TracerProvider};
+/// # use rootcause::prelude::*;
+/// # use rootcause_opentelemetry::span_event::SpanReportExt;
+/// use rootcause_opentelemetry::testing::{
+///     assert_error_status, assert_has_exception_event, providers::test_providers,
+/// };
+///
+/// let providers = test_providers();
+/// let mut span = providers.tracer_provider.tracer("test").start("checkout");
+/// span.record_error_report(&report!("payment declined"))
+///     .as_event()
+///     .with_error_status();
+/// span.end();
+///
+/// let spans = providers.finished_spans();
+/// assert_has_exception_event(&spans[0], "str", "declined");
+/// assert_error_status(&spans[0]);
+/// ```
+#[cfg(feature = "testing-sdk")]
+#[track_caller]
+pub fn assert_has_exception_event(
+    span: &opentelemetry_sdk::trace::SpanData,
+    type_contains: &str,
+    message_contains: &str,
+) {
+    use opentelemetry_semantic_conventions::attribute;
+
+    let matcher = EventMatcher::exception()
+        .attr(attribute::EXCEPTION_TYPE, contains(type_contains))
+        .attr(attribute::EXCEPTION_MESSAGE, contains(message_contains));
+    if find_matching_event(&span.events.events, &matcher).is_none() {
+        let mut message = format!("span {:?} has no matching exception event\n", span.name);
+        for event in &span.events.events {
+            if let Err(mismatch) = matcher.check(event) {
+                message.push_str(&mismatch.to_string());
+            }
+        }
+        panic!("{message}");
+    }
+}
+
+/// Panic unless `span` has no `exception` events.
+#[cfg(feature = "testing-sdk")]
+#[track_caller]
+pub fn assert_no_exception_events(span: &opentelemetry_sdk::trace::SpanData) {
+    let matcher = EventMatcher::exception();
+    if let Some(event) = find_matching_event(&span.events.events, &matcher) {
+        panic!(
+            "span {:?} has an exception event with attributes {:?}",
+            span.name, event.attributes
+        );
+    }
+}
+
+/// Panic unless the status of `span` is [`Status::Error`](opentelemetry::trace::Status::Error).
+#[cfg(feature = "testing-sdk")]
+#[track_caller]
+pub fn assert_error_status(span: &opentelemetry_sdk::trace::SpanData) {
+    if !matches!(span.status, opentelemetry::trace::Status::Error { .. }) {
+        panic!(
+            "span {:?} has status {:?}, expected an error",
+            span.name, span.status
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! A [`Span`] recording every call made on it, for unit testing emission
+//! without an SDK pipeline.
+
+use std::{borrow::Cow, time::SystemTime};
+
+use opentelemetry::{
+    KeyValue,
+    trace::{Event, Link, Span, SpanContext, Status},
+};
+
+use crate::utilities::EXCEPTION;
+
+/// Test double for [`Span`], recording events, attributes, statuses and links
+/// in the order they were set instead of exporting them.
+///
+/// It is recording by default, so that the steps of
+/// [`RecordErrorReport`](crate::span_event::RecordErrorReport) take effect.
+///
+/// ```
+/// use rootcause::prelude::*;
+/// use rootcause_opentelemetry::{span_event::SpanReportExt, testing::capturing::CapturingSpan};
+///
+/// let mut span = CapturingSpan::new();
+/// span.record_error_report(&report!("payment declined"))
+///     .as_event()
+///     .with_error_status();
+///
+/// assert_eq!(span.exception_events().count(), 1);
+/// assert!(span.status().is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CapturingSpan {
+    pub span_context: SpanContext,
+    pub recording: bool,
+    pub name: Option<Cow<'static, str>>,
+    pub events: Vec<Event>,
+    pub attributes: Vec<KeyValue>,
+    pub statuses: Vec<Status>,
+    pub links: Vec<Link>,
+    pub ended: Option<SystemTime>,
+}
+
+impl Default for CapturingSpan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CapturingSpan {
+    /// A recording span with an empty span context.
+    pub fn new() -> Self {
+        Self::with_span_context(SpanContext::empty_context())
+    }
+
+    /// A recording span with `span_context`, e.g. to test linking and correlation.
+    pub fn with_span_context(span_context: SpanContext) -> Self {
+        Self {
+            span_context,
+            recording: true,
+            name: None,
+            events: Vec::new(),
+            attributes: Vec::new(),
+            statuses: Vec::new(),
+            links: Vec::new(),
+            ended: None,
+        }
+    }
+
+    /// A span which is not recording, as in an unsampled trace.
+    pub fn not_recording() -> Self {
+        Self {
+            recording: false,
+            ..Self::new()
+        }
+    }
+
+    /// The recorded events named `exception`.
+    pub fn exception_events(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(|event| event.name == EXCEPTION)
+    }
+
+    /// The value of the last attribute set with `key`, if any.
+    pub fn attribute(&self, key: &str) -> Option<&opentelemetry::Value> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| &kv.value)
+    }
+
+    /// The last status set, if any.
+    pub fn status(&self) -> Option<&Status> {
+        self.statuses.last()
+    }
+}
+
+impl Span for CapturingSpan {
+    fn add_event_with_timestamp<T>(
+        &mut self,
+        name: T,
+        timestamp: SystemTime,
+        attributes: Vec<KeyValue>,
+    ) where
+        T: Into<Cow<'static, str>>,
+    {
+        self.events.push(Event::new(name, timestamp, attributes, 0));
+    }
+
+    fn span_context(&self) -> &SpanContext {
+        &self.span_context
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    fn set_attribute(&mut self, attribute: KeyValue) {
+        self.attributes.push(attribute);
+    }
+
+    fn set_status(&mut self, status: Status) {
+        self.statuses.push(status);
+    }
+
+    fn update_name<T>(&mut self, new_name: T)
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        self.name = Some(new_name.into());
+    }
+
+    fn add_link(&mut self, span_context: SpanContext, attributes: Vec<KeyValue>) {
+        self.links.push(Link::new(span_context, attributes, 0));
+    }
+
+    fn end_with_timestamp(&mut self, timestamp: SystemTime) {
+        self.ended = Some(timestamp);
+    }
+}
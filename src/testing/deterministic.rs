@@ -0,0 +1,123 @@
+//! Deterministic ids and timestamps, so that snapshots of emitted telemetry,
+//! e.g. as printed by `opentelemetry_stdout`, are stable across runs.
+//!
+//! [`deterministic_mode`] installs a [`ManualClock`] and sequential
+//! [`ReportId`]s for as long as the returned guard lives, and
+//! [`SequentialIdGenerator`] hands out sequential trace and span ids to a
+//! tracer provider. [`Fingerprint`](crate::fingerprint::Fingerprint)s need no
+//! such treatment, as they only depend on the shape of the report tree and the
+//! registered error types or raw type names of its contexts.
+//!
+//! ```
+//! use std::time::Duration;
+//! use rootcause_opentelemetry::{identity::ReportId, testing::deterministic::deterministic_mode};
+//!
+//! let mode = deterministic_mode();
+//! assert_eq!(ReportId::generate().to_string(), "report-1");
+//! assert_eq!(ReportId::generate().to_string(), "report-2");
+//! mode.clock().advance(Duration::from_secs(1));
+//! ```
+
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    clock::{self, ManualClock},
+    identity::{self, ReportId, ReportIdGenerator},
+};
+
+/// The time the [`ManualClock`] of [`deterministic_mode`] starts at,
+/// 2024-01-01T00:00:00Z.
+pub const DETERMINISTIC_START: Duration = Duration::from_secs(1_704_067_200);
+
+/// Serializes the tests using [`deterministic_mode`], as it replaces the
+/// process-wide clock and id generator.
+static MODE: Mutex<()> = Mutex::new(());
+
+/// Report id generator handing out `report-1`, `report-2`, ….
+///
+/// Clones share the same sequence.
+#[derive(Debug, Clone, Default)]
+pub struct SequentialReportIds {
+    next: Arc<AtomicU64>,
+}
+
+impl ReportIdGenerator for SequentialReportIds {
+    fn generate(&self) -> ReportId {
+        let n = self.next.fetch_add(1, Ordering::Relaxed) + 1;
+        ReportId::custom(format!("report-{n}"))
+    }
+}
+
+/// Trace and span id generator handing out `1`, `2`, … as ids, for
+/// [`TracerProviderBuilder::with_id_generator`](opentelemetry_sdk::trace::TracerProviderBuilder::with_id_generator).
+///
+/// Trace and span ids are numbered independently, and clones share the same
+/// sequences.
+#[cfg(feature = "testing-sdk")]
+#[derive(Debug, Clone, Default)]
+pub struct SequentialIdGenerator {
+    next_trace: Arc<AtomicU64>,
+    next_span: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "testing-sdk")]
+impl opentelemetry_sdk::trace::IdGenerator for SequentialIdGenerator {
+    fn new_trace_id(&self) -> opentelemetry::trace::TraceId {
+        let n = self.next_trace.fetch_add(1, Ordering::Relaxed) + 1;
+        opentelemetry::trace::TraceId::from(u128::from(n))
+    }
+
+    fn new_span_id(&self) -> opentelemetry::trace::SpanId {
+        let n = self.next_span.fetch_add(1, Ordering::Relaxed) + 1;
+        opentelemetry::trace::SpanId::from(n)
+    }
+}
+
+/// Guard returned by [`deterministic_mode`], going back to the system clock
+/// and ULID report ids when dropped.
+pub struct DeterministicMode {
+    clock: ManualClock,
+    _lock: MutexGuard<'static, ()>,
+}
+
+/// Install a [`ManualClock`] starting at [`DETERMINISTIC_START`] and
+/// [`SequentialReportIds`] until the returned guard is dropped.
+///
+/// The clock and id generator are process-wide, so concurrent callers wait
+/// for the previous guard to be dropped.
+pub fn deterministic_mode() -> DeterministicMode {
+    let lock = MODE.lock().unwrap_or_else(|poison| poison.into_inner());
+    let clock = ManualClock::new(SystemTime::UNIX_EPOCH + DETERMINISTIC_START);
+    clock::install_clock(clock.clone());
+    identity::install_id_generator(SequentialReportIds::default());
+    DeterministicMode { clock, _lock: lock }
+}
+
+impl DeterministicMode {
+    /// The installed clock, for advancing it between emissions.
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+}
+
+impl fmt::Debug for DeterministicMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeterministicMode")
+            .field("clock", &self.clock)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DeterministicMode {
+    fn drop(&mut self) {
+        clock::reset_clock();
+        identity::reset_id_generator();
+    }
+}
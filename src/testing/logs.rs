@@ -0,0 +1,120 @@
+//! Assertions on captured log records, mirroring those on span events.
+//!
+//! Records are captured by the logger provider of
+//! [`test_providers`](super::providers::test_providers).
+//!
+//! ```
+//! # use opentelemetry::logs::{LoggerProvider, Severity};
+//! # use rootcause::prelude::*;
+//! # use rootcause_opentelemetry::log_event::LoggerExt;
+//! use rootcause_opentelemetry::testing::{
+//!     EventMatcher, contains,
+//!     logs::{assert_log_matches, assert_log_severity},
+//!     providers::test_providers,
+//! };
+//!
+//! let providers = test_providers();
+//! providers
+//!     .logger_provider
+//!     .logger("test")
+//!     .emit_error_report(&report!("payment declined"));
+//!
+//! let records = providers.log_records();
+//! assert_log_severity(&records[0], Severity::Error);
+//! assert_log_matches(
+//!     &records[0],
+//!     &EventMatcher::exception().attr("exception.message", contains("declined")),
+//! );
+//! ```
+
+use opentelemetry::{
+    KeyValue, Value,
+    logs::{AnyValue, Severity},
+    trace::SpanContext,
+};
+use opentelemetry_sdk::logs::SdkLogRecord;
+
+use super::{EventMatcher, Mismatch};
+
+/// The attributes of `record` as [`KeyValue`]s, for use with [`EventMatcher`]s.
+///
+/// Values without an attribute equivalent, such as maps, are converted to
+/// their debug representation.
+pub fn log_attributes(record: &SdkLogRecord) -> Vec<KeyValue> {
+    record
+        .attributes_iter()
+        .map(|(key, value)| KeyValue::new(key.clone(), attribute_value(value)))
+        .collect()
+}
+
+fn attribute_value(value: &AnyValue) -> Value {
+    match value {
+        AnyValue::Boolean(value) => Value::Bool(*value),
+        AnyValue::Int(value) => Value::I64(*value),
+        AnyValue::Double(value) => Value::F64(*value),
+        AnyValue::String(value) => Value::String(value.clone()),
+        other => Value::from(format!("{other:?}")),
+    }
+}
+
+/// Check `record` against `matcher`, with its event name as the name.
+pub fn check_log(record: &SdkLogRecord, matcher: &EventMatcher) -> Result<(), Mismatch> {
+    matcher.check_parts(
+        Some(record.event_name().unwrap_or_default()),
+        &log_attributes(record),
+    )
+}
+
+/// Panic with a readable description unless `record` satisfies `matcher`.
+#[track_caller]
+pub fn assert_log_matches(record: &SdkLogRecord, matcher: &EventMatcher) {
+    if let Err(mismatch) = check_log(record, matcher) {
+        panic!("{mismatch}");
+    }
+}
+
+/// Panic with a readable description unless one of the captured `records`
+/// satisfies `matcher`.
+#[track_caller]
+pub fn assert_any_log_matches<'r>(
+    records: impl IntoIterator<Item = &'r SdkLogRecord>,
+    matcher: &EventMatcher,
+) {
+    let mut mismatches = Vec::new();
+    for record in records {
+        match check_log(record, matcher) {
+            Ok(()) => return,
+            Err(mismatch) => mismatches.push(mismatch),
+        }
+    }
+    let mut message = format!(
+        "none of {} log records match {matcher:?}\n",
+        mismatches.len()
+    );
+    for mismatch in mismatches {
+        message.push_str(&mismatch.to_string());
+    }
+    panic!("{message}");
+}
+
+/// Panic unless `record` has the severity `severity`.
+#[track_caller]
+pub fn assert_log_severity(record: &SdkLogRecord, severity: Severity) {
+    let actual = record.severity_number();
+    if actual != Some(severity) {
+        panic!("log record has severity {actual:?}, expected {severity:?}");
+    }
+}
+
+/// Panic unless `record` is correlated with the span of `span_context`, i.e.
+/// has its trace and span ids.
+#[track_caller]
+pub fn assert_log_correlated(record: &SdkLogRecord, span_context: &SpanContext) {
+    let actual = record
+        .trace_context()
+        .map(|trace_context| (trace_context.trace_id, trace_context.span_id));
+    let expected = (span_context.trace_id(), span_context.span_id());
+    if actual != Some(expected) {
+        panic!("log record has trace context {actual:?}, expected {expected:?}");
+    }
+}
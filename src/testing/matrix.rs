@@ -0,0 +1,673 @@
+//! Running the same emission scenario against several exporter setups, to
+//! catch regressions specific to one exporter or its limit handling.
+//!
+//! Each [`ExporterAdapter`] builds a fresh tracer provider exporting with one
+//! exporter, e.g. in memory, to stdout or, with the `otlp` feature, over OTLP
+//! to a [`MockOtlpServer`]. The events are decoded from what that exporter
+//! actually wrote or sent, and normalized so that the runs can be compared.
+
+use std::collections::BTreeMap;
+#[cfg(any(unix, feature = "otlp"))]
+use std::io;
+#[cfg(feature = "otlp")]
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use opentelemetry::{
+    Value,
+    global::BoxedTracer,
+    trace::{Event, TracerProvider},
+};
+use opentelemetry_sdk::trace::{
+    InMemorySpanExporter, SdkTracerProvider, SpanExporter, SpanLimits, TracerProviderBuilder,
+};
+
+type SetupFn = Box<dyn Fn(TracerProviderBuilder) -> TracerProviderBuilder>;
+
+/// An exporter setup to run scenarios against, see [`run_matrix`].
+pub struct ExporterAdapter {
+    name: String,
+    setup: SetupFn,
+    output: Output,
+}
+
+/// Where the events of a run are decoded from.
+enum Output {
+    /// The spans handed to the exporter, as captured in memory.
+    InMemory,
+    /// The text the stdout exporter wrote.
+    #[cfg(unix)]
+    Stdout,
+    /// The export requests the server received.
+    #[cfg(feature = "otlp")]
+    Otlp(MockOtlpServer),
+}
+
+impl ExporterAdapter {
+    /// Export to an in-memory capture.
+    pub fn in_memory() -> Self {
+        Self {
+            name: "in-memory".to_owned(),
+            setup: Box::new(|builder| builder),
+            output: Output::InMemory,
+        }
+    }
+
+    /// Export to stdout with [`opentelemetry_stdout::SpanExporter`], decoding
+    /// the events from its output.
+    ///
+    /// The standard output of the process is redirected while the scenario
+    /// runs. The test harness captures what tests print before it reaches the
+    /// standard output, so the tests using this adapter must run with
+    /// `--nocapture`, and the run panics otherwise.
+    #[cfg(unix)]
+    pub fn stdout() -> Self {
+        Self {
+            name: "stdout".to_owned(),
+            setup: Box::new(|builder| {
+                builder.with_simple_exporter(opentelemetry_stdout::SpanExporter::default())
+            }),
+            output: Output::Stdout,
+        }
+    }
+
+    /// Export over OTLP/HTTP with binary protobuf to `server`, decoding the
+    /// events from the requests it received, so that the spans go through the
+    /// encoding of the OTLP exporter.
+    ///
+    /// ```
+    /// use opentelemetry::trace::{Tracer, TraceContextExt};
+    /// use rootcause::prelude::*;
+    /// use rootcause_opentelemetry::{
+    ///     span_event::SpanRefReportExt,
+    ///     testing::matrix::{ExporterAdapter, MockOtlpServer, run_matrix},
+    /// };
+    ///
+    /// let server = MockOtlpServer::start().unwrap();
+    /// let adapters = [ExporterAdapter::in_memory(), ExporterAdapter::mock_otlp(&server)];
+    /// let results = run_matrix(&adapters, |tracer| {
+    ///     tracer.in_span("operation", |cx| {
+    ///         let _ = cx.span().record_error_report(&report!("failed")).as_event();
+    ///     });
+    /// });
+    /// results.assert_consistent();
+    /// assert_eq!(server.exports(), 1);
+    /// ```
+    #[cfg(feature = "otlp")]
+    pub fn mock_otlp(server: &MockOtlpServer) -> Self {
+        Self::otlp(
+            "otlp (mock server)",
+            server,
+            opentelemetry_otlp::Protocol::HttpBinary,
+        )
+    }
+
+    /// As [`Self::mock_otlp`], with the JSON encoding of OTLP/HTTP.
+    #[cfg(feature = "otlp")]
+    pub fn mock_otlp_json(server: &MockOtlpServer) -> Self {
+        Self::otlp(
+            "otlp json (mock server)",
+            server,
+            opentelemetry_otlp::Protocol::HttpJson,
+        )
+    }
+
+    #[cfg(feature = "otlp")]
+    fn otlp(name: &str, server: &MockOtlpServer, protocol: opentelemetry_otlp::Protocol) -> Self {
+        use opentelemetry_otlp::WithExportConfig;
+
+        let endpoint = server.endpoint();
+        Self {
+            name: name.to_owned(),
+            setup: Box::new(move |builder| {
+                builder.with_simple_exporter(
+                    opentelemetry_otlp::SpanExporter::builder()
+                        .with_http()
+                        .with_protocol(protocol)
+                        .with_endpoint(endpoint.clone())
+                        .build()
+                        .expect("OTLP exporter for the mock server"),
+                )
+            }),
+            output: Output::Otlp(server.clone()),
+        }
+    }
+
+    /// Export to a new exporter made by `exporter` for each run as well.
+    ///
+    /// Its output is not decoded: the events are those of the spans handed to
+    /// it, as captured in memory.
+    pub fn with_exporter<E: SpanExporter + 'static>(
+        name: impl Into<String>,
+        exporter: impl Fn() -> E + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            setup: Box::new(move |builder| builder.with_simple_exporter(exporter())),
+            output: Output::InMemory,
+        }
+    }
+
+    /// Apply `limits` to the spans, as the SDK would when configured with them.
+    pub fn span_limits(mut self, limits: SpanLimits) -> Self {
+        let setup = self.setup;
+        self.setup = Box::new(move |builder| setup(builder).with_span_limits(limits));
+        self.name = format!("{} (limited)", self.name);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run `scenario` with a tracer of a fresh provider, returning the events
+    /// of the exported spans.
+    pub fn run(&self, scenario: &dyn Fn(&BoxedTracer)) -> Vec<NormalizedEvent> {
+        // The spans are captured in memory for every adapter, to tell whether
+        // the exporter had anything to export.
+        let capture = InMemorySpanExporter::default();
+        let provider: SdkTracerProvider =
+            (self.setup)(SdkTracerProvider::builder().with_simple_exporter(capture.clone()))
+                .build();
+        let tracer = BoxedTracer::new(Box::new(provider.tracer("rootcause-opentelemetry-matrix")));
+
+        #[cfg(unix)]
+        let stdout = matches!(self.output, Output::Stdout)
+            .then(|| StdoutCapture::start().expect("redirecting stdout"));
+        scenario(&tracer);
+        let _ = provider.force_flush();
+        let spans = capture.get_finished_spans().unwrap_or_default();
+        let _ = provider.shutdown();
+
+        match &self.output {
+            Output::InMemory => spans
+                .iter()
+                .flat_map(|span| span.events.iter())
+                .map(NormalizedEvent::from)
+                .collect(),
+            #[cfg(unix)]
+            Output::Stdout => {
+                let output = stdout
+                    .expect("stdout is captured for the stdout adapter")
+                    .finish()
+                    .expect("reading the captured stdout");
+                assert!(
+                    spans.is_empty() || output.contains("Span #"),
+                    "the stdout exporter's output did not reach the standard output, \
+                     run the tests with `--nocapture`"
+                );
+                parse_stdout(&output)
+            }
+            #[cfg(feature = "otlp")]
+            Output::Otlp(server) => server.take_events(),
+        }
+    }
+}
+
+/// The standard output of the process redirected to a temporary file, until
+/// dropped.
+#[cfg(unix)]
+struct StdoutCapture {
+    saved: std::os::fd::OwnedFd,
+    file: std::fs::File,
+}
+
+#[cfg(unix)]
+impl StdoutCapture {
+    fn start() -> io::Result<Self> {
+        use std::{
+            io::Write,
+            os::fd::{AsRawFd, FromRawFd},
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        static CAPTURES: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "rootcause-opentelemetry-stdout-{}-{}",
+            std::process::id(),
+            CAPTURES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let _ = std::fs::remove_file(&path);
+
+        io::stdout().flush()?;
+        // SAFETY: `dup` has no preconditions, and the descriptor it returns is
+        // owned by nothing else.
+        let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if saved < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let saved = unsafe { std::os::fd::OwnedFd::from_raw_fd(saved) };
+        // SAFETY: both descriptors are open.
+        if unsafe { libc::dup2(file.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { saved, file })
+    }
+
+    /// Restore the standard output, returning what was written to it.
+    fn finish(mut self) -> io::Result<String> {
+        use std::io::{Seek, SeekFrom};
+
+        self.restore();
+        let mut output = String::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        io::Read::read_to_string(&mut self.file, &mut output)?;
+        Ok(output)
+    }
+
+    fn restore(&self) {
+        use std::{io::Write, os::fd::AsRawFd};
+
+        let _ = io::stdout().flush();
+        // SAFETY: both descriptors are open.
+        unsafe { libc::dup2(self.saved.as_raw_fd(), libc::STDOUT_FILENO) };
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StdoutCapture {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// The events in the human-readable output of the stdout exporter, which
+/// prints each event as an `Event #<index>` line followed by its `Name` and
+/// `Timestamp` lines and one `->  <key>: <value>` line per attribute, with the
+/// value in its debug representation.
+#[cfg(unix)]
+fn parse_stdout(output: &str) -> Vec<NormalizedEvent> {
+    let mut events: Vec<NormalizedEvent> = Vec::new();
+    let mut in_event = false;
+    for line in output.lines().map(str::trim) {
+        if line.starts_with("Span #") || line.starts_with("Link") {
+            in_event = false;
+        } else if line.starts_with("Event #") {
+            in_event = true;
+            events.push(NormalizedEvent {
+                name: String::new(),
+                attributes: BTreeMap::new(),
+                dropped_attributes_count: None,
+            });
+        } else if !in_event {
+            continue;
+        } else if let Some(event) = events.last_mut() {
+            if let Some(name) = line
+                .strip_prefix("Name")
+                .and_then(|rest| rest.trim_start().strip_prefix(':'))
+            {
+                event.name = name.trim().to_owned();
+            } else if let Some((key, value)) = line
+                .strip_prefix("->")
+                .and_then(|attribute| attribute.trim_start().split_once(": "))
+            {
+                event
+                    .attributes
+                    .insert(key.to_owned(), strip_string_representation(value));
+            }
+        }
+    }
+    events
+}
+
+/// A minimal OTLP/HTTP collector on a local port, decoding and acknowledging
+/// every export, for [`ExporterAdapter::mock_otlp`].
+#[cfg(feature = "otlp")]
+#[derive(Debug, Clone)]
+pub struct MockOtlpServer {
+    address: SocketAddr,
+    exports: Arc<AtomicUsize>,
+    events: Arc<Mutex<Vec<NormalizedEvent>>>,
+}
+
+#[cfg(feature = "otlp")]
+impl MockOtlpServer {
+    /// Listen on a free local port, answering on a background thread for the
+    /// rest of the process.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let server = Self {
+            address,
+            exports: Arc::new(AtomicUsize::new(0)),
+            events: Arc::new(Mutex::new(Vec::new())),
+        };
+        let serving = server.clone();
+        std::thread::Builder::new()
+            .name("mock-otlp-server".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = serving.serve(stream);
+                }
+            })?;
+        Ok(server)
+    }
+
+    /// The URL to export spans to.
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/v1/traces", self.address)
+    }
+
+    /// Number of export requests received so far.
+    pub fn exports(&self) -> usize {
+        self.exports.load(Ordering::Relaxed)
+    }
+
+    /// The events of the spans received since the last call, in order of receipt.
+    pub fn take_events(&self) -> Vec<NormalizedEvent> {
+        std::mem::take(
+            &mut *self
+                .events
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner()),
+        )
+    }
+
+    /// Answer the requests on `stream` with empty successful export responses
+    /// until the client closes it, keeping the events of the decoded requests.
+    fn serve(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let mut content_length = 0;
+            let mut json = false;
+            loop {
+                line.clear();
+                reader.read_line(&mut line)?;
+                let header = line.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                let Some((name, value)) = header.split_once(':') else {
+                    continue;
+                };
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                } else if name.eq_ignore_ascii_case("content-type") {
+                    json = value.trim().starts_with("application/json");
+                }
+            }
+            let mut body = Vec::with_capacity(content_length);
+            (&mut reader)
+                .take(content_length as u64)
+                .read_to_end(&mut body)?;
+
+            // Decoded before answering, so that the events are in place once
+            // the export returns.
+            let events = decode_export(&body, json);
+            self.events
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .extend(events.unwrap_or_default());
+            self.exports.fetch_add(1, Ordering::Relaxed);
+
+            let response: &[u8] = if json {
+                b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\n\r\n{}"
+            } else {
+                b"HTTP/1.1 200 OK\r\ncontent-type: application/x-protobuf\r\ncontent-length: 0\r\n\r\n"
+            };
+            writer.write_all(response)?;
+        }
+    }
+}
+
+/// The events of an OTLP trace export request body, or `None` if it does not
+/// decode.
+#[cfg(feature = "otlp")]
+fn decode_export(body: &[u8], json: bool) -> Option<Vec<NormalizedEvent>> {
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use prost::Message;
+
+    let request: ExportTraceServiceRequest = if json {
+        serde_json::from_slice(body).ok()?
+    } else {
+        ExportTraceServiceRequest::decode(body).ok()?
+    };
+    Some(
+        request
+            .resource_spans
+            .iter()
+            .flat_map(|resource| &resource.scope_spans)
+            .flat_map(|scope| &scope.spans)
+            .flat_map(|span| &span.events)
+            .map(|event| NormalizedEvent {
+                name: event.name.clone(),
+                attributes: event
+                    .attributes
+                    .iter()
+                    .map(|kv| {
+                        let value = kv.value.as_ref().and_then(proto_value);
+                        let value = value.map(|value| normalize_value(&value));
+                        (kv.key.clone(), value.unwrap_or_default())
+                    })
+                    .collect(),
+                dropped_attributes_count: Some(event.dropped_attributes_count),
+            })
+            .collect(),
+    )
+}
+
+/// The attribute value an OTLP value was encoded from.
+#[cfg(feature = "otlp")]
+fn proto_value(value: &opentelemetry_proto::tonic::common::v1::AnyValue) -> Option<Value> {
+    use opentelemetry::{Array, StringValue};
+    use opentelemetry_proto::tonic::common::v1::any_value::Value as Proto;
+
+    Some(match value.value.as_ref()? {
+        Proto::StringValue(value) => Value::from(value.clone()),
+        Proto::BoolValue(value) => Value::Bool(*value),
+        Proto::IntValue(value) => Value::I64(*value),
+        Proto::DoubleValue(value) => Value::F64(*value),
+        Proto::ArrayValue(array) => {
+            let values = array.values.iter().filter_map(|value| value.value.as_ref());
+            Value::Array(
+                match array.values.first().and_then(|value| value.value.as_ref()) {
+                    Some(Proto::BoolValue(_)) => Array::Bool(
+                        values
+                            .filter_map(|value| match value {
+                                Proto::BoolValue(value) => Some(*value),
+                                _ => None,
+                            })
+                            .collect(),
+                    ),
+                    Some(Proto::IntValue(_)) => Array::I64(
+                        values
+                            .filter_map(|value| match value {
+                                Proto::IntValue(value) => Some(*value),
+                                _ => None,
+                            })
+                            .collect(),
+                    ),
+                    Some(Proto::DoubleValue(_)) => Array::F64(
+                        values
+                            .filter_map(|value| match value {
+                                Proto::DoubleValue(value) => Some(*value),
+                                _ => None,
+                            })
+                            .collect(),
+                    ),
+                    _ => Array::String(
+                        values
+                            .filter_map(|value| match value {
+                                Proto::StringValue(value) => Some(StringValue::from(value.clone())),
+                                _ => None,
+                            })
+                            .collect(),
+                    ),
+                },
+            )
+        }
+        Proto::KvlistValue(_) | Proto::BytesValue(_) => return None,
+    })
+}
+
+/// The debug representation of `value`, without how its strings are stored.
+fn normalize_value(value: &Value) -> String {
+    strip_string_representation(&format!("{value:?}"))
+}
+
+/// Remove the `Owned(…)`, `Static(…)` and `RefCounted(…)` wrappers around the
+/// string literals of the debug representation of a [`Value`], which tell how
+/// the strings are stored rather than what they are.
+fn strip_string_representation(debug: &str) -> String {
+    const WRAPPERS: [&str; 3] = ["Owned(", "Static(", "RefCounted("];
+
+    let mut stripped = String::with_capacity(debug.len());
+    let mut rest = debug;
+    let mut unwrapping = false;
+    while let Some(c) = rest.chars().next() {
+        if c == '"' {
+            // Copy the string literal, with its escapes, as is.
+            let mut escaped = false;
+            let end = rest[1..]
+                .char_indices()
+                .find(|&(_, c)| {
+                    let end = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    end
+                })
+                .map_or(rest.len(), |(index, _)| index + 2);
+            stripped.push_str(&rest[..end]);
+            rest = &rest[end..];
+            if unwrapping && rest.starts_with(')') {
+                rest = &rest[1..];
+                unwrapping = false;
+            }
+        } else if let Some(wrapper) = WRAPPERS.iter().find(|wrapper| rest.starts_with(**wrapper)) {
+            rest = &rest[wrapper.len()..];
+            unwrapping = true;
+        } else {
+            stripped.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    stripped
+}
+
+/// A span event with its timestamp removed and attributes in key order, so
+/// that events from different runs compare equal if they carry the same data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedEvent {
+    pub name: String,
+    /// Attribute values by key, in the debug representation of their [`Value`],
+    /// without how their strings are stored, e.g. `String("failed")`.
+    pub attributes: BTreeMap<String, String>,
+    /// The number of attributes dropped by the span limits, or `None` if the
+    /// exporter's output does not tell, as for stdout.
+    pub dropped_attributes_count: Option<u32>,
+}
+
+impl NormalizedEvent {
+    /// Whether `self` and `other` carry the same data, as far as both tell.
+    fn matches(&self, other: &Self) -> bool {
+        let dropped = match (
+            self.dropped_attributes_count,
+            other.dropped_attributes_count,
+        ) {
+            (Some(count), Some(other)) => count == other,
+            _ => true,
+        };
+        self.name == other.name && self.attributes == other.attributes && dropped
+    }
+}
+
+impl From<&Event> for NormalizedEvent {
+    fn from(event: &Event) -> Self {
+        Self {
+            name: event.name.to_string(),
+            attributes: event
+                .attributes
+                .iter()
+                .map(|kv| (kv.key.to_string(), normalize_value(&kv.value)))
+                .collect(),
+            dropped_attributes_count: Some(event.dropped_attributes_count),
+        }
+    }
+}
+
+/// The events captured by each adapter for one scenario.
+#[derive(Debug, Clone)]
+pub struct MatrixResults {
+    /// The adapter name and its captured events, in adapter order.
+    pub runs: Vec<(String, Vec<NormalizedEvent>)>,
+}
+
+impl MatrixResults {
+    /// One line per difference between the first run and each of the others.
+    pub fn differences(&self) -> Vec<String> {
+        let Some(((baseline_name, baseline), others)) = self.runs.split_first() else {
+            return Vec::new();
+        };
+
+        let mut differences = Vec::new();
+        for (name, events) in others {
+            if events.len() != baseline.len() {
+                differences.push(format!(
+                    "{name}: {} events, {baseline_name}: {} events",
+                    events.len(),
+                    baseline.len()
+                ));
+            }
+            for (index, (event, expected)) in events.iter().zip(baseline).enumerate() {
+                if !event.matches(expected) {
+                    differences.push(format!(
+                        "{name}: event {index} is {event:?}, {baseline_name}: {expected:?}"
+                    ));
+                }
+            }
+        }
+        differences
+    }
+
+    /// Panic with the [differences](Self::differences) unless all runs captured the same events.
+    #[track_caller]
+    pub fn assert_consistent(&self) {
+        let differences = self.differences();
+        if !differences.is_empty() {
+            panic!("exporter runs differ:\n  {}", differences.join("\n  "));
+        }
+    }
+}
+
+/// Run `scenario` against each of `adapters`.
+///
+/// ```
+/// use opentelemetry::trace::{Tracer, TraceContextExt};
+/// use rootcause::prelude::*;
+/// use rootcause_opentelemetry::{
+///     span_event::SpanRefReportExt,
+///     testing::matrix::{ExporterAdapter, run_matrix},
+/// };
+///
+/// let results = run_matrix(&[ExporterAdapter::in_memory()], |tracer| {
+///     tracer.in_span("operation", |cx| {
+///         let _ = cx.span().record_error_report(&report!("failed")).as_event();
+///     });
+/// });
+/// results.assert_consistent();
+/// ```
+pub fn run_matrix(adapters: &[ExporterAdapter], scenario: impl Fn(&BoxedTracer)) -> MatrixResults {
+    MatrixResults {
+        runs: adapters
+            .iter()
+            .map(|adapter| (adapter.name.clone(), adapter.run(&scenario)))
+            .collect(),
+    }
+}
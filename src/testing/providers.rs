@@ -0,0 +1,100 @@
+//! Tracer and logger providers exporting to memory, for asserting on what
+//! this crate emitted without scraping stdout.
+//!
+//! ```
+//! use opentelemetry::trace::{Tracer, TracerProvider};
+//! use rootcause::prelude::*;
+//! use rootcause_opentelemetry::{span_event::SpanReportExt, testing::providers::test_providers};
+//!
+//! let providers = test_providers();
+//! let tracer = providers.tracer_provider.tracer("test");
+//! let mut span = tracer.start("checkout");
+//! span.record_error_report(&report!("payment declined")).as_event();
+//! drop(span);
+//!
+//! assert_eq!(providers.exception_events().len(), 1);
+//! ```
+
+use opentelemetry::trace::Event;
+use opentelemetry_sdk::trace::{
+    InMemorySpanExporter, SdkTracerProvider, SpanData, TracerProviderBuilder,
+};
+
+use crate::{testing::deterministic::SequentialIdGenerator, utilities::EXCEPTION};
+
+/// Providers exporting each span and log record to memory as soon as it ends
+/// or is emitted, see [`test_providers`].
+///
+/// The providers are not installed globally, so that tests can run in parallel.
+#[derive(Clone)]
+pub struct TestProviders {
+    pub tracer_provider: SdkTracerProvider,
+    #[cfg(feature = "logs")]
+    pub logger_provider: opentelemetry_sdk::logs::SdkLoggerProvider,
+    span_exporter: InMemorySpanExporter,
+    #[cfg(feature = "logs")]
+    log_exporter: opentelemetry_sdk::logs::InMemoryLogExporter,
+}
+
+/// New [`TestProviders`] with nothing captured yet.
+pub fn test_providers() -> TestProviders {
+    build(SdkTracerProvider::builder())
+}
+
+/// New [`TestProviders`] whose tracer provider numbers trace and span ids
+/// sequentially, see [`deterministic_mode`](super::deterministic::deterministic_mode).
+pub fn deterministic_test_providers() -> TestProviders {
+    build(SdkTracerProvider::builder().with_id_generator(SequentialIdGenerator::default()))
+}
+
+fn build(tracer_provider: TracerProviderBuilder) -> TestProviders {
+    let span_exporter = InMemorySpanExporter::default();
+    #[cfg(feature = "logs")]
+    let log_exporter = opentelemetry_sdk::logs::InMemoryLogExporter::default();
+    TestProviders {
+        tracer_provider: tracer_provider
+            .with_simple_exporter(span_exporter.clone())
+            .build(),
+        #[cfg(feature = "logs")]
+        logger_provider: opentelemetry_sdk::logs::SdkLoggerProvider::builder()
+            .with_simple_exporter(log_exporter.clone())
+            .build(),
+        span_exporter,
+        #[cfg(feature = "logs")]
+        log_exporter,
+    }
+}
+
+impl TestProviders {
+    /// The spans which have ended so far.
+    pub fn finished_spans(&self) -> Vec<SpanData> {
+        self.span_exporter.get_finished_spans().unwrap_or_default()
+    }
+
+    /// The `exception` events of the spans which have ended so far.
+    pub fn exception_events(&self) -> Vec<Event> {
+        self.finished_spans()
+            .into_iter()
+            .flat_map(|span| span.events.events)
+            .filter(|event| event.name == EXCEPTION)
+            .collect()
+    }
+
+    /// The log records emitted so far.
+    #[cfg(feature = "logs")]
+    pub fn log_records(&self) -> Vec<opentelemetry_sdk::logs::SdkLogRecord> {
+        self.log_exporter
+            .get_emitted_logs()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|log| log.record)
+            .collect()
+    }
+
+    /// Forget everything captured so far.
+    pub fn reset(&self) {
+        self.span_exporter.reset();
+        #[cfg(feature = "logs")]
+        self.log_exporter.reset();
+    }
+}
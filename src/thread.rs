@@ -0,0 +1,98 @@
+//! Thread and tokio task metadata captured at report creation.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+use opentelemetry::KeyValue;
+use opentelemetry_semantic_conventions::attribute;
+use rootcause::{
+    ReportMut,
+    hooks::report_creation::ReportCreationHook,
+    markers::{Dynamic, Local, SendSync},
+};
+
+use crate::attachments::Hidden;
+
+#[cfg(feature = "tokio-task")]
+pub const TOKIO_TASK_ID: &str = "tokio.task.id";
+
+/// The thread, and with the `tokio-task` feature the tokio task, a report was created on.
+///
+/// Emitted as the `thread.id`, `thread.name` and `tokio.task.id` attributes on
+/// exception events and log records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadSnapshot {
+    /// Numeric identifier of the thread, assigned by this crate on first use
+    /// and unique for the lifetime of the process.
+    pub id: Option<u64>,
+    /// Name of the thread, if it has one.
+    pub name: Option<String>,
+    /// Identifier of the tokio task, if created within one.
+    #[cfg(feature = "tokio-task")]
+    pub task_id: Option<tokio::task::Id>,
+}
+
+impl ThreadSnapshot {
+    /// Capture the metadata of the current thread and task.
+    pub fn capture() -> Self {
+        let current = thread::current();
+        Self {
+            id: Some(thread_id()),
+            name: current.name().map(str::to_owned),
+            #[cfg(feature = "tokio-task")]
+            task_id: tokio::task::try_id(),
+        }
+    }
+
+    pub(crate) fn attributes(&self) -> Vec<KeyValue> {
+        let mut attributes = Vec::new();
+        if let Some(id) = self.id {
+            attributes.push(KeyValue::new(attribute::THREAD_ID, id as i64));
+        }
+        if let Some(name) = &self.name {
+            attributes.push(KeyValue::new(attribute::THREAD_NAME, name.clone()));
+        }
+        #[cfg(feature = "tokio-task")]
+        if let Some(task_id) = self.task_id {
+            attributes.push(KeyValue::new(TOKIO_TASK_ID, task_id.to_string()));
+        }
+        attributes
+    }
+}
+
+/// Identifier of the current thread, from a process-wide counter.
+///
+/// `ThreadId::as_u64` is unstable and its `Debug` representation is not a
+/// stable interface, so threads are numbered here instead.
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    ID.with(|id| *id)
+}
+
+/// Report creation hook attaching a [`ThreadSnapshot`] to new reports, for
+/// diagnosing which worker produced an error.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThreadCollector {
+    _priv: (),
+}
+
+impl ThreadCollector {
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl ReportCreationHook for ThreadCollector {
+    fn on_local_creation(&self, report: ReportMut<'_, Dynamic, Local>) {
+        let _ = report.attach_custom::<Hidden, _>(ThreadSnapshot::capture());
+    }
+
+    fn on_sendsync_creation(&self, report: ReportMut<'_, Dynamic, SendSync>) {
+        let _ = report.attach_custom::<Hidden, _>(ThreadSnapshot::capture());
+    }
+}
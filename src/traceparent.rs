@@ -0,0 +1,169 @@
+//! Carrying the span context of a report across channels and process
+//! boundaries as W3C [`traceparent`/`tracestate`](https://www.w3.org/TR/trace-context/)
+//! strings.
+//!
+//! The sending side attaches a [`TraceParent`] before serializing the report,
+//! and the receiving side parses it back into a [`RemoteSpanContext`]
+//! attachment, which takes precedence over the span context the report was
+//! created in, so that [`link_child_report_spans`](crate::span_event::RecordErrorReport::link_child_report_spans)
+//! links to the span the error originated in.
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use opentelemetry::{
+    Context,
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+};
+use rootcause::{
+    Report,
+    markers::{Mutable, ObjectMarkerFor},
+};
+
+use crate::{attachments::Hidden, utilities::AttachmentsExt};
+
+/// The serialized span context of a report, as `traceparent` and `tracestate`
+/// header values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceParent {
+    pub traceparent: String,
+    pub tracestate: String,
+}
+
+impl TraceParent {
+    /// Serialize `span_context`.
+    pub fn of(span_context: &SpanContext) -> Self {
+        Self {
+            traceparent: format!(
+                "00-{:032x}-{:016x}-{:02x}",
+                span_context.trace_id(),
+                span_context.span_id(),
+                span_context.trace_flags(),
+            ),
+            tracestate: span_context.trace_state().header(),
+        }
+    }
+
+    /// Parse the span context back, marked as remote, or `None` if the
+    /// `traceparent` is malformed or describes an invalid span context.
+    ///
+    /// A malformed `tracestate` is discarded, as the W3C specification requires.
+    ///
+    /// ```
+    /// use rootcause_opentelemetry::traceparent::TraceParent;
+    ///
+    /// let parent = TraceParent {
+    ///     traceparent: "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".into(),
+    ///     tracestate: "vendor=value".into(),
+    /// };
+    /// let span_context = parent.to_span_context().unwrap();
+    /// assert!(span_context.is_remote());
+    /// assert_eq!(TraceParent::of(&span_context), parent);
+    /// ```
+    pub fn to_span_context(&self) -> Option<SpanContext> {
+        let mut parts = self.traceparent.trim().split('-');
+        let (Some(version), Some(trace_id), Some(span_id), Some(flags)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        // Later versions may append fields, version 00 must not.
+        if version.len() != 2
+            || version == "ff"
+            || (version == "00" && parts.next().is_some())
+            || trace_id.len() != 32
+            || span_id.len() != 16
+            || flags.len() != 2
+        {
+            return None;
+        }
+        u8::from_str_radix(version, 16).ok()?;
+
+        let span_context = SpanContext::new(
+            TraceId::from_hex(trace_id).ok()?,
+            SpanId::from_hex(span_id).ok()?,
+            TraceFlags::new(u8::from_str_radix(flags, 16).ok()? & TraceFlags::SAMPLED.to_u8()),
+            true,
+            TraceState::from_str(&self.tracestate).unwrap_or_default(),
+        );
+        span_context.is_valid().then_some(span_context)
+    }
+}
+
+/// The span context a report received from elsewhere originated in, as
+/// described by its [`TraceParent`].
+///
+/// It takes precedence over the span context attached when the report was
+/// created on the receiving side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSpanContext(pub SpanContext);
+
+impl Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.traceparent)?;
+        if !self.tracestate.is_empty() {
+            write!(f, " ({})", self.tracestate)?;
+        }
+        Ok(())
+    }
+}
+
+/// Extension trait for [`Report`]s, for carrying their span context across
+/// process boundaries.
+pub trait TraceParentReportExt: Sized {
+    /// Attach a [`TraceParent`] serializing the span context the report
+    /// originated in, or the one of the current span if there is none.
+    ///
+    /// Nothing is attached if neither is valid.
+    fn attach_traceparent(self) -> Self;
+
+    /// Parse the `traceparent` and `tracestate` received alongside the report
+    /// and attach the span context they describe as a [`RemoteSpanContext`].
+    ///
+    /// Nothing is attached if the `traceparent` is malformed.
+    fn attach_remote_span_context(self, traceparent: &str, tracestate: &str) -> Self;
+
+    /// Parse the [`TraceParent`] attached to the report, e.g. on the receiving
+    /// end of a channel, into a [`RemoteSpanContext`] attachment.
+    fn resolve_traceparent(self) -> Self;
+}
+
+impl<C: ?Sized, T: 'static> TraceParentReportExt for Report<C, Mutable, T>
+where
+    TraceParent: ObjectMarkerFor<T>,
+    RemoteSpanContext: ObjectMarkerFor<T>,
+{
+    fn attach_traceparent(self) -> Self {
+        let span_context = crate::correlation::span_context(self.attachments())
+            .cloned()
+            .unwrap_or_else(|| Context::current().span().span_context().clone());
+        if !span_context.is_valid() {
+            return self;
+        }
+        self.attach_custom::<Hidden, _>(TraceParent::of(&span_context))
+    }
+
+    fn attach_remote_span_context(self, traceparent: &str, tracestate: &str) -> Self {
+        let parent = TraceParent {
+            traceparent: traceparent.into(),
+            tracestate: tracestate.into(),
+        };
+        match parent.to_span_context() {
+            Some(span_context) => self.attach_custom::<Hidden, _>(RemoteSpanContext(span_context)),
+            None => self,
+        }
+    }
+
+    fn resolve_traceparent(self) -> Self {
+        match self
+            .attachments()
+            .find_attachment_inner::<TraceParent>()
+            .and_then(TraceParent::to_span_context)
+        {
+            Some(span_context) => self.attach_custom::<Hidden, _>(RemoteSpanContext(span_context)),
+            None => self,
+        }
+    }
+}
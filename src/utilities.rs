@@ -1,6 +1,16 @@
-use std::time::SystemTime;
+use std::{
+    fmt::Write,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
 
-use opentelemetry::KeyValue;
+use opentelemetry::{
+    Array, Context, KeyValue, StringValue, Value,
+    baggage::BaggageExt,
+    trace::{SpanContext, TraceId},
+};
 use opentelemetry_semantic_conventions::attribute;
 use rootcause::{
     Report, ReportMut, ReportRef,
@@ -9,10 +19,29 @@ use rootcause::{
     report_attachments::ReportAttachments,
 };
 
-pub const EXCEPTION: &'static str = "exception";
+use crate::{
+    attachments::AttributeGroup,
+    panic::without_panic_recording,
+    sampling::Decision,
+    spec::{ExceptionEventSpec, MessageLines, MessageSource, PrimitiveAttachment, SemconvProfile},
+};
+
+pub const EXCEPTION: &str = "exception";
+pub const EXCEPTION_DROPPED_LINK_COUNT: &str = "exception.dropped_link_count";
+pub const EXCEPTION_DROPPED_ATTRIBUTE_COUNT: &str = "exception.dropped_attribute_count";
+pub const EXCEPTION_REPORT_AGE_MS: &str = "exception.report_age_ms";
+pub const EXCEPTION_MESSAGE_OVERFLOW: &str = "exception.message_overflow";
+pub const FORWARD_TO_TRACE_IDS: &str = "rootcause.forward_to_trace_ids";
+pub const PROCESS_ENVIRONMENT_VARIABLE: &str = "process.environment_variable";
+pub const EXCEPTION_ESCAPED: &str = "exception.escaped";
+pub const ERROR_MESSAGE: &str = "error.message";
 
 /// Trait for getting the most general type of [`ReportRef`] from
 /// anything [`Report`]-related.
+///
+/// Implemented for reports and report references of any marker combination,
+/// and for references and smart pointers to those, so that the entry points
+/// taking an `impl AsReportRef` need no marker conversions by the caller.
 pub trait AsReportRef {
     /// Get a universally-obtainable [`ReportRef`].
     fn as_report_ref(&self) -> ReportRef<'_, Dynamic, Uncloneable, Local>;
@@ -36,33 +65,522 @@ impl<'a, C: ?Sized, T> AsReportRef for ReportMut<'a, C, T> {
     }
 }
 
-pub(crate) fn attributes_brief(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<KeyValue> {
-    let rep = rep.as_report_ref();
-    vec![
-        KeyValue::new(attribute::EXCEPTION_TYPE, rep.current_context_type_name()),
-        KeyValue::new(
-            attribute::EXCEPTION_MESSAGE,
-            rep.format_current_context().to_string(),
-        ),
-    ]
+impl<R: AsReportRef + ?Sized> AsReportRef for &R {
+    fn as_report_ref(&self) -> ReportRef<'_, Dynamic, Uncloneable, Local> {
+        (**self).as_report_ref()
+    }
+}
+
+impl<R: AsReportRef + ?Sized> AsReportRef for &mut R {
+    fn as_report_ref(&self) -> ReportRef<'_, Dynamic, Uncloneable, Local> {
+        (**self).as_report_ref()
+    }
+}
+
+impl<R: AsReportRef + ?Sized> AsReportRef for Box<R> {
+    fn as_report_ref(&self) -> ReportRef<'_, Dynamic, Uncloneable, Local> {
+        (**self).as_report_ref()
+    }
 }
 
-pub(crate) fn attributes(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<KeyValue> {
-    let rep = rep.as_report_ref();
+impl<R: AsReportRef + ?Sized> AsReportRef for Rc<R> {
+    fn as_report_ref(&self) -> ReportRef<'_, Dynamic, Uncloneable, Local> {
+        (**self).as_report_ref()
+    }
+}
+
+impl<R: AsReportRef + ?Sized> AsReportRef for Arc<R> {
+    fn as_report_ref(&self) -> ReportRef<'_, Dynamic, Uncloneable, Local> {
+        (**self).as_report_ref()
+    }
+}
+
+/// `exception.type` and `exception.message`, as used on span links.
+pub(crate) fn type_and_message(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<KeyValue> {
     vec![
-        KeyValue::new(attribute::EXCEPTION_TYPE, rep.current_context_type_name()),
+        KeyValue::new(attribute::EXCEPTION_TYPE, crate::error_type::type_name(rep)),
         KeyValue::new(
             attribute::EXCEPTION_MESSAGE,
-            rep.format_current_context().to_string(),
+            format_contained(rep.current_context_type_name(), || {
+                rep.format_current_context().to_string()
+            }),
         ),
-        KeyValue::new(attribute::EXCEPTION_STACKTRACE, rep.to_string()),
     ]
 }
 
+pub(crate) fn attributes_brief(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> Vec<KeyValue> {
+    let mut attributes = type_and_message(rep);
+    message_from_source(rep, &mut attributes, &spec.message_source);
+    split_message(&mut attributes, spec.message_lines);
+    if let Some(error_type) = crate::error_type::registered_error_type(rep) {
+        attributes.push(KeyValue::new(attribute::ERROR_TYPE, error_type));
+    }
+    attributes.extend(crate::error_type::registered_attributes(rep));
+    spec_attributes(rep, spec, &mut attributes);
+    apply_profile(&mut attributes, spec.semconv_profile);
+    attributes
+}
+
+pub(crate) fn attributes(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+) -> Vec<KeyValue> {
+    let mut stacktrace = format_report(rep);
+    if let Some(limit) = spec.max_stacktrace_len {
+        truncate_middle(&mut stacktrace, limit);
+    }
+    let mut attributes = type_and_message(rep);
+    attributes.push(KeyValue::new(attribute::EXCEPTION_STACKTRACE, stacktrace));
+    message_from_source(rep, &mut attributes, &spec.message_source);
+    split_message(&mut attributes, spec.message_lines);
+    if let Some(error_type) = crate::error_type::registered_error_type(rep) {
+        attributes.push(KeyValue::new(attribute::ERROR_TYPE, error_type));
+    }
+    attributes.extend(crate::error_type::registered_attributes(rep));
+    spec_attributes(rep, spec, &mut attributes);
+    apply_profile(&mut attributes, spec.semconv_profile);
+    attributes
+}
+
+/// Replace the `exception.message` as [`ExceptionEventSpec::message_chain`]
+/// and [`ExceptionEventSpec::message_from_root`] require.
+fn message_from_source(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    attributes: &mut [KeyValue],
+    source: &MessageSource,
+) {
+    let message = |rep: ReportRef<'_, Dynamic, Uncloneable, Local>| {
+        format_contained(rep.current_context_type_name(), || {
+            rep.format_current_context().to_string()
+        })
+    };
+    let chain = std::iter::successors(Some(rep), |rep| {
+        rep.children()
+            .iter()
+            .next()
+            .map(|child| child.into_uncloneable())
+    });
+    let text = match source {
+        MessageSource::Current => return,
+        MessageSource::Root => match chain.last() {
+            Some(root) => message(root),
+            None => return,
+        },
+        MessageSource::Chain(separator) => chain.map(message).collect::<Vec<_>>().join(separator),
+    };
+    if let Some(kv) = attributes
+        .iter_mut()
+        .find(|kv| kv.key.as_str() == attribute::EXCEPTION_MESSAGE)
+    {
+        kv.value = text.into();
+    }
+}
+
+/// Apply [`ExceptionEventSpec::message_lines`] to the `exception.message` attribute,
+/// replacing any previous `exception.message_overflow` attribute.
+pub(crate) fn split_message(attributes: &mut Vec<KeyValue>, policy: MessageLines) {
+    attributes.retain(|kv| kv.key.as_str() != EXCEPTION_MESSAGE_OVERFLOW);
+    let Some(message) = attributes.iter_mut().find(|kv| {
+        matches!(
+            kv.key.as_str(),
+            attribute::EXCEPTION_MESSAGE | ERROR_MESSAGE
+        )
+    }) else {
+        return;
+    };
+    let text = message.value.as_str().into_owned();
+    let Some((first, rest)) = text.split_once('\n') else {
+        return;
+    };
+
+    match policy {
+        MessageLines::Keep => {}
+        MessageLines::Collapse => {
+            message.value = text.lines().collect::<Vec<_>>().join(" ⏎ ").into();
+        }
+        MessageLines::Overflow => {
+            message.value = first.trim_end_matches('\r').to_owned().into();
+            attributes.push(KeyValue::new(EXCEPTION_MESSAGE_OVERFLOW, rest.to_owned()));
+        }
+    }
+}
+
+/// Rename the attributes as [`ExceptionEventSpec::semconv_profile`] requires,
+/// keeping their order.
+#[allow(deprecated)]
+fn apply_profile(attributes: &mut Vec<KeyValue>, profile: SemconvProfile) {
+    match profile {
+        SemconvProfile::Stable => {}
+        SemconvProfile::Legacy => {
+            for kv in attributes.iter_mut() {
+                let key = match kv.key.as_str() {
+                    attribute::CODE_FILE_PATH => attribute::CODE_FILEPATH,
+                    attribute::CODE_LINE_NUMBER => attribute::CODE_LINENO,
+                    attribute::CODE_COLUMN_NUMBER => attribute::CODE_COLUMN,
+                    attribute::DB_SYSTEM_NAME => attribute::DB_SYSTEM,
+                    _ => continue,
+                };
+                kv.key = key.into();
+            }
+        }
+        SemconvProfile::ErrorAttributes => {
+            let error_type = attributes
+                .iter()
+                .position(|kv| kv.key.as_str() == attribute::ERROR_TYPE)
+                .map(|index| attributes.remove(index).value);
+            for kv in attributes.iter_mut() {
+                match kv.key.as_str() {
+                    attribute::EXCEPTION_TYPE => {
+                        kv.key = attribute::ERROR_TYPE.into();
+                        if let Some(error_type) = &error_type {
+                            kv.value = error_type.clone();
+                        }
+                    }
+                    attribute::EXCEPTION_MESSAGE => kv.key = ERROR_MESSAGE.into(),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// The `rootcause.forward_to_trace_ids` attribute listing the traces other than
+/// the one of `target` which reports in the tree of `rep` originated in, if any.
+pub(crate) fn forward_trace_ids(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    target: &SpanContext,
+) -> Option<KeyValue> {
+    forward_to(&origin_trace_ids(rep), target)
+}
+
+/// The traces which reports in the tree of `rep` originated in, in order of
+/// first occurrence.
+pub(crate) fn origin_trace_ids(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> Vec<TraceId> {
+    let mut trace_ids = Vec::new();
+    for ctx in rep
+        .iter_reports()
+        .filter_map(|r| crate::correlation::span_context(r.attachments()))
+        .filter(|ctx| ctx.is_valid())
+    {
+        if !trace_ids.contains(&ctx.trace_id()) {
+            trace_ids.push(ctx.trace_id());
+        }
+    }
+    trace_ids
+}
+
+/// The `rootcause.forward_to_trace_ids` attribute listing the `trace_ids` other
+/// than the one of `target`, if any.
+pub(crate) fn forward_to(trace_ids: &[TraceId], target: &SpanContext) -> Option<KeyValue> {
+    let trace_ids: Vec<StringValue> = trace_ids
+        .iter()
+        .filter(|&&trace_id| trace_id != target.trace_id())
+        .map(|trace_id| trace_id.to_string().into())
+        .collect();
+    (!trace_ids.is_empty())
+        .then(|| KeyValue::new(FORWARD_TO_TRACE_IDS, Value::Array(Array::String(trace_ids))))
+}
+
+/// The attributes of `rep`, or the brief ones if not `full`, as allowed by
+/// the `decision` of the [`ReportSampler`](crate::sampling::ReportSampler), or
+/// `None` if it is not to be emitted.
+pub(crate) fn sampled_attributes(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+    decision: Decision,
+    full: bool,
+) -> Option<Vec<KeyValue>> {
+    match decision {
+        Decision::Full if full => Some(attributes(rep, spec)),
+        Decision::Full | Decision::Brief => Some(attributes_brief(rep, spec)),
+        Decision::MetricsOnly | Decision::Drop => {
+            #[cfg(feature = "metrics")]
+            crate::meter::record_dropped(crate::meter::DropReason::Sampled);
+            None
+        }
+    }
+}
+
+/// The value of `attachment` as an attribute, if it is of the primitive type `kind`.
+fn typed_value(
+    kind: PrimitiveAttachment,
+    attachment: ReportAttachmentRef<'_, Dynamic>,
+) -> Option<Value> {
+    match kind {
+        PrimitiveAttachment::I64 => attachment.downcast_inner::<i64>().map(|&v| v.into()),
+        PrimitiveAttachment::F64 => attachment.downcast_inner::<f64>().map(|&v| v.into()),
+        PrimitiveAttachment::Bool => attachment.downcast_inner::<bool>().map(|&v| v.into()),
+        PrimitiveAttachment::Str => attachment
+            .downcast_inner::<&'static str>()
+            .map(|&v| v.into())
+            .or_else(|| {
+                attachment
+                    .downcast_inner::<String>()
+                    .map(|v| v.clone().into())
+            }),
+    }
+}
+
+/// Optional attributes enabled through the [`ExceptionEventSpec`].
+fn spec_attributes(
+    rep: ReportRef<'_, Dynamic, Uncloneable, Local>,
+    spec: &ExceptionEventSpec,
+    attributes: &mut Vec<KeyValue>,
+) {
+    if spec.attachment_attributes {
+        for sub_rep in rep.iter_reports() {
+            for kv in sub_rep.attachments().iter().flat_map(|a| {
+                a.downcast_inner::<KeyValue>()
+                    .map(std::slice::from_ref)
+                    .or_else(|| a.downcast_inner::<AttributeGroup>().map(|g| &g.0[..]))
+                    .unwrap_or_default()
+            }) {
+                if !attributes.iter().any(|existing| existing.key == kv.key) {
+                    attributes.push(kv.clone());
+                }
+            }
+            for kv in sub_rep
+                .attachments()
+                .iter()
+                .filter_map(crate::conversion::attributes_of)
+                .flatten()
+            {
+                if !attributes.iter().any(|existing| existing.key == kv.key) {
+                    attributes.push(kv);
+                }
+            }
+        }
+    }
+    for kv in crate::code::attributes(rep) {
+        if !attributes.iter().any(|existing| existing.key == kv.key) {
+            attributes.push(kv);
+        }
+    }
+    #[cfg(feature = "http")]
+    if let Some(kv) = crate::http::status_code_attribute(rep) {
+        if !attributes.iter().any(|existing| existing.key == kv.key) {
+            attributes.push(kv);
+        }
+    }
+    #[cfg(feature = "grpc")]
+    for kv in crate::grpc::attributes(rep, spec) {
+        if !attributes.iter().any(|existing| existing.key == kv.key) {
+            attributes.push(kv);
+        }
+    }
+    for (kind, key) in &spec.typed_attachments {
+        if attributes
+            .iter()
+            .any(|existing| existing.key.as_str() == key)
+        {
+            continue;
+        }
+        if let Some(value) = rep
+            .iter_reports()
+            .find_map(|r| r.attachments().iter().find_map(|a| typed_value(*kind, a)))
+        {
+            attributes.push(KeyValue::new(key.clone(), value));
+        }
+    }
+
+    if let Some(identity) = rep.find_attachment_inner::<crate::identity::ReportIdentity>() {
+        attributes.extend(identity.attributes());
+    }
+
+    if let Some(created) = rep.find_attachment_inner::<Instant>() {
+        let age = crate::clock::monotonic().saturating_duration_since(*created);
+        attributes.push(KeyValue::new(
+            EXCEPTION_REPORT_AGE_MS,
+            age.as_millis() as i64,
+        ));
+    }
+
+    #[cfg(feature = "regex")]
+    if let Some(category) = crate::classification::category(rep) {
+        attributes.push(KeyValue::new(
+            crate::classification::ERROR_CATEGORY,
+            category,
+        ));
+    }
+
+    if let Some(snapshot) = rep.iter_reports().find_map(|r| {
+        r.attachments()
+            .find_attachment_inner::<crate::thread::ThreadSnapshot>()
+    }) {
+        attributes.extend(snapshot.attributes());
+    }
+
+    #[cfg(feature = "tokio-metrics")]
+    if let Some(snapshot) = rep.iter_reports().find_map(|r| {
+        r.attachments()
+            .find_attachment_inner::<crate::runtime_metrics::RuntimeSnapshot>()
+    }) {
+        attributes.extend(snapshot.attributes());
+    }
+
+    #[cfg(feature = "process-metrics")]
+    if let Some(snapshot) = rep.iter_reports().find_map(|r| {
+        r.attachments()
+            .find_attachment_inner::<crate::process_metrics::ProcessSnapshot>()
+    }) {
+        attributes.extend(snapshot.attributes());
+    }
+
+    for key in &spec.env_allowlist {
+        if let Ok(value) = std::env::var(key.as_ref()) {
+            attributes.push(KeyValue::new(
+                format!("{PROCESS_ENVIRONMENT_VARIABLE}.{key}"),
+                value,
+            ));
+        }
+    }
+
+    if let Some(baggage) = rep
+        .iter_reports()
+        .find_map(|r| crate::correlation::baggage(r.attachments()))
+    {
+        for kv in baggage {
+            if !attributes.iter().any(|existing| existing.key == kv.key) {
+                attributes.push(kv);
+            }
+        }
+    }
+
+    if let Some(snapshot) = rep.iter_reports().find_map(|r| {
+        r.attachments()
+            .find_attachment_inner::<crate::resource::ResourceSnapshot>()
+    }) {
+        for kv in snapshot.0.iter() {
+            if !attributes.iter().any(|existing| existing.key == kv.key) {
+                attributes.push(kv.clone());
+            }
+        }
+    }
+
+    if spec.baggage_attributes {
+        for (key, (value, _)) in Context::current().baggage() {
+            if !attributes.iter().any(|existing| &existing.key == key) {
+                attributes.push(KeyValue::new(key.clone(), value.clone()));
+            }
+        }
+    }
+}
+
+/// Merge `from` into `into`, turning the values of keys present in both into a
+/// string array of their distinct values.
+pub(crate) fn merge_attributes(into: &mut Vec<KeyValue>, from: Vec<KeyValue>) {
+    for kv in from {
+        let Some(existing) = into.iter_mut().find(|existing| existing.key == kv.key) else {
+            into.push(kv);
+            continue;
+        };
+
+        let mut values = match &existing.value {
+            Value::Array(Array::String(values)) => values.clone(),
+            other => vec![StringValue::from(other.as_str().into_owned())],
+        };
+        let value = StringValue::from(kv.value.as_str().into_owned());
+        if !values.contains(&value) {
+            values.push(value);
+            existing.value = Value::Array(Array::String(values));
+        }
+    }
+}
+
+/// Run `format`, substituting `<formatting failed: type_name>` if it panics, so
+/// that one bad `Display` implementation cannot suppress a whole emission.
+///
+/// The contained panic is not recorded by the [panic hook](crate::panic::install_panic_hook).
+pub(crate) fn format_contained(type_name: &str, format: impl FnOnce() -> String) -> String {
+    without_panic_recording(|| panic::catch_unwind(AssertUnwindSafe(format)))
+        .unwrap_or_else(|_| format!("<formatting failed: {type_name}>"))
+}
+
+/// The report tree of `rep` as displayed by rootcause, or if a context or
+/// attachment panics while formatting, a plain indented rendering of the tree
+/// in which only the panicking entries are substituted as in [`format_contained`].
+pub(crate) fn format_report(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> String {
+    without_panic_recording(|| panic::catch_unwind(AssertUnwindSafe(|| rep.to_string())))
+        .unwrap_or_else(|_| {
+            let mut out = String::new();
+            format_entries(rep, 0, &mut out);
+            out
+        })
+}
+
+fn format_entries(rep: ReportRef<'_, Dynamic, Uncloneable, Local>, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let context = format_contained(rep.current_context_type_name(), || {
+        rep.format_current_context().to_string()
+    });
+    let _ = writeln!(out, "{indent}{context}");
+    for attachment in visible_attachments(rep) {
+        let value = format_contained(attachment.inner_type_name(), || {
+            attachment.format_inner().to_string()
+        });
+        let _ = writeln!(out, "{indent}- {value}");
+    }
+    for child in rep.children().iter() {
+        format_entries(child.into_uncloneable(), depth + 1, out);
+    }
+}
+
+/// Shorten `text` to at most `limit` bytes by replacing its middle
+/// with a `…[truncated N bytes]` marker, or by cutting its end if even the
+/// marker does not fit.
+pub(crate) fn truncate_middle(text: &mut String, limit: usize) {
+    if text.len() <= limit {
+        return;
+    }
+
+    let marker_len = truncation_marker(text.len()).len();
+    if marker_len > limit {
+        let mut end = limit;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        return;
+    }
+
+    let budget = limit - marker_len;
+    let mut head = budget / 2;
+    while !text.is_char_boundary(head) {
+        head -= 1;
+    }
+    let mut tail = text.len() - (budget - head);
+    while !text.is_char_boundary(tail) {
+        tail += 1;
+    }
+
+    let marker = truncation_marker(tail - head);
+    text.replace_range(head..tail, &marker);
+}
+
+fn truncation_marker(truncated: usize) -> String {
+    format!("…[truncated {truncated} bytes]")
+}
+
+/// Attachments of a single report which are not hidden by their handler or
+/// an [`AttachmentFormatterHook`](rootcause::hooks::attachment_formatter::AttachmentFormatterHook).
+pub(crate) fn visible_attachments<'r>(
+    rep: ReportRef<'r, Dynamic, Uncloneable, Local>,
+) -> impl Iterator<Item = ReportAttachmentRef<'r, Dynamic>> {
+    rep.attachments().iter().filter(|attachment| {
+        attachment
+            .preferred_formatting_style(rootcause::handlers::FormattingFunction::Display)
+            .placement
+            != rootcause::handlers::AttachmentFormattingPlacement::Hidden
+    })
+}
+
 pub(crate) fn timestamp(rep: ReportRef<'_, Dynamic, Uncloneable, Local>) -> SystemTime {
     rep.find_attachment_inner()
         .cloned()
-        .unwrap_or_else(SystemTime::now)
+        .unwrap_or_else(crate::clock::now)
 }
 
 pub(crate) trait AttachmentsExt {
@@ -99,3 +617,124 @@ impl<'a, C: 'static + ?Sized, T: 'static> AttachmentsExt for ReportMut<'a, C, T>
         self.attachments().find_attachment::<A>()
     }
 }
+
+/// Remove ANSI escape sequences, such as the colors added by formatting hooks, from `text`.
+#[cfg(feature = "logs")]
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters and intermediates up to a final byte in `@..=~`.
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, e.g. hyperlinks: up to BEL or ST (`ESC \`).
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.next_if_eq(&'\\').is_some() {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use rootcause::prelude::*;
+
+    use super::*;
+
+    struct Unprintable;
+
+    impl fmt::Display for Unprintable {
+        fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+            panic!("unprintable")
+        }
+    }
+
+    impl fmt::Debug for Unprintable {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(self, f)
+        }
+    }
+
+    #[test]
+    fn format_report_substitutes_only_the_panicking_attachment() {
+        let rep = report!("child failed")
+            .attach(Unprintable)
+            .attach("attempt 3")
+            .context("parent failed");
+        let text = format_report(rep.as_report_ref());
+        assert!(text.contains("parent failed"), "{text}");
+        assert!(text.contains("child failed"), "{text}");
+        assert!(text.contains("attempt 3"), "{text}");
+        assert!(text.contains("<formatting failed: "), "{text}");
+        assert!(text.contains("Unprintable>"), "{text}");
+    }
+
+    fn truncated(text: &str, limit: usize) -> String {
+        let mut text = text.to_owned();
+        truncate_middle(&mut text, limit);
+        assert!(text.len() <= limit, "{text:?} exceeds {limit} bytes");
+        text
+    }
+
+    #[test]
+    fn truncate_middle_keeps_short_text() {
+        assert_eq!(truncated("short", 5), "short");
+        assert_eq!(truncated("", 0), "");
+    }
+
+    #[test]
+    fn truncate_middle_replaces_the_middle() {
+        let text = format!("{}{}", "a".repeat(100), "b".repeat(100));
+        let result = truncated(&text, 60);
+        assert!(result.starts_with('a') && result.ends_with('b'));
+        let (_, marker) = result.split_once('…').expect("missing marker");
+        let removed: usize = marker
+            .trim_start_matches("[truncated ")
+            .split(' ')
+            .next()
+            .and_then(|count| count.parse().ok())
+            .expect("missing count");
+        assert_eq!(
+            result.len(),
+            text.len() - removed + truncation_marker(removed).len()
+        );
+    }
+
+    #[test]
+    fn truncate_middle_respects_char_boundaries() {
+        let text = "é🦀".repeat(50);
+        // Cutting inside a character would panic.
+        for limit in 0..text.len() {
+            truncated(&text, limit);
+        }
+    }
+
+    #[test]
+    fn truncate_middle_drops_the_marker_below_its_length() {
+        let text = "x".repeat(100);
+        for limit in 0..truncation_marker(text.len()).len() {
+            assert_eq!(truncated(&text, limit), "x".repeat(limit));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Counting of emitted exceptions by [`ErrorAggregator`], per window.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use opentelemetry::{
+    logs::{AnyValue, LoggerProvider},
+    trace::{Span, TraceContextExt, Tracer, TracerProvider},
+};
+use rootcause::Report;
+use rootcause_opentelemetry::{
+    AsReportRef,
+    aggregation::{AggregationWindow, EXCEPTION_SUMMARY_COUNT, ErrorAggregator},
+    clock::{ManualClock, install_clock, reset_clock},
+    error_type::register_error_type_with,
+    pipeline::{clear_layers, install_layer},
+    span_event::{SpanRefReportExt, SpanReportExt},
+    testing::providers::{TestProviders, test_providers},
+};
+
+/// The layers and clock are process-wide, so the tests run one at a time.
+static GLOBALS: Mutex<()> = Mutex::new(());
+
+#[derive(Debug)]
+struct DbTimeout;
+
+#[derive(Debug)]
+struct CacheMiss;
+
+impl fmt::Display for DbTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("query timed out")
+    }
+}
+
+impl fmt::Display for CacheMiss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cache missed")
+    }
+}
+
+/// Run `f` with `clock` and the built `aggregator` installed.
+fn with_aggregator(
+    clock: &ManualClock,
+    aggregator: impl FnOnce() -> ErrorAggregator,
+    f: impl FnOnce(&ErrorAggregator, &TestProviders),
+) {
+    let _guard = GLOBALS.lock().unwrap_or_else(|poison| poison.into_inner());
+    register_error_type_with::<DbTimeout>(|_| "db.timeout".into());
+    register_error_type_with::<CacheMiss>(|_| "cache.miss".into());
+    install_clock(clock.clone());
+    let aggregator = aggregator();
+    install_layer(aggregator.clone());
+
+    f(&aggregator, &test_providers());
+
+    clear_layers();
+    reset_clock();
+}
+
+/// Record `rep` as an `exception` event on a fresh span.
+fn record(providers: &TestProviders, rep: &impl AsReportRef) {
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span.record_error_report(rep).as_event();
+    span.end();
+}
+
+/// A sink collecting the flushed windows.
+fn collect() -> (
+    Arc<Mutex<Vec<AggregationWindow>>>,
+    impl Fn(&AggregationWindow) + Send + Sync + 'static,
+) {
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let sink = windows.clone();
+    (windows, move |window: &AggregationWindow| {
+        sink.lock().unwrap().push(window.clone());
+    })
+}
+
+fn counts(window: &AggregationWindow) -> Vec<(String, u64)> {
+    window
+        .summaries
+        .iter()
+        .map(|summary| (summary.error_type.to_string(), summary.count))
+        .collect()
+}
+
+#[test]
+fn counts_by_error_type_in_order_of_first_occurrence() {
+    let (windows, sink) = collect();
+    let clock = ManualClock::default();
+    with_aggregator(
+        &clock,
+        || ErrorAggregator::new(Duration::from_secs(60)).on_flush(sink),
+        |aggregator, providers| {
+            record(providers, &Report::new(DbTimeout));
+            record(providers, &Report::new(CacheMiss));
+            record(providers, &Report::new(DbTimeout));
+            clock.advance(Duration::from_secs(10));
+            aggregator.flush();
+
+            assert_eq!(providers.exception_events().len(), 3);
+        },
+    );
+
+    let windows = windows.lock().unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(
+        counts(&windows[0]),
+        [("db.timeout".to_owned(), 2), ("cache.miss".to_owned(), 1)]
+    );
+    assert_eq!(windows[0].duration(), Duration::from_secs(10));
+}
+
+#[test]
+fn elapsed_windows_are_flushed_by_the_next_emission() {
+    let (windows, sink) = collect();
+    let clock = ManualClock::default();
+    with_aggregator(
+        &clock,
+        || ErrorAggregator::new(Duration::from_secs(60)).on_flush(sink),
+        |aggregator, providers| {
+            record(providers, &Report::new(DbTimeout));
+            clock.advance(Duration::from_secs(61));
+            record(providers, &Report::new(CacheMiss));
+            assert_eq!(windows.lock().unwrap().len(), 1);
+
+            aggregator.flush();
+            // Empty windows are not handed to the sinks.
+            aggregator.flush();
+        },
+    );
+
+    let windows = windows.lock().unwrap();
+    assert_eq!(windows.len(), 2);
+    assert_eq!(counts(&windows[0]), [("db.timeout".to_owned(), 1)]);
+    assert_eq!(
+        windows[0].end,
+        SystemTime::UNIX_EPOCH + Duration::from_secs(61)
+    );
+    assert_eq!(counts(&windows[1]), [("cache.miss".to_owned(), 1)]);
+}
+
+#[test]
+fn quiet_windows_are_flushed_by_the_timer() {
+    let (windows, sink) = collect();
+    let clock = ManualClock::default();
+    with_aggregator(
+        &clock,
+        || ErrorAggregator::new(Duration::from_millis(20)).on_flush(sink),
+        |_, providers| {
+            record(providers, &Report::new(DbTimeout));
+            clock.advance(Duration::from_millis(20));
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while windows.lock().unwrap().is_empty() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(5));
+            }
+            assert_eq!(windows.lock().unwrap().len(), 1);
+        },
+    );
+
+    let windows = windows.lock().unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(counts(&windows[0]), [("db.timeout".to_owned(), 1)]);
+}
+
+#[test]
+fn the_current_window_is_flushed_when_the_last_clone_is_dropped() {
+    let (windows, sink) = collect();
+    let clock = ManualClock::default();
+    with_aggregator(
+        &clock,
+        || ErrorAggregator::new(Duration::from_secs(60)).on_flush(sink),
+        |_, providers| {
+            record(providers, &Report::new(CacheMiss));
+            assert!(windows.lock().unwrap().is_empty());
+        },
+    );
+
+    let windows = windows.lock().unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(counts(&windows[0]), [("cache.miss".to_owned(), 1)]);
+}
+
+#[test]
+fn suppressed_events_are_only_summarized() {
+    let providers = test_providers();
+    let logger = providers.logger_provider.logger("summaries");
+    let clock = ManualClock::default();
+    with_aggregator(
+        &clock,
+        || {
+            ErrorAggregator::new(Duration::from_secs(60))
+                .suppress_events()
+                .log_to(logger)
+        },
+        |aggregator, events| {
+            record(events, &Report::new(DbTimeout));
+            record(events, &Report::new(DbTimeout));
+            aggregator.flush();
+
+            assert!(events.exception_events().is_empty());
+        },
+    );
+
+    let records = providers.log_records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].body(),
+        Some(&AnyValue::from("2× db.timeout in last 0s"))
+    );
+    assert!(
+        records[0]
+            .attributes_iter()
+            .any(|(key, value)| key.as_str() == EXCEPTION_SUMMARY_COUNT
+                && value == &AnyValue::Int(2))
+    );
+}
+
+#[test]
+fn sample_trace_is_the_trace_of_the_current_span() {
+    let (windows, sink) = collect();
+    let clock = ManualClock::default();
+    let mut trace_id = None;
+    with_aggregator(
+        &clock,
+        || ErrorAggregator::new(Duration::from_secs(60)).on_flush(sink),
+        |aggregator, providers| {
+            providers
+                .tracer_provider
+                .tracer("test")
+                .in_span("operation", |cx| {
+                    trace_id = Some(cx.span().span_context().trace_id());
+                    let _ = cx
+                        .span()
+                        .record_error_report(&Report::new(DbTimeout))
+                        .as_event();
+                });
+            aggregator.flush();
+        },
+    );
+
+    let windows = windows.lock().unwrap();
+    assert_eq!(windows[0].summaries[0].sample_trace_id, trace_id);
+}
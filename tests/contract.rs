@@ -0,0 +1,264 @@
+//! Machine-readable contract of the telemetry emitted by each emission path.
+//!
+//! Every emission path is run against the fixtures below, and the keys and
+//! types of the attributes they produce are described in
+//! `contract/emission-contract.json`, which is published with the crate so that
+//! collector transforms can be validated against it. An attribute is optional
+//! if some fixture does not produce it. Log paths also list the types of their
+//! record bodies.
+//!
+//! The test fails when the emitted telemetry no longer matches the committed
+//! contract. Run it with `UPDATE_CONTRACT=1` to regenerate the contract after
+//! an intentional change, and commit the result.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use opentelemetry::{
+    KeyValue, Value,
+    logs::{AnyValue, LoggerProvider},
+    trace::{TraceContextExt, Tracer, TracerProvider},
+};
+use opentelemetry_sdk::{
+    logs::{InMemoryLogExporter, SdkLogger, SdkLoggerProvider},
+    trace::{InMemorySpanExporter, SdkTracerProvider},
+};
+use rootcause::prelude::*;
+use rootcause_opentelemetry::{
+    AsReportRef,
+    attachments::AttributeReportExt,
+    log_event::{LoggerExt, ReportLogExt},
+    span_event::SpanRefReportExt,
+    spec::MessageLines,
+    traceparent::TraceParentReportExt,
+};
+use serde_json::{Value as Json, json};
+
+const CONTRACT_VERSION: u32 = 1;
+
+const REMOTE_PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn fixtures() -> Vec<Box<dyn AsReportRef>> {
+    vec![
+        Box::new(report!("connection refused")),
+        Box::new(report!("upstream timed out").attach_attribute("http.route", "/orders")),
+        Box::new(report!("first line\nsecond line")),
+        Box::new(
+            report!("batch item failed")
+                .attach_remote_span_context(REMOTE_PARENT, "")
+                .context("batch failed"),
+        ),
+    ]
+}
+
+/// Attribute types by key, for each emission of one path.
+type Observations = Vec<BTreeMap<String, &'static str>>;
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::I64(_) => "int",
+        Value::F64(_) => "double",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        _ => "other",
+    }
+}
+
+fn any_value_type(value: &AnyValue) -> &'static str {
+    match value {
+        AnyValue::Boolean(_) => "bool",
+        AnyValue::Int(_) => "int",
+        AnyValue::Double(_) => "double",
+        AnyValue::String(_) => "string",
+        AnyValue::Bytes(_) => "bytes",
+        AnyValue::ListAny(_) => "array",
+        AnyValue::Map(_) => "map",
+        _ => "other",
+    }
+}
+
+fn observe(attributes: &[KeyValue]) -> BTreeMap<String, &'static str> {
+    attributes
+        .iter()
+        .map(|kv| (kv.key.to_string(), value_type(&kv.value)))
+        .collect()
+}
+
+/// Run the span emission paths on each fixture, returning the observations of
+/// each path by its name in the contract.
+fn span_paths() -> BTreeMap<String, Observations> {
+    let mut paths: BTreeMap<String, Observations> = BTreeMap::new();
+    for fixture in fixtures() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let tracer = provider.tracer("contract");
+
+        tracer.in_span("span_event", |cx| {
+            let _ = cx.span().record_error_report(&fixture).as_event();
+        });
+        tracer.in_span("span_event_brief", |cx| {
+            let _ = cx.span().record_error_report(&fixture).as_event_brief();
+        });
+        tracer.in_span("span_attributes", |cx| {
+            let _ = cx
+                .span()
+                .record_error_report(&fixture)
+                .message_lines(MessageLines::Overflow)
+                .on_span_attributes()
+                .with_error_status();
+        });
+        tracer.in_span("span_link", |cx| {
+            let _ = cx
+                .span()
+                .record_error_report(&fixture)
+                .link_child_report_spans();
+        });
+        tracer.in_span("origin_span", |cx| {
+            let _ = cx
+                .span()
+                .record_error_report(&fixture)
+                .as_events_on_origin_spans(&tracer);
+        });
+
+        let _ = provider.force_flush();
+        for span in exporter.get_finished_spans().unwrap_or_default() {
+            let observations: Observations = match &*span.name {
+                "span_event" | "span_event_brief" | "exception" => {
+                    span.events.iter().map(|e| observe(&e.attributes)).collect()
+                }
+                "span_attributes" => vec![observe(&span.attributes)],
+                "span_link" => span.links.iter().map(|l| observe(&l.attributes)).collect(),
+                _ => continue,
+            };
+            // `exception` spans are the children started by `as_events_on_origin_spans`.
+            let path = match &*span.name {
+                "exception" => "origin_span_event".to_owned(),
+                name => name.to_owned(),
+            };
+            paths.entry(path).or_default().extend(observations);
+        }
+        let _ = provider.shutdown();
+    }
+    paths
+}
+
+/// Run a log emission path on each fixture, returning the observations of the
+/// records and the types of their bodies.
+fn log_path(emit: impl Fn(&SdkLogger, &dyn AsReportRef)) -> (Observations, BTreeSet<&'static str>) {
+    let (mut records, mut bodies) = (Vec::new(), BTreeSet::new());
+    for fixture in fixtures() {
+        let exporter = InMemoryLogExporter::default();
+        let provider = SdkLoggerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        emit(&provider.logger("contract"), &*fixture);
+
+        let _ = provider.force_flush();
+        for log in exporter.get_emitted_logs().unwrap_or_default() {
+            records.push(
+                log.record
+                    .attributes_iter()
+                    .map(|(key, value)| (key.to_string(), any_value_type(value)))
+                    .collect(),
+            );
+            bodies.insert(log.record.body().map_or("none", any_value_type));
+        }
+        let _ = provider.shutdown();
+    }
+    (records, bodies)
+}
+
+/// Describe the attributes of a path: their type, and whether every emission had them.
+fn describe(name: Option<&str>, observations: &Observations) -> Json {
+    assert!(
+        !observations.is_empty(),
+        "path {name:?} emitted nothing for the fixtures"
+    );
+    let mut attributes: BTreeMap<&str, (&str, usize)> = BTreeMap::new();
+    for emission in observations {
+        for (key, ty) in emission {
+            attributes.entry(key.as_str()).or_insert((*ty, 0)).1 += 1;
+        }
+    }
+    let attributes: serde_json::Map<String, Json> = attributes
+        .into_iter()
+        .map(|(key, (ty, count))| {
+            (
+                key.to_owned(),
+                json!({ "type": ty, "required": count == observations.len() }),
+            )
+        })
+        .collect();
+    json!({ "name": name, "attributes": attributes })
+}
+
+/// Describe a span emission path, whose events are named `exception`.
+fn describe_span(path: &str, spans: &BTreeMap<String, Observations>) -> Json {
+    let name = match path {
+        "span_attributes" | "span_link" => None,
+        _ => Some("exception"),
+    };
+    describe(name, spans.get(path).unwrap_or(&Vec::new()))
+}
+
+/// Describe a log emission path, adding the types of the record bodies,
+/// `none` for a record without one.
+fn describe_log((records, bodies): (Observations, BTreeSet<&'static str>)) -> Json {
+    let mut description = describe(Some("exception"), &records);
+    description["body"] = json!(bodies);
+    description
+}
+
+#[test]
+fn emission_contract() {
+    let spans = span_paths();
+    let span_path = |path| describe_span(path, &spans);
+
+    let contract = json!({
+        "contract_version": CONTRACT_VERSION,
+        "paths": {
+            "span_event": span_path("span_event"),
+            "span_event_brief": span_path("span_event_brief"),
+            "span_attributes": span_path("span_attributes"),
+            "span_link": span_path("span_link"),
+            "origin_span_event": span_path("origin_span_event"),
+            "log_record": describe_log(log_path(|logger, fixture| {
+                logger.emit_error_report(&fixture)
+            })),
+            "log_record_structured": describe_log(log_path(|logger, fixture| {
+                logger.emit_error_report_structured(&fixture)
+            })),
+            "log_record_granular": describe_log(log_path(|logger, fixture| {
+                logger.emit_error_report_granular(&fixture)
+            })),
+            "log_record_group_siblings": describe_log(log_path(|logger, fixture| {
+                fixture.otel_log(logger).group_siblings().emit()
+            })),
+            "as_log_event": describe_log(log_path(|logger, fixture| {
+                let provider = SdkTracerProvider::builder().build();
+                provider.tracer("contract").in_span("as_log_event", |cx| {
+                    let _ = cx.span().record_error_report(&fixture).as_log_event(logger);
+                });
+                let _ = provider.shutdown();
+            })),
+        },
+    });
+    let rendered = serde_json::to_string_pretty(&contract).unwrap() + "\n";
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contract/emission-contract.json");
+    if std::env::var_os("UPDATE_CONTRACT").is_some() {
+        std::fs::write(&path, rendered).unwrap();
+        return;
+    }
+    let published = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        published == rendered,
+        "emitted telemetry no longer matches {}, rerun with UPDATE_CONTRACT=1 if intended:\n{rendered}",
+        path.display(),
+    );
+}
@@ -0,0 +1,51 @@
+//! Emission of deferred reports on the span current when finalized.
+
+use opentelemetry::{
+    Context,
+    trace::{FutureExt, TraceContextExt, Tracer, TracerProvider},
+};
+use rootcause::prelude::*;
+use rootcause_opentelemetry::{
+    deferred::defer_emit,
+    testing::{EventMatcher, assert_event_matches, eq, providers::test_providers},
+};
+
+#[tokio::test]
+async fn deferred_reports_are_recorded_on_the_span_current_when_finalized() {
+    let providers = test_providers();
+    let tracer = providers.tracer_provider.tracer("test");
+
+    let deferred = {
+        let rep = report!("upstream unavailable");
+        defer_emit(&rep)
+    };
+    let cx = Context::current_with_span(tracer.start("handler"));
+    tokio::spawn(deferred.finalize().with_context(cx.clone()))
+        .await
+        .unwrap();
+    cx.span().end();
+
+    let spans = providers.finished_spans();
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].name, "handler");
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception().attr("exception.message", eq("upstream unavailable")),
+    );
+}
+
+#[tokio::test]
+async fn nothing_is_recorded_until_finalized() {
+    let providers = test_providers();
+    let tracer = providers.tracer_provider.tracer("test");
+
+    let cx = Context::current_with_span(tracer.start("handler"));
+    let finalize = defer_emit(&report!("upstream unavailable")).finalize();
+    cx.span().end();
+    drop(finalize);
+
+    assert_eq!(providers.finished_spans().len(), 1);
+    assert!(providers.exception_events().is_empty());
+}
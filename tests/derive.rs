@@ -0,0 +1,131 @@
+//! Implementations generated by `#[derive(OtelError)]`, and their effect on
+//! emitted exception events once registered.
+
+use std::fmt::{self, Debug, Display};
+
+use opentelemetry::{
+    KeyValue,
+    logs::Severity,
+    trace::{Event, Span, Tracer, TracerProvider},
+};
+use rootcause::Report;
+use rootcause_opentelemetry::{
+    AsReportRef, OtelError,
+    error_type::{OtelErrorType, register_otel_error},
+    severity::OtelSeverity,
+    span_event::SpanReportExt,
+    testing::{EventMatcher, absent, assert_event_matches, eq, providers::test_providers},
+};
+
+#[derive(Debug, OtelError)]
+#[otel(error_type = "db.timeout", severity = "warn")]
+#[otel(attribute(key = "db.system", value = "postgresql"))]
+#[otel(attribute(key = "db.retries", value = 3))]
+#[otel(attribute(key = "db.backoff", value = 1.5))]
+#[otel(attribute(key = "db.idempotent", value = true))]
+struct QueryTimeout;
+
+#[derive(Debug, OtelError)]
+#[otel(error_type = "payment.rejected", severity = "ERROR")]
+enum PaymentError {
+    Declined,
+    Expired { months: u32 },
+}
+
+#[derive(Debug, OtelError)]
+#[otel(error_type = "wrapped")]
+struct Wrapped<T: Debug + 'static>(T);
+
+#[derive(Debug, OtelError)]
+#[otel(error_type = "never.registered")]
+struct NeverRegistered;
+
+impl Display for QueryTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("query timed out")
+    }
+}
+
+impl Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Declined => f.write_str("payment declined"),
+            Self::Expired { months } => write!(f, "card expired {months} months ago"),
+        }
+    }
+}
+
+impl<T: Debug + Display> Display for Wrapped<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wrapped: {}", self.0)
+    }
+}
+
+impl Display for NeverRegistered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("never registered")
+    }
+}
+
+/// The `exception` event recorded for `rep` on a fresh span.
+fn exception_event(rep: &impl AsReportRef) -> Event {
+    let providers = test_providers();
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span.record_error_report(rep).as_event();
+    span.end();
+    let mut spans = providers.finished_spans();
+    spans.remove(0).events.events.remove(0)
+}
+
+#[test]
+fn struct_with_attributes() {
+    assert_eq!(QueryTimeout.otel_error_type(), "db.timeout");
+    assert_eq!(
+        QueryTimeout.otel_attributes(),
+        [
+            KeyValue::new("db.system", "postgresql"),
+            KeyValue::new("db.retries", 3_i64),
+            KeyValue::new("db.backoff", 1.5),
+            KeyValue::new("db.idempotent", true),
+        ]
+    );
+    assert_eq!(QueryTimeout.otel_severity(), Severity::Warn);
+}
+
+#[test]
+fn enum_variants_share_the_error_type() {
+    for error in [PaymentError::Declined, PaymentError::Expired { months: 2 }] {
+        assert_eq!(error.otel_error_type(), "payment.rejected");
+        assert!(error.otel_attributes().is_empty());
+        assert_eq!(error.otel_severity(), Severity::Error);
+    }
+}
+
+#[test]
+fn generic_types() {
+    assert_eq!(Wrapped(0_u8).otel_error_type(), "wrapped");
+    assert_eq!(Wrapped("text").otel_error_type(), "wrapped");
+}
+
+#[test]
+fn registered_types_are_emitted() {
+    register_otel_error::<QueryTimeout>();
+
+    let event = exception_event(&Report::new(QueryTimeout));
+    assert_event_matches(
+        &event,
+        &EventMatcher::exception()
+            .attr("error.type", eq("db.timeout"))
+            .attr("db.system", eq("postgresql"))
+            .attr("db.retries", eq(3_i64)),
+    );
+}
+
+#[test]
+fn unregistered_types_are_not_emitted() {
+    let event = exception_event(&Report::new(NeverRegistered));
+    assert_event_matches(
+        &event,
+        &EventMatcher::exception().attr("error.type", absent()),
+    );
+}
@@ -0,0 +1,88 @@
+//! Counting of reports which were created but not yet emitted, installed as a
+//! rootcause hook, which can only be installed once per process.
+
+use opentelemetry::{
+    metrics::MeterProvider,
+    trace::{Span, Tracer, TracerProvider},
+};
+use opentelemetry_sdk::metrics::{
+    InMemoryMetricExporter, SdkMeterProvider,
+    data::{AggregatedMetrics, MetricData},
+};
+use rootcause::{hooks::Hooks, prelude::*};
+use rootcause_opentelemetry::{
+    outstanding::{OutstandingReportCollector, REPORTS_DISCARDED, REPORTS_OUTSTANDING},
+    span_event::SpanReportExt,
+    testing::providers::test_providers,
+};
+
+/// The latest value of the sum named `name`, or 0 if nothing was recorded on it.
+fn sum(provider: &SdkMeterProvider, exporter: &InMemoryMetricExporter, name: &str) -> i64 {
+    provider.force_flush().unwrap();
+    let metrics = exporter.get_finished_metrics().unwrap();
+    let metric = metrics
+        .iter()
+        .rev()
+        .flat_map(|resource| resource.scope_metrics())
+        .flat_map(|scope| scope.metrics())
+        .find(|metric| metric.name() == name);
+    match metric.map(|metric| metric.data()) {
+        None => 0,
+        Some(AggregatedMetrics::I64(MetricData::Sum(sum))) => {
+            sum.data_points().map(|point| point.value()).sum()
+        }
+        Some(AggregatedMetrics::U64(MetricData::Sum(sum))) => {
+            sum.data_points().map(|point| point.value() as i64).sum()
+        }
+        Some(_) => panic!("{name} is not a sum"),
+    }
+}
+
+#[test]
+fn reports_are_outstanding_until_emitted_or_dropped() {
+    let exporter = InMemoryMetricExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    Hooks::new()
+        .report_creation_hook(OutstandingReportCollector::new(
+            &meter_provider.meter("test"),
+        ))
+        .install()
+        .unwrap();
+    let counts = || {
+        (
+            sum(&meter_provider, &exporter, REPORTS_OUTSTANDING),
+            sum(&meter_provider, &exporter, REPORTS_DISCARDED),
+        )
+    };
+
+    let emitted = report!("emitted");
+    let discarded = report!("discarded");
+    let pending = report!("pending");
+    assert_eq!(counts(), (3, 0));
+
+    let providers = test_providers();
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span.record_error_report(&emitted).as_event();
+    span.end();
+    assert_eq!(counts(), (2, 0));
+
+    // Emitting a report again, or dropping it once emitted, counts nothing.
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span.record_error_report(&emitted).as_event();
+    span.end();
+    drop(emitted);
+    assert_eq!(counts(), (2, 0));
+
+    drop(discarded);
+    assert_eq!(counts(), (1, 1));
+
+    // The children of a report are settled with it.
+    let parent = pending.context("parent");
+    assert_eq!(counts(), (2, 1));
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span.record_error_report(&parent).as_event();
+    span.end();
+    assert_eq!(counts(), (0, 1));
+}
@@ -0,0 +1,73 @@
+//! Recording of panics by the hook of [`install_panic_hook`].
+
+use std::{fmt, panic};
+
+use opentelemetry::trace::{TraceContextExt, Tracer, TracerProvider};
+use rootcause::prelude::*;
+use rootcause_opentelemetry::{
+    panic::{PANIC_ERROR_TYPE, install_panic_hook},
+    span_event::SpanRefReportExt,
+    testing::{EventMatcher, assert_event_matches, contains, eq, providers::test_providers},
+};
+
+struct Unprintable;
+
+impl fmt::Display for Unprintable {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        panic!("unprintable")
+    }
+}
+
+impl fmt::Debug for Unprintable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[test]
+fn panics_are_recorded_once_on_the_current_span() {
+    install_panic_hook();
+    install_panic_hook();
+
+    let providers = test_providers();
+    providers
+        .tracer_provider
+        .tracer("test")
+        .in_span("operation", |_| {
+            let _ = panic::catch_unwind(|| panic!("boom"));
+        });
+
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception()
+            .attr("error.type", eq(PANIC_ERROR_TYPE))
+            .attr("exception.escaped", eq(true))
+            .attr("exception.message", contains("boom"))
+            .attr("code.file.path", contains("panic.rs")),
+    );
+}
+
+#[test]
+fn contained_formatting_panics_are_not_recorded() {
+    install_panic_hook();
+
+    let providers = test_providers();
+    providers
+        .tracer_provider
+        .tracer("test")
+        .in_span("operation", |cx| {
+            let _ = cx
+                .span()
+                .record_error_report(&report!("connection refused").attach(Unprintable))
+                .as_event();
+        });
+
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception().attr("exception.message", eq("connection refused")),
+    );
+}
@@ -0,0 +1,88 @@
+//! Rewriting of every output derived from a report by the [`EmitLayer`]s, so
+//! that a redacting layer leaves nothing behind.
+
+use std::{
+    ops::ControlFlow,
+    sync::{Arc, Mutex},
+};
+
+use opentelemetry::{
+    KeyValue,
+    logs::{AnyValue, LoggerProvider},
+    trace::Status,
+};
+use rootcause::prelude::*;
+use rootcause_opentelemetry::{
+    cbor::{EXCEPTION_REPORT_CBOR, ReportTree},
+    log_event::ReportLogExt,
+    pipeline::{EmitLayer, ExceptionSnapshot, clear_layers, install_layer},
+    span_event::SpanReportExt,
+    spec::ExceptionEventSpec,
+    testing::{capturing::CapturingSpan, providers::test_providers},
+};
+
+const REDACTED: &str = "[redacted]";
+
+/// A layer replacing the message of the report in every output, keeping the
+/// last report tree it rewrote in `tree`.
+fn redact(tree: Arc<Mutex<Option<ReportTree>>>) -> impl EmitLayer {
+    move |snapshot: &mut ExceptionSnapshot<'_>| {
+        for key in ["exception.message", "exception.stacktrace"] {
+            if snapshot.attribute(key).is_some() {
+                snapshot.set_attribute(KeyValue::new(key, REDACTED));
+            }
+        }
+        if snapshot.description.is_some() {
+            snapshot.description = Some(REDACTED.into());
+        }
+        if snapshot.body.is_some() {
+            snapshot.body = Some(AnyValue::from(REDACTED));
+        }
+        if let Some(report_tree) = &mut snapshot.report_tree {
+            report_tree.message = REDACTED.to_owned();
+            *tree.lock().unwrap() = Some(report_tree.clone());
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[test]
+fn layers_rewrite_the_status_body_and_report_tree() {
+    let tree = Arc::new(Mutex::new(None));
+    install_layer(redact(tree.clone()));
+    let spec = ExceptionEventSpec::default().report_cbor(true);
+    let rep = report!("password=hunter2");
+
+    let mut span = CapturingSpan::new();
+    let _ = span
+        .record_error_report(&rep)
+        .with_spec(spec.clone())
+        .as_event()
+        .with_error_status();
+    assert_eq!(span.status(), Some(&Status::error(REDACTED)));
+    let event = span.exception_events().next().unwrap();
+    let cbor = event
+        .attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == EXCEPTION_REPORT_CBOR)
+        .unwrap();
+    assert_eq!(
+        cbor.value.to_string(),
+        tree.lock().unwrap().as_ref().unwrap().encode_base64()
+    );
+
+    let providers = test_providers();
+    let logger = providers.logger_provider.logger("test");
+    rep.otel_log(&logger)
+        .with_spec(spec)
+        .structured_body()
+        .emit();
+    let records = providers.log_records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].body(), Some(&AnyValue::from(REDACTED)));
+    assert!(records[0].attributes_iter().all(|(_, value)| {
+        !matches!(value, AnyValue::String(text) if text.as_str().contains("hunter2"))
+    }));
+
+    clear_layers();
+}
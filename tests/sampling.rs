@@ -0,0 +1,133 @@
+//! Emission of reports as decided by the installed [`ReportSampler`](rootcause_opentelemetry::sampling::ReportSampler).
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use opentelemetry::{
+    logs::LoggerProvider,
+    trace::{Span, Tracer, TracerProvider},
+};
+use rootcause::{
+    ReportRef,
+    markers::{Dynamic, Local, Uncloneable},
+    prelude::*,
+};
+use rootcause_opentelemetry::{
+    AsReportRef,
+    log_event::LoggerExt,
+    sampling::{Decision, clear_sampler, install_sampler},
+    span_event::SpanReportExt,
+    testing::{
+        EventMatcher, absent, assert_event_matches, present,
+        providers::{TestProviders, test_providers},
+    },
+};
+
+/// The sampler is process-wide, so the tests installing one run one at a time.
+static SAMPLER: Mutex<()> = Mutex::new(());
+
+/// Emit `rep` as a span event and as a structured log record, with `sampler`
+/// installed.
+fn emit_sampled(
+    sampler: impl Fn(ReportRef<'_, Dynamic, Uncloneable, Local>) -> Decision + Send + Sync + 'static,
+    rep: &impl AsReportRef,
+) -> TestProviders {
+    let _guard = SAMPLER.lock().unwrap_or_else(|poison| poison.into_inner());
+    install_sampler(sampler);
+
+    let providers = test_providers();
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span.record_error_report(rep).as_event().with_error_status();
+    span.end();
+    providers
+        .logger_provider
+        .logger("test")
+        .emit_error_report_structured(rep);
+
+    clear_sampler();
+    providers
+}
+
+#[test]
+fn full_emits_everything() {
+    let providers = emit_sampled(|_| Decision::Full, &report!("connection refused"));
+
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception().attr("exception.stacktrace", present()),
+    );
+    let records = providers.log_records();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].body().is_some());
+}
+
+#[test]
+fn brief_omits_stacktrace_and_body() {
+    let providers = emit_sampled(|_| Decision::Brief, &report!("connection refused"));
+
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception()
+            .attr("exception.message", present())
+            .attr("exception.stacktrace", absent()),
+    );
+    let records = providers.log_records();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].body().is_none());
+}
+
+#[test]
+fn metrics_only_and_drop_emit_no_events_or_records() {
+    for decision in [Decision::MetricsOnly, Decision::Drop] {
+        let providers = emit_sampled(move |_| decision, &report!("connection refused"));
+
+        assert!(providers.exception_events().is_empty(), "{decision:?}");
+        assert!(providers.log_records().is_empty(), "{decision:?}");
+    }
+}
+
+#[test]
+fn sampler_decides_by_content() {
+    let sampler = |rep: ReportRef<'_, Dynamic, Uncloneable, Local>| {
+        if rep.format_current_context().to_string().contains("health") {
+            Decision::Drop
+        } else {
+            Decision::Full
+        }
+    };
+
+    let providers = emit_sampled(sampler, &report!("health check not found"));
+    assert!(providers.exception_events().is_empty());
+
+    let providers = emit_sampled(sampler, &report!("order not found"));
+    assert_eq!(providers.exception_events().len(), 1);
+}
+
+#[test]
+fn sampler_is_consulted_once_per_builder() {
+    let _guard = SAMPLER.lock().unwrap_or_else(|poison| poison.into_inner());
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    install_sampler(move |_: ReportRef<'_, Dynamic, Uncloneable, Local>| {
+        counted.fetch_add(1, Ordering::Relaxed);
+        Decision::Full
+    });
+
+    let providers = test_providers();
+    let mut span = providers.tracer_provider.tracer("test").start("operation");
+    let _ = span
+        .record_error_report(&report!("connection refused"))
+        .as_event()
+        .on_span_attributes()
+        .link_child_report_spans();
+    span.end();
+    clear_sampler();
+
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,51 @@
+//! Installation of the global providers by [`TelemetrySetup`], which can only
+//! be observed once per process.
+
+use std::env;
+
+use opentelemetry::{
+    global,
+    trace::{TraceContextExt, Tracer},
+};
+use opentelemetry_sdk::trace::InMemorySpanExporter;
+use rootcause::prelude::*;
+use rootcause_opentelemetry::{setup::TelemetrySetup, span_event::SpanRefReportExt};
+
+#[test]
+fn install_sets_the_global_providers_unless_the_sdk_is_disabled() {
+    let exporter = InMemorySpanExporter::default();
+
+    // SAFETY: this is the only test of this file, so no other thread reads
+    // the environment.
+    unsafe { env::set_var("OTEL_SDK_DISABLED", "true") };
+    let guard = TelemetrySetup::from_env()
+        .with_simple_span_exporter(exporter.clone())
+        .install()
+        .unwrap();
+    unsafe { env::remove_var("OTEL_SDK_DISABLED") };
+
+    global::tracer("test").in_span("disabled", |cx| {
+        assert!(!cx.span().is_recording());
+    });
+    guard.shutdown().unwrap();
+    assert!(exporter.get_finished_spans().unwrap().is_empty());
+
+    let guard = TelemetrySetup::new("checkout")
+        .with_simple_span_exporter(exporter.clone())
+        .install()
+        .unwrap();
+    global::tracer("test").in_span("enabled", |cx| {
+        assert!(cx.span().is_recording());
+        let _ = cx
+            .span()
+            .record_error_report(&report!("payment declined"))
+            .as_event();
+    });
+    guard.force_flush().unwrap();
+
+    let spans = exporter.get_finished_spans().unwrap();
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].name, "enabled");
+    assert_eq!(spans[0].events.len(), 1);
+    guard.shutdown().unwrap();
+}
@@ -0,0 +1,85 @@
+//! Recording of task failures on the span which spawned the task.
+
+use std::hint::black_box;
+
+use opentelemetry::{
+    Context,
+    trace::{TraceContextExt, Tracer, TracerProvider},
+};
+use rootcause::prelude::*;
+use rootcause_opentelemetry::{
+    panic::PANIC_ERROR_TYPE,
+    task::spawn_traced,
+    testing::{
+        EventMatcher, assert_event_matches, contains, eq,
+        providers::{TestProviders, test_providers},
+    },
+};
+
+/// A context with a new span from `providers`, for spawning tasks in.
+fn spawning_context(providers: &TestProviders) -> Context {
+    Context::current_with_span(providers.tracer_provider.tracer("test").start("spawner"))
+}
+
+#[tokio::test]
+async fn errors_are_recorded_on_the_spawning_span() {
+    let providers = test_providers();
+    let cx = spawning_context(&providers);
+
+    let handle = {
+        let _guard = cx.clone().attach();
+        spawn_traced(async { Err::<(), Report>(report!("cache refresh failed")) })
+    };
+    assert!(handle.await.unwrap().is_err());
+    cx.span().end();
+
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception().attr("exception.message", eq("cache refresh failed")),
+    );
+}
+
+#[tokio::test]
+async fn successes_are_not_recorded() {
+    let providers = test_providers();
+    let cx = spawning_context(&providers);
+
+    let handle = {
+        let _guard = cx.clone().attach();
+        spawn_traced(async { Ok::<_, Report>(42) })
+    };
+    assert_eq!(handle.await.unwrap().unwrap(), 42);
+    cx.span().end();
+
+    assert!(providers.exception_events().is_empty());
+}
+
+#[tokio::test]
+async fn panics_are_recorded_and_resumed() {
+    let providers = test_providers();
+    let cx = spawning_context(&providers);
+
+    let handle = {
+        let _guard = cx.clone().attach();
+        spawn_traced(async {
+            if black_box(true) {
+                panic!("task crashed");
+            }
+            Ok::<(), Report>(())
+        })
+    };
+    assert!(handle.await.unwrap_err().is_panic());
+    cx.span().end();
+
+    let events = providers.exception_events();
+    assert_eq!(events.len(), 1);
+    assert_event_matches(
+        &events[0],
+        &EventMatcher::exception()
+            .attr("error.type", eq(PANIC_ERROR_TYPE))
+            .attr("exception.escaped", eq(true))
+            .attr("exception.message", contains("task crashed")),
+    );
+}
@@ -0,0 +1,44 @@
+//! Linking to the span a report received from elsewhere originated in, with
+//! the span context collector installed, which can only be installed once per
+//! process.
+
+use opentelemetry::trace::{TraceContextExt, TraceId, Tracer, TracerProvider};
+use rootcause::{hooks::Hooks, prelude::*};
+use rootcause_opentelemetry::{
+    attachments::OpenTelemetryMetadataCollector, span_event::SpanRefReportExt,
+    testing::providers::test_providers, traceparent::TraceParentReportExt,
+};
+
+const REMOTE_PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+#[test]
+fn remote_span_contexts_take_precedence_over_the_receiving_span() {
+    Hooks::new()
+        .report_creation_hook(OpenTelemetryMetadataCollector::new())
+        .install()
+        .unwrap();
+
+    let providers = test_providers();
+    providers
+        .tracer_provider
+        .tracer("test")
+        .in_span("receiver", |cx| {
+            let rep = report!("upstream failed")
+                .attach_remote_span_context(REMOTE_PARENT, "")
+                .context("handling failed");
+            let _ = cx
+                .span()
+                .record_error_report(&rep)
+                .link_child_report_spans();
+        });
+
+    let spans = providers.finished_spans();
+    assert_eq!(spans.len(), 1);
+    let links = &spans[0].links.links;
+    assert_eq!(links.len(), 1);
+    assert_eq!(
+        links[0].span_context.trace_id(),
+        TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+    );
+    assert!(links[0].span_context.is_remote());
+}